@@ -0,0 +1,251 @@
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand, ValueEnum};
+use mfm_core::{
+    config::{
+        loader::{ConfigError, ConfigLoader},
+        schema,
+    },
+    contexts::{ConfigFormat, ConfigSource},
+};
+
+use crate::ExitCode;
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Check a config file, printing each problem with its line number
+    Check(CheckArgs),
+    /// Print the JSON Schema of the config files
+    Schema(SchemaArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    /// Path to the config file
+    pub path: PathBuf,
+    /// Format of the config file, guessed from its extension when not set
+    #[arg(long, value_enum)]
+    pub format: Option<Format>,
+    /// Profile of the config file to apply
+    #[arg(long)]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    /// Print the schema of a single authentication method instead
+    #[arg(long)]
+    pub auth_method: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Format {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl From<Format> for ConfigFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Toml => Self::Toml,
+            Format::Yaml => Self::Yaml,
+            Format::Json => Self::Json,
+        }
+    }
+}
+
+/// Checks a config file against the JSON Schema of the config, then validates it once
+/// it fits, returning one `file:line:column: message` line per problem; the position
+/// is left out when it isn't known.
+pub fn check(loader: &ConfigLoader, args: &CheckArgs) -> Result<(), Vec<String>> {
+    let origin = args.path.display().to_string();
+
+    let format = args
+        .format
+        .map(ConfigFormat::from)
+        .or_else(|| ConfigFormat::from_path(&args.path))
+        .ok_or_else(|| {
+            vec![format!(
+                "{}: unknown format; use a .toml, .yaml, .yml or .json extension, or --format",
+                origin
+            )]
+        })?;
+
+    let mut source = ConfigSource::file(&origin, format);
+    if let Some(profile) = &args.profile {
+        source = ConfigSource::Profile(profile.clone(), Box::new(source));
+    }
+
+    let load_error = |e| match e {
+        ConfigError::Parse(e) => vec![e.to_string()],
+        ConfigError::Io(path, e) => vec![format!("{}: {}", path.display(), e)],
+    };
+    let value = loader.load_value(&source).map_err(load_error)?;
+
+    // the schema goes first, so all its problems are reported rather than the first
+    // one the deserialization stops at, and it can't drift from what the loader accepts
+    let errors = match schema::validate_schema(&value) {
+        Ok(()) => {
+            let config = loader.load(&source).map_err(load_error)?;
+            config.validate().err().unwrap_or_default()
+        }
+        Err(errors) => errors,
+    };
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    // a value is located in the file setting it, which may be an included one
+    Err(errors
+        .iter()
+        .map(|e| match loader.position(&source, &e.path) {
+            Some((origin, line, column)) => format!("{}:{}:{}: {}", origin, line, column, e),
+            None => format!("{}: {}", origin, e),
+        })
+        .collect())
+}
+
+pub fn run(command: ConfigCommand) -> ExitCode {
+    match command {
+        ConfigCommand::Check(args) => match check(&ConfigLoader::new(), &args) {
+            Ok(()) => {
+                println!("{}: ok", args.path.display());
+                ExitCode::Ok
+            }
+            Err(errors) => {
+                errors.iter().for_each(|e| eprintln!("{}", e));
+                ExitCode::BadConfiguration
+            }
+        },
+        ConfigCommand::Schema(args) => {
+            let schema = match args.auth_method {
+                true => schema::method_schema(),
+                false => schema::config_schema(),
+            };
+
+            match serde_json::to_string_pretty(&schema) {
+                Ok(schema) => {
+                    println!("{}", schema);
+                    ExitCode::Ok
+                }
+                Err(e) => {
+                    eprintln!("failed to serialize the schema: {}", e);
+                    ExitCode::UnexpectedError
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use super::*;
+
+    const FIXTURE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../mfm_core/tests/fixtures/config.toml"
+    );
+
+    fn check_file(content: &str, suffix: &str) -> Result<(), Vec<String>> {
+        let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+
+        let args = CheckArgs {
+            path: file.path().to_path_buf(),
+            format: None,
+            profile: None,
+        };
+
+        check(&ConfigLoader::new().env(vec![]), &args).map_err(|errors| {
+            let origin = file.path().display().to_string();
+            errors
+                .iter()
+                .map(|e| e.replace(&origin, "config"))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_check_valid_file() {
+        let args = CheckArgs {
+            path: PathBuf::from(FIXTURE),
+            format: None,
+            profile: None,
+        };
+        assert_eq!(check(&ConfigLoader::new().env(vec![]), &args), Ok(()));
+    }
+
+    #[test]
+    fn test_check_reports_lines() {
+        let content = std::fs::read_to_string(FIXTURE)
+            .unwrap()
+            .replace(
+                "slippage = 0.5\npath_asset = \"wbnb\"\n\n[tokens.busd]",
+                "slippage = 0.5\npath_asset = \"weth\"\n\n[tokens.busd]",
+            )
+            .replace("chain_id = 56", "chain_id = \"56\"")
+            .replace("decimals = 18", "decimals = \"18\"");
+
+        // every schema problem is reported, and the config isn't validated further
+        assert_eq!(
+            check_file(&content, ".toml"),
+            Err(vec![
+                "config:6:1: networks.bsc.chain_id: \"56\" is not of type \"integer\"".to_string(),
+                "config:5:1: networks.bsc.decimals: \"18\" is not of types \"integer\", \"null\""
+                    .to_string(),
+            ])
+        );
+
+        let content = content
+            .replace("chain_id = \"56\"", "chain_id = 56")
+            .replace("decimals = \"18\"", "decimals = 18");
+        assert_eq!(
+            check_file(&content, ".toml"),
+            Err(vec![
+                "config:37:1: tokens.wbnb.networks.bsc.path_asset: token 'weth' is not defined in tokens"
+                    .to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_check_reports_included_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = std::fs::read_to_string(FIXTURE)
+            .unwrap()
+            .replace("chain_id = 56", "chain_id = \"56\"");
+        std::fs::write(dir.path().join("base.toml"), base).unwrap();
+
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "include = \"base.toml\"\n\n[networks.bsc]\nnode_url = \"https://bsc.example.org\"\n",
+        )
+        .unwrap();
+
+        let args = CheckArgs {
+            path,
+            format: None,
+            profile: None,
+        };
+        // the value is located in the included file setting it
+        assert_eq!(
+            check(&ConfigLoader::new().env(vec![]), &args),
+            Err(vec![format!(
+                "{}:6:1: networks.bsc.chain_id: \"56\" is not of type \"integer\"",
+                dir.path().join("base.toml").display()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_check_unknown_format() {
+        match check_file("", ".ini") {
+            Err(errors) => assert!(errors[0].starts_with("config: unknown format")),
+            result => panic!("expected an error, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use clap::{Parser, Subcommand};
+
+use crate::ExitCode;
+
+pub mod config;
+pub mod wallet;
+
+#[derive(Debug, Parser)]
+#[command(name = crate::APP_NAME, version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Inspect and check config files
+    #[command(subcommand)]
+    Config(config::ConfigCommand),
+    /// Create and re-encrypt the keys of wallet auth methods
+    #[command(subcommand)]
+    Wallet(wallet::WalletCommand),
+}
+
+pub fn run(cli: Cli) -> ExitCode {
+    match cli.command {
+        Command::Config(command) => config::run(command),
+        Command::Wallet(command) => wallet::run(command),
+    }
+}
@@ -0,0 +1,648 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, Subcommand};
+use mfm_core::{
+    config::{
+        address::Address,
+        authentication::{
+            keystore::{Kdf, Keystore},
+            mnemonic::{DerivationPath, Mnemonic},
+            wallet::{read_password, PrivateKey, WalletError},
+            Method,
+        },
+        loader::{ConfigError, Layer},
+        secret,
+    },
+    contexts::ConfigFormat,
+};
+use serde_json::{json, Value};
+use zeroize::Zeroizing;
+
+use super::config::Format;
+use crate::ExitCode;
+
+#[derive(Debug, Subcommand)]
+pub enum WalletCommand {
+    /// Generate a new private key, printing its encrypted wallet entry
+    Generate(EncryptArgs),
+    /// Encrypt an existing private key, read from a hidden prompt
+    Import(EncryptArgs),
+    /// Encrypt an existing BIP-39 phrase, read from a hidden prompt
+    ImportMnemonic(ImportMnemonicArgs),
+    /// Encrypt a wallet or mnemonic entry, or a keystore, again with a new password
+    Reencrypt(ReencryptArgs),
+}
+
+/// The name of the printed entries when `--name` isn't set.
+pub const DEFAULT_NAME: &str = "wallet";
+
+#[derive(Debug, Clone, Args)]
+pub struct EncryptArgs {
+    /// Name of the printed entry; defaults to the one of the re-encrypted entry, or
+    /// else to `wallet`
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Network the entry signs on, repeated for each network; defaults to the ones
+    /// of the re-encrypted entry, or else to all of them
+    #[arg(long = "network")]
+    pub networks: Vec<String>,
+    /// Env var holding the new password, also recorded in the printed entry; the
+    /// password is prompted for when it isn't set
+    #[arg(long)]
+    pub env_password: Option<String>,
+    /// Format of the printed entry
+    #[arg(long, value_enum, default_value = "toml")]
+    pub format: Format,
+    /// Derive the encryption key with lighter scrypt parameters, faster but weaker
+    #[arg(long)]
+    pub light: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportMnemonicArgs {
+    /// Derivation path of an account, repeated for each account; defaults to the
+    /// first Ethereum account, m/44'/60'/0'/0/0
+    #[arg(long = "derivation-path")]
+    pub derivation_paths: Vec<DerivationPath>,
+    #[command(flatten)]
+    pub encrypt: EncryptArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ReencryptArgs {
+    /// Path to an entry, as printed by the other commands, or to a keystore json file
+    pub path: PathBuf,
+    #[command(flatten)]
+    pub encrypt: EncryptArgs,
+}
+
+/// Where the passwords and keys come from: the environment, or a prompt that
+/// doesn't echo its input.
+pub struct Secrets<'a> {
+    pub env: &'a dyn Fn(&str) -> Option<String>,
+    pub prompt: &'a mut dyn FnMut(&str) -> io::Result<String>,
+}
+
+impl<'a> Secrets<'a> {
+    fn prompt(&mut self, prompt: &str) -> Result<Zeroizing<String>, String> {
+        (self.prompt)(prompt).map(Zeroizing::new).map_err(|e| {
+            format!(
+                "failed to read the {}: {}",
+                prompt.trim_end_matches(": "),
+                e
+            )
+        })
+    }
+
+    // the new password is confirmed when it's typed in
+    fn new_password(&mut self, env_password: &Option<String>) -> Result<Zeroizing<String>, String> {
+        if let Some(password) = env_password.as_deref().and_then(self.env) {
+            return Ok(Zeroizing::new(password));
+        }
+
+        let password = self.prompt("new wallet password: ")?;
+        if password.is_empty() {
+            return Err("the wallet password can't be empty".to_string());
+        }
+
+        match *self.prompt("repeat the new wallet password: ")? == *password {
+            true => Ok(password),
+            false => Err("the passwords don't match".to_string()),
+        }
+    }
+
+    // the current password of an entry, from its env var or a prompt
+    fn current_password(
+        &mut self,
+        env_password: &Option<String>,
+    ) -> Result<Zeroizing<String>, String> {
+        let prompt = &mut self.prompt;
+        read_password(env_password, self.env, |_| {
+            prompt("current wallet password: ")
+        })
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// The secret to encrypt into an auth method entry.
+enum Secret {
+    Key(PrivateKey),
+    Phrase(Zeroizing<Vec<u8>>, Vec<DerivationPath>),
+}
+
+impl Secret {
+    fn addresses(&self) -> Result<Vec<Address>, WalletError> {
+        match self {
+            Self::Key(key) => Ok(vec![key.address()]),
+            Self::Phrase(phrase, derivation_paths) => {
+                let mnemonic = Mnemonic {
+                    phrase: secret::Secret::from(String::from_utf8_lossy(phrase).into_owned()),
+                    not_encrypted: true,
+                    env_password: None,
+                    derivation_paths: derivation_paths.clone(),
+                };
+                let keys = mnemonic.decrypt(&[])?;
+                Ok(keys.iter().map(PrivateKey::address).collect())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedWallet {
+    pub addresses: Vec<Address>,
+    /// An `auth_methods` entry, ready to be pasted into a config file.
+    pub entry: String,
+}
+
+// the name and networks of the entry being re-encrypted, if any
+#[derive(Debug, Default)]
+struct Entry {
+    name: Option<String>,
+    networks: Vec<String>,
+}
+
+fn encrypt(
+    secret: &Secret,
+    entry: Entry,
+    args: &EncryptArgs,
+    secrets: &mut Secrets,
+) -> Result<EncryptedWallet, String> {
+    // checked before asking for a password
+    let addresses = secret.addresses().map_err(|e| e.to_string())?;
+
+    let password = secrets.new_password(&args.env_password)?;
+    let kdf = match args.light {
+        true => Kdf::light(),
+        false => Kdf::standard(),
+    };
+
+    let bytes: &[u8] = match secret {
+        Secret::Key(key) => key.as_bytes(),
+        Secret::Phrase(phrase, _) => phrase,
+    };
+    let mut keystore = Keystore::encrypt(bytes, password.as_bytes(), kdf)
+        .map_err(|e| format!("failed to encrypt the secret: {}", e))?;
+    keystore.address = addresses
+        .first()
+        .map(|address| address.to_string()[2..].to_lowercase());
+
+    let mut method = match secret {
+        Secret::Key(_) => json!({
+            "type": "wallet",
+            "private_key": keystore.to_json(),
+            "not_encrypted": false,
+        }),
+        Secret::Phrase(_, derivation_paths) => json!({
+            "type": "mnemonic",
+            "phrase": keystore.to_json(),
+            "not_encrypted": false,
+            "derivation_paths": derivation_paths,
+        }),
+    };
+    if let Some(env_password) = &args.env_password {
+        method["env_password"] = json!(env_password);
+    }
+
+    method["name"] = json!(args
+        .name
+        .as_deref()
+        .or(entry.name.as_deref())
+        .unwrap_or(DEFAULT_NAME));
+    let networks = match args.networks.is_empty() {
+        true => entry.networks,
+        false => args.networks.clone(),
+    };
+    if !networks.is_empty() {
+        method["networks"] = json!(networks);
+    }
+
+    let config = json!({ "auth_methods": [method] });
+    let entry = match args.format {
+        Format::Toml => toml::to_string(&config).map_err(|e| e.to_string()),
+        Format::Yaml => serde_yaml::to_string(&config).map_err(|e| e.to_string()),
+        Format::Json => serde_json::to_string_pretty(&config).map_err(|e| e.to_string()),
+    }
+    .map_err(|e| format!("failed to serialize the entry: {}", e))?;
+
+    Ok(EncryptedWallet { addresses, entry })
+}
+
+pub fn generate(args: &EncryptArgs, secrets: &mut Secrets) -> Result<EncryptedWallet, String> {
+    encrypt(
+        &Secret::Key(PrivateKey::random()),
+        Entry::default(),
+        args,
+        secrets,
+    )
+}
+
+pub fn import(args: &EncryptArgs, secrets: &mut Secrets) -> Result<EncryptedWallet, String> {
+    let hex = secrets.prompt("private key: ")?;
+    let key = PrivateKey::from_hex(hex.as_bytes()).map_err(|e| e.to_string())?;
+    encrypt(&Secret::Key(key), Entry::default(), args, secrets)
+}
+
+pub fn import_mnemonic(
+    args: &ImportMnemonicArgs,
+    secrets: &mut Secrets,
+) -> Result<EncryptedWallet, String> {
+    let phrase = secrets.prompt("mnemonic phrase: ")?;
+    let derivation_paths = match args.derivation_paths.is_empty() {
+        true => vec![DerivationPath::default()],
+        false => args.derivation_paths.clone(),
+    };
+
+    // extra whitespace would change the seed of an encrypted phrase
+    let phrase = Zeroizing::new(phrase.split_whitespace().collect::<Vec<_>>().join(" "));
+    encrypt(
+        &Secret::Phrase(Zeroizing::new(phrase.as_bytes().to_vec()), derivation_paths),
+        Entry::default(),
+        &args.encrypt,
+        secrets,
+    )
+}
+
+// finds the auth method in a keystore, a single entry, or an `auth_methods` list
+// holding a single entry
+fn read_method(path: &Path) -> Result<(Entry, Method), String> {
+    let origin = path.display().to_string();
+    let format = ConfigFormat::from_path(path).ok_or_else(|| {
+        format!(
+            "{}: unknown format; use a .toml, .yaml, .yml or .json extension",
+            origin
+        )
+    })?;
+
+    let value: Value = Layer::from_file(format, path)
+        .and_then(|layer| layer.parse())
+        .map_err(|e| match e {
+            ConfigError::Parse(e) => e.to_string(),
+            ConfigError::Io(path, e) => format!("{}: {}", path.display(), e),
+        })?;
+
+    let entry = match value {
+        Value::Object(ref object)
+            if object.contains_key("crypto") || object.contains_key("Crypto") =>
+        {
+            json!({ "type": "wallet", "private_key": value.to_string(), "not_encrypted": false })
+        }
+        Value::Object(mut object) => match object.remove("auth_methods") {
+            Some(Value::Array(mut methods)) if methods.len() == 1 => methods.remove(0),
+            Some(Value::Array(methods)) => {
+                return Err(format!(
+                    "{}: holds {} auth methods, re-encrypt them one at a time",
+                    origin,
+                    methods.len()
+                ))
+            }
+            Some(_) => return Err(format!("{}: auth_methods isn't a list", origin)),
+            None => Value::Object(object),
+        },
+        _ => {
+            return Err(format!(
+                "{}: expected an auth method entry or a keystore",
+                origin
+            ))
+        }
+    };
+
+    let name = entry["name"].as_str().map(ToString::to_string);
+    let networks = serde_json::from_value(entry["networks"].clone()).unwrap_or_default();
+
+    // the entry holds the secret, so serde errors are kept out of the message
+    let method = serde_json::from_value(entry)
+        .map_err(|_| format!("{}: expected an auth method entry or a keystore", origin))?;
+    Ok((Entry { name, networks }, method))
+}
+
+pub fn reencrypt(args: &ReencryptArgs, secrets: &mut Secrets) -> Result<EncryptedWallet, String> {
+    let in_file = |e: WalletError| format!("{}: {}", args.path.display(), e);
+
+    let (entry, method) = read_method(&args.path)?;
+    let secret = match method {
+        Method::Wallet(wallet) => {
+            let password = match wallet.not_encrypted {
+                true => Zeroizing::new(String::new()),
+                false => secrets.current_password(&wallet.env_password)?,
+            };
+            Secret::Key(wallet.decrypt(password.as_bytes()).map_err(in_file)?)
+        }
+        Method::Mnemonic(mnemonic) => {
+            let password = match mnemonic.not_encrypted {
+                true => Zeroizing::new(String::new()),
+                false => secrets.current_password(&mnemonic.env_password)?,
+            };
+            let phrase = mnemonic
+                .decrypt_phrase(password.as_bytes())
+                .map_err(in_file)?;
+            Secret::Phrase(phrase, mnemonic.derivation_paths)
+        }
+        method => {
+            return Err(format!(
+                "{}: a {:?} auth method holds no secret",
+                args.path.display(),
+                method
+            ))
+        }
+    };
+
+    encrypt(&secret, entry, &args.encrypt, secrets)
+}
+
+pub fn run(command: WalletCommand) -> ExitCode {
+    let env = |var: &str| std::env::var(var).ok();
+    let mut prompt = |prompt: &str| rpassword::prompt_password(prompt);
+    let mut secrets = Secrets {
+        env: &env,
+        prompt: &mut prompt,
+    };
+
+    let result = match &command {
+        WalletCommand::Generate(args) => generate(args, &mut secrets),
+        WalletCommand::Import(args) => import(args, &mut secrets),
+        WalletCommand::ImportMnemonic(args) => import_mnemonic(args, &mut secrets),
+        WalletCommand::Reencrypt(args) => reencrypt(args, &mut secrets),
+    };
+
+    // the entry goes to stdout alone, so it can be redirected into a file
+    match result {
+        Ok(wallet) => {
+            for address in &wallet.addresses {
+                eprintln!("address: {}", address);
+            }
+            print!("{}", wallet.entry);
+            if !wallet.entry.ends_with('\n') {
+                println!();
+            }
+            ExitCode::Ok
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::GenericError
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::VecDeque, io::Write};
+
+    use mfm_core::config::authentication::{wallet::Wallet, AuthMethod};
+
+    use super::*;
+
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ADDRESS: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    // hardhat's default accounts; never hold funds with them
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    fn args(format: Format, env_password: Option<&str>) -> EncryptArgs {
+        EncryptArgs {
+            name: None,
+            networks: vec![],
+            env_password: env_password.map(ToString::to_string),
+            format,
+            light: true,
+        }
+    }
+
+    // runs `f` answering the prompts in order, returning the prompts asked
+    fn with_answers<T>(
+        answers: &[&str],
+        env: &dyn Fn(&str) -> Option<String>,
+        f: impl FnOnce(&mut Secrets) -> T,
+    ) -> (T, Vec<String>) {
+        let mut answers: VecDeque<String> = answers.iter().map(ToString::to_string).collect();
+        let mut asked = Vec::new();
+        let mut prompt = |prompt: &str| {
+            asked.push(prompt.to_string());
+            answers
+                .pop_front()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "no more answers"))
+        };
+
+        let result = f(&mut Secrets {
+            env,
+            prompt: &mut prompt,
+        });
+        (result, asked)
+    }
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    fn parse_entry(entry: &str, format: ConfigFormat) -> AuthMethod {
+        let value: Value = Layer::new(format, entry.to_string(), "entry")
+            .parse()
+            .unwrap();
+        serde_json::from_value(value["auth_methods"][0].clone()).unwrap()
+    }
+
+    fn parse_wallet(entry: &str, format: ConfigFormat) -> Wallet {
+        match parse_entry(entry, format).method {
+            Method::Wallet(wallet) => wallet,
+            method => panic!("expected a wallet, got {:?}", method),
+        }
+    }
+
+    #[test]
+    fn test_import() {
+        let (wallet, asked) = with_answers(&[KEY, "secret", "secret"], &no_env, |secrets| {
+            import(&args(Format::Toml, None), secrets)
+        });
+        let wallet = wallet.unwrap();
+
+        assert_eq!(
+            asked,
+            vec![
+                "private key: ",
+                "new wallet password: ",
+                "repeat the new wallet password: "
+            ]
+        );
+        assert_eq!(wallet.addresses[0].to_string(), ADDRESS);
+        assert!(wallet.entry.starts_with("[[auth_methods]]\n"));
+        assert!(!wallet.entry.contains(KEY));
+
+        let entry = parse_entry(&wallet.entry, ConfigFormat::Toml);
+        assert_eq!(entry.name, DEFAULT_NAME);
+        assert!(entry.networks.is_empty());
+
+        let decrypted = parse_wallet(&wallet.entry, ConfigFormat::Toml)
+            .decrypt(b"secret")
+            .unwrap();
+        assert_eq!(decrypted, PrivateKey::from_hex(KEY.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_generate_with_env_password() {
+        let env = |var: &str| (var == "MFM_PASSWORD").then(|| "secret".to_string());
+        let (wallet, asked) = with_answers(&[], &env, |secrets| {
+            generate(&args(Format::Yaml, Some("MFM_PASSWORD")), secrets)
+        });
+        let wallet = wallet.unwrap();
+        assert!(asked.is_empty());
+
+        let parsed = parse_wallet(&wallet.entry, ConfigFormat::Yaml);
+        assert_eq!(parsed.env_password.as_deref(), Some("MFM_PASSWORD"));
+        assert_eq!(
+            parsed.decrypt(b"secret").unwrap().address(),
+            wallet.addresses[0]
+        );
+    }
+
+    #[test]
+    fn test_password_errors() {
+        let (result, _) = with_answers(&["secret", "other"], &no_env, |secrets| {
+            generate(&args(Format::Toml, None), secrets)
+        });
+        assert_eq!(result, Err("the passwords don't match".to_string()));
+
+        let (result, _) = with_answers(&[""], &no_env, |secrets| {
+            generate(&args(Format::Toml, None), secrets)
+        });
+        assert_eq!(
+            result,
+            Err("the wallet password can't be empty".to_string())
+        );
+
+        let (result, _) = with_answers(&["0x1234"], &no_env, |secrets| {
+            import(&args(Format::Toml, None), secrets)
+        });
+        assert!(!result.unwrap_err().contains("1234"));
+    }
+
+    #[test]
+    fn test_reencrypt() {
+        let (wallet, _) = with_answers(&[KEY, "old", "old"], &no_env, |secrets| {
+            import(&args(Format::Json, None), secrets)
+        });
+
+        let mut file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        file.write_all(wallet.unwrap().entry.as_bytes()).unwrap();
+
+        let reencrypt_args = ReencryptArgs {
+            path: file.path().to_path_buf(),
+            encrypt: args(Format::Toml, None),
+        };
+
+        let (result, _) = with_answers(&["wrong"], &no_env, |secrets| {
+            reencrypt(&reencrypt_args, secrets)
+        });
+        assert!(result
+            .unwrap_err()
+            .ends_with("wrong password, the keystore mac doesn't match"));
+
+        let (wallet, asked) = with_answers(&["old", "new", "new"], &no_env, |secrets| {
+            reencrypt(&reencrypt_args, secrets)
+        });
+        let wallet = wallet.unwrap();
+        assert_eq!(asked[0], "current wallet password: ");
+        assert_eq!(wallet.addresses[0].to_string(), ADDRESS);
+
+        let parsed = parse_wallet(&wallet.entry, ConfigFormat::Toml);
+        assert!(parsed.decrypt(b"old").is_err());
+        assert_eq!(
+            parsed.decrypt(b"new").unwrap().address(),
+            wallet.addresses[0]
+        );
+    }
+
+    #[test]
+    fn test_reencrypt_keystore() {
+        let key = PrivateKey::from_hex(KEY.as_bytes()).unwrap();
+        let keystore = Keystore::encrypt(key.as_bytes(), b"old", Kdf::light()).unwrap();
+
+        let mut file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        file.write_all(keystore.to_json().as_bytes()).unwrap();
+
+        let reencrypt_args = ReencryptArgs {
+            path: file.path().to_path_buf(),
+            encrypt: args(Format::Toml, None),
+        };
+        let (wallet, _) = with_answers(&["old", "new", "new"], &no_env, |secrets| {
+            reencrypt(&reencrypt_args, secrets)
+        });
+        assert_eq!(wallet.unwrap().addresses[0].to_string(), ADDRESS);
+    }
+
+    fn parse_mnemonic(entry: &str, format: ConfigFormat) -> Mnemonic {
+        match parse_entry(entry, format).method {
+            Method::Mnemonic(mnemonic) => mnemonic,
+            method => panic!("expected a mnemonic, got {:?}", method),
+        }
+    }
+
+    #[test]
+    fn test_import_mnemonic() {
+        let mnemonic_args = ImportMnemonicArgs {
+            derivation_paths: vec![
+                "m/44'/60'/0'/0/0".parse().unwrap(),
+                "m/44'/60'/0'/0/1".parse().unwrap(),
+            ],
+            encrypt: EncryptArgs {
+                name: Some("savings".to_string()),
+                networks: vec!["bsc".to_string()],
+                ..args(Format::Yaml, None)
+            },
+        };
+        let phrase = format!("  {}\n", PHRASE.replace(' ', "   "));
+        let (wallet, asked) = with_answers(&[&phrase, "secret", "secret"], &no_env, |secrets| {
+            import_mnemonic(&mnemonic_args, secrets)
+        });
+        let wallet = wallet.unwrap();
+
+        assert_eq!(asked[0], "mnemonic phrase: ");
+        assert_eq!(wallet.addresses.len(), 2);
+        assert_eq!(wallet.addresses[0].to_string(), ADDRESS);
+        assert!(!wallet.entry.contains("junk"));
+
+        let mnemonic = parse_mnemonic(&wallet.entry, ConfigFormat::Yaml);
+        assert_eq!(mnemonic.derivation_paths, mnemonic_args.derivation_paths);
+        let keys = mnemonic.decrypt(b"secret").unwrap();
+        assert_eq!(keys[1].address(), wallet.addresses[1]);
+
+        // re-encrypting keeps the name, the networks and the derivation paths
+        let mut file = tempfile::Builder::new().suffix(".yaml").tempfile().unwrap();
+        file.write_all(wallet.entry.as_bytes()).unwrap();
+        let reencrypt_args = ReencryptArgs {
+            path: file.path().to_path_buf(),
+            encrypt: args(Format::Toml, None),
+        };
+        let (reencrypted, _) = with_answers(&["secret", "new", "new"], &no_env, |secrets| {
+            reencrypt(&reencrypt_args, secrets)
+        });
+        let reencrypted = reencrypted.unwrap();
+        assert_eq!(reencrypted.addresses, wallet.addresses);
+        let entry = parse_entry(&reencrypted.entry, ConfigFormat::Toml);
+        assert_eq!(entry.name, "savings");
+        assert_eq!(entry.networks, ["bsc"]);
+        assert_eq!(
+            parse_mnemonic(&reencrypted.entry, ConfigFormat::Toml).derivation_paths,
+            mnemonic_args.derivation_paths
+        );
+    }
+
+    #[test]
+    fn test_import_invalid_mnemonic() {
+        let mnemonic_args = ImportMnemonicArgs {
+            derivation_paths: vec![],
+            encrypt: args(Format::Toml, None),
+        };
+        let (result, asked) =
+            with_answers(&[&PHRASE.replace("junk", "jnuk")], &no_env, |secrets| {
+                import_mnemonic(&mnemonic_args, secrets)
+            });
+
+        // the phrase is rejected before asking for a password, and not echoed
+        assert_eq!(asked.len(), 1);
+        let error = result.unwrap_err();
+        assert!(error.starts_with("invalid mnemonic"));
+        assert!(!error.contains("jnuk"));
+    }
+}
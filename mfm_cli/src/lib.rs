@@ -1,3 +1,4 @@
+pub mod cli;
 pub mod telemetry;
 
 pub const APP_NAME: &str = "mfm";
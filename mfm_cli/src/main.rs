@@ -1,4 +1,6 @@
+use clap::{error::ErrorKind, Parser};
 use mfm::{
+    cli::{self, Cli},
     telemetry::{get_subscriber, init_subscriber},
     ExitCode, APP_NAME, DEFAULT_LOG_LEVEL,
 };
@@ -18,8 +20,20 @@ fn main() {
 
 #[tracing::instrument(name = "main exitable")]
 fn main_exitable() -> ExitCode {
-    let subscriber = get_subscriber(APP_NAME.into(), DEFAULT_LOG_LEVEL.into(), std::io::stdout);
+    // stdout is left to the command outputs
+    let subscriber = get_subscriber(APP_NAME.into(), DEFAULT_LOG_LEVEL.into(), std::io::stderr);
     init_subscriber(subscriber);
 
-    unimplemented!()
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return match e.kind() {
+                ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => ExitCode::Ok,
+                _ => ExitCode::ArgParsing,
+            };
+        }
+    };
+
+    cli::run(cli)
 }
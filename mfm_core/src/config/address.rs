@@ -0,0 +1,234 @@
+use std::{fmt, str::FromStr};
+
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, Schema, SchemaObject, StringValidation},
+    JsonSchema,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use tiny_keccak::{Hasher, Keccak};
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    let mut output = [0u8; 32];
+    hasher.update(data);
+    hasher.finalize(&mut output);
+    output
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    MissingPrefix,
+    InvalidLength(usize),
+    InvalidHex,
+    InvalidChecksum(String),
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPrefix => write!(f, "an EVM address starts with 0x"),
+            Self::InvalidLength(len) => {
+                write!(f, "an EVM address has 40 hex digits, found {}", len)
+            }
+            Self::InvalidHex => write!(f, "an EVM address has only hex digits after 0x"),
+            Self::InvalidChecksum(expected) => {
+                write!(f, "invalid EIP-55 checksum, expected {}", expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// The JSON Schema `format` of an [`Address`]: its pattern only checks the hex
+/// digits, while the format also checks the EIP-55 checksum of a mixed case one.
+pub const ADDRESS_FORMAT: &str = "evm-address";
+
+/// Whether `s` parses as an [`Address`], e.g. for the [`ADDRESS_FORMAT`] of a schema.
+pub fn is_address(s: &str) -> bool {
+    s.parse::<Address>().is_ok()
+}
+
+/// A 20 bytes EVM address.
+///
+/// Parsing accepts all lowercase or all uppercase hex, while mixed case must match
+/// the EIP-55 checksum; it's always displayed and serialized checksummed.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Address([u8; 20]);
+
+impl Address {
+    pub const fn new(bytes: [u8; 20]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
+    /// The EIP-55 mixed case representation.
+    pub fn to_checksum(&self) -> String {
+        let hex: String = self.0.iter().map(|b| format!("{:02x}", b)).collect();
+        let hash = keccak256(hex.as_bytes());
+
+        let checksummed: String = hex
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let nibble = (hash[i / 2] >> (4 * (1 - i % 2))) & 0x0f;
+                match nibble >= 8 {
+                    true => c.to_ascii_uppercase(),
+                    false => c,
+                }
+            })
+            .collect();
+
+        format!("0x{}", checksummed)
+    }
+}
+
+impl From<[u8; 20]> for Address {
+    fn from(bytes: [u8; 20]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix("0x").ok_or(AddressError::MissingPrefix)?;
+
+        if hex.len() != 40 {
+            return Err(AddressError::InvalidLength(hex.len()));
+        }
+
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AddressError::InvalidHex);
+        }
+
+        let mut bytes = [0u8; 20];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).expect("checked hex digits");
+        }
+
+        let address = Self(bytes);
+        let mixed_case = hex.chars().any(|c| c.is_ascii_lowercase())
+            && hex.chars().any(|c| c.is_ascii_uppercase());
+
+        if mixed_case && address.to_checksum() != s {
+            return Err(AddressError::InvalidChecksum(address.to_checksum()));
+        }
+
+        Ok(address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_checksum())
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Address({})", self.to_checksum())
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_checksum())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|e| de::Error::custom(format!("invalid address '{}': {}", s, e)))
+    }
+}
+
+impl JsonSchema for Address {
+    fn schema_name() -> String {
+        "Address".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            format: Some(ADDRESS_FORMAT.to_string()),
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "An EVM address, all lowercase, all uppercase or EIP-55 checksummed"
+                        .to_string(),
+                ),
+                ..Default::default()
+            })),
+            string: Some(Box::new(StringValidation {
+                pattern: Some("^0x[0-9a-fA-F]{40}$".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // from the EIP-55 test cases
+    const CHECKSUMMED: [&str; 4] = [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn test_checksum() {
+        for checksummed in CHECKSUMMED {
+            let address: Address = checksummed.to_lowercase().parse().unwrap();
+            assert_eq!(address.to_string(), checksummed);
+            assert_eq!(checksummed.parse::<Address>().unwrap(), address);
+        }
+
+        let upper = format!("0x{}", CHECKSUMMED[0][2..].to_uppercase());
+        assert!(upper.parse::<Address>().is_ok());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse::<Address>(),
+            Err(AddressError::MissingPrefix)
+        );
+        assert_eq!(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA".parse::<Address>(),
+            Err(AddressError::InvalidLength(38))
+        );
+        assert_eq!(
+            "0xzaAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse::<Address>(),
+            Err(AddressError::InvalidHex)
+        );
+        assert_eq!(
+            "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse::<Address>(),
+            Err(AddressError::InvalidChecksum(CHECKSUMMED[0].to_string()))
+        );
+    }
+
+    #[test]
+    fn test_serde() {
+        let address: Address = serde_json::from_str(&format!("\"{}\"", CHECKSUMMED[1])).unwrap();
+        assert_eq!(
+            serde_json::to_string(&address).unwrap(),
+            format!("\"{}\"", CHECKSUMMED[1])
+        );
+
+        let error = serde_json::from_str::<Address>("\"0x1234\"").unwrap_err();
+        assert!(error.to_string().starts_with("invalid address '0x1234'"));
+    }
+}
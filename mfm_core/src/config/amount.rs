@@ -0,0 +1,334 @@
+use std::{fmt, str::FromStr};
+
+use rust_decimal::Decimal;
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Schema, SchemaObject},
+    JsonSchema,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+// numbers are read through their shortest representation, so a `0.2` float
+// becomes exactly 0.2 instead of its binary approximation
+struct DecimalVisitor;
+
+impl<'de> de::Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a decimal number or a string holding one")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Decimal, E> {
+        Decimal::from_str_exact(v.trim())
+            .or_else(|_| Decimal::from_scientific(v.trim()))
+            .map_err(|_| E::custom(format!("invalid decimal '{}'", v)))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Decimal, E> {
+        Ok(Decimal::from(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Decimal, E> {
+        Ok(Decimal::from(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Decimal, E> {
+        if !v.is_finite() {
+            return Err(E::custom(format!("invalid decimal '{}'", v)));
+        }
+        self.visit_str(&v.to_string())
+    }
+}
+
+fn decimal_schema() -> Schema {
+    SchemaObject {
+        instance_type: Some(vec![InstanceType::Number, InstanceType::String].into()),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// A non negative quantity of a coin or token, in whole units (e.g. `0.2` BNB).
+///
+/// It's a fixed point decimal, read from numbers or strings and serialized as a
+/// string, so it never loses precision on a round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Amount(Decimal);
+
+impl Amount {
+    pub const ZERO: Self = Self(Decimal::ZERO);
+
+    pub fn new(value: Decimal) -> Option<Self> {
+        match value.is_sign_negative() && !value.is_zero() {
+            true => None,
+            false => Some(Self(value)),
+        }
+    }
+
+    pub fn value(&self) -> Decimal {
+        self.0
+    }
+
+    /// The amount in the smallest unit of an asset with `decimals` decimals, e.g. wei.
+    ///
+    /// `None` when it has more decimals than the asset, or doesn't fit in a u128.
+    pub fn to_base_units(&self, decimals: u8) -> Option<u128> {
+        let value = self.0.normalize();
+        let exponent = u32::from(decimals).checked_sub(value.scale())?;
+        let mantissa = u128::try_from(value.mantissa()).ok()?;
+
+        10u128
+            .checked_pow(exponent)
+            .and_then(|factor| mantissa.checked_mul(factor))
+    }
+
+    /// The amount of `units` of the smallest unit of an asset with `decimals` decimals.
+    pub fn from_base_units(units: u128, decimals: u8) -> Option<Self> {
+        let units = i128::try_from(units).ok()?;
+        Decimal::try_from_i128_with_scale(units, u32::from(decimals))
+            .ok()
+            .map(|value| Self(value.normalize()))
+    }
+}
+
+impl FromStr for Amount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value: Decimal = de::Visitor::visit_str::<de::value::Error>(DecimalVisitor, s)
+            .map_err(|e| e.to_string())?;
+        Self::new(value).ok_or_else(|| format!("amounts can't be negative, found {}", s))
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = deserializer.deserialize_any(DecimalVisitor)?;
+        Self::new(value)
+            .ok_or_else(|| de::Error::custom(format!("amounts can't be negative, found {}", value)))
+    }
+}
+
+impl JsonSchema for Amount {
+    fn schema_name() -> String {
+        "Amount".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        decimal_schema()
+    }
+}
+
+/// A fixed point percentage, e.g. `0.5` for 0.5%.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Percentage(Decimal);
+
+impl Percentage {
+    pub const fn new(value: Decimal) -> Self {
+        Self(value)
+    }
+
+    pub fn value(&self) -> Decimal {
+        self.0
+    }
+
+    /// The percentage as a fraction of 1, e.g. `0.005` for 0.5%.
+    pub fn fraction(&self) -> Decimal {
+        self.0 / Decimal::ONE_HUNDRED
+    }
+}
+
+impl FromStr for Percentage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        de::Visitor::visit_str::<de::value::Error>(DecimalVisitor, s)
+            .map(Self)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl fmt::Display for Percentage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+impl Serialize for Percentage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Percentage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DecimalVisitor).map(Self)
+    }
+}
+
+impl JsonSchema for Percentage {
+    fn schema_name() -> String {
+        "Percentage".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        decimal_schema()
+    }
+}
+
+/// A quantity in the smallest unit of a coin or token, e.g. wei, for the amounts
+/// whose decimals aren't known upfront.
+///
+/// It's serialized as a decimal string, as a JSON number can't hold every u128.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct BaseUnits(pub u128);
+
+impl BaseUnits {
+    /// The decimal representation of the units of an asset with `decimals` decimals,
+    /// e.g. `1.5` for 1500000000000000000 wei.
+    ///
+    /// Unlike an [`Amount`], it's exact for any u128 and any number of decimals.
+    pub fn to_decimal_string(&self, decimals: u8) -> String {
+        let decimals = usize::from(decimals);
+        let digits = format!("{:0>width$}", self.0, width = decimals + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - decimals);
+
+        match fraction.trim_end_matches('0') {
+            "" => integer.to_string(),
+            fraction => format!("{}.{}", integer, fraction),
+        }
+    }
+}
+
+impl fmt::Display for BaseUnits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for BaseUnits {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BaseUnitsRepr {
+    Number(u64),
+    Text(String),
+}
+
+impl<'de> Deserialize<'de> for BaseUnits {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match BaseUnitsRepr::deserialize(deserializer)? {
+            BaseUnitsRepr::Number(units) => Ok(Self(units.into())),
+            BaseUnitsRepr::Text(units) => units.trim().parse().map(Self).map_err(|_| {
+                de::Error::custom(format!("invalid amount of base units '{}'", units))
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_amount() {
+        let from_float: Amount = toml::from_str::<toml::Value>("a = 0.2").unwrap()["a"]
+            .clone()
+            .try_into()
+            .unwrap();
+        let from_string: Amount = serde_json::from_str("\"0.2\"").unwrap();
+        let from_int: Amount = serde_json::from_str("2").unwrap();
+
+        assert_eq!(from_float.to_string(), "0.2");
+        assert_eq!(from_float, from_string);
+        assert_eq!(from_int.to_string(), "2");
+        assert!(serde_json::from_str::<Amount>("-1").is_err());
+        assert!(serde_json::from_str::<Amount>("\"two\"").is_err());
+
+        // serialized as a string, it round trips without losing precision
+        let precise: Amount = "0.123456789012345678".parse().unwrap();
+        let json = serde_json::to_string(&precise).unwrap();
+        assert_eq!(json, "\"0.123456789012345678\"");
+        assert_eq!(serde_json::from_str::<Amount>(&json).unwrap(), precise);
+    }
+
+    #[test]
+    fn test_base_units() {
+        let amount: Amount = "0.2".parse().unwrap();
+        assert_eq!(amount.to_base_units(18), Some(200_000_000_000_000_000));
+        assert_eq!(
+            Amount::from_base_units(200_000_000_000_000_000, 18),
+            Some(amount)
+        );
+
+        let amount: Amount = "1.0000001".parse().unwrap();
+        assert_eq!(amount.to_base_units(6), None);
+        assert_eq!(amount.to_base_units(7), Some(10_000_001));
+
+        assert_eq!(Amount::ZERO.to_base_units(18), Some(0));
+        assert_eq!(Amount::from_base_units(u128::MAX, 18), None);
+    }
+
+    #[test]
+    fn test_serialize_base_units() {
+        let units = BaseUnits(u128::MAX);
+        let value = serde_json::to_value(units).unwrap();
+        assert_eq!(value, serde_json::json!(u128::MAX.to_string()));
+        assert_eq!(serde_json::from_value::<BaseUnits>(value).unwrap(), units);
+
+        assert_eq!(
+            serde_json::from_str::<BaseUnits>("1000").unwrap(),
+            BaseUnits(1000)
+        );
+        assert!(serde_json::from_str::<BaseUnits>("\"-1\"").is_err());
+        assert!(serde_json::from_str::<BaseUnits>("0.5").is_err());
+    }
+
+    #[test]
+    fn test_base_units_to_decimal_string() {
+        assert_eq!(
+            BaseUnits(1_500_000_000_000_000_000).to_decimal_string(18),
+            "1.5"
+        );
+        assert_eq!(BaseUnits(1_000).to_decimal_string(0), "1000");
+        assert_eq!(BaseUnits(1_000).to_decimal_string(3), "1");
+        assert_eq!(BaseUnits(5).to_decimal_string(4), "0.0005");
+        assert_eq!(BaseUnits(0).to_decimal_string(18), "0");
+
+        // past what an Amount holds, e.g. a large balance of an 18 decimals token
+        let large = BaseUnits(10u128.pow(30) + 1);
+        assert_eq!(Amount::from_base_units(large.0, 18), None);
+        assert_eq!(
+            large.to_decimal_string(18),
+            "1000000000000.000000000000000001"
+        );
+        assert_eq!(
+            BaseUnits(u128::MAX).to_decimal_string(40),
+            "0.0340282366920938463463374607431768211455"
+        );
+    }
+
+    #[test]
+    fn test_percentage() {
+        let slippage: Percentage = serde_json::from_str("0.5").unwrap();
+        assert_eq!(slippage.fraction(), "0.005".parse().unwrap());
+        assert_eq!(slippage.to_string(), "0.5%");
+    }
+}
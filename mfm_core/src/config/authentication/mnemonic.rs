@@ -0,0 +1,369 @@
+use std::{fmt, io, str::FromStr};
+
+use hmac::{Hmac, Mac};
+use k256::{
+    elliptic_curve::{sec1::ToEncodedPoint, PrimeField},
+    NonZeroScalar, Scalar, SecretKey,
+};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Schema, SchemaObject, StringValidation},
+    JsonSchema,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::Sha512;
+use zeroize::Zeroizing;
+
+use super::wallet::{
+    decrypt_keystore, read_password, read_password_interactive, PrivateKey, WalletError,
+};
+use crate::config::secret::Secret;
+
+const HARDENED: u32 = 1 << 31;
+
+/// The first account of the Ethereum BIP-44 derivation, as used by most wallets.
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationPathError(String);
+
+impl fmt::Display for DerivationPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid derivation path '{}', expected e.g. {}",
+            self.0, DEFAULT_DERIVATION_PATH
+        )
+    }
+}
+
+impl std::error::Error for DerivationPathError {}
+
+/// A BIP-32 derivation path, e.g. `m/44'/60'/0'/0/0`; hardened indexes are marked
+/// with `'` or `h`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    pub fn indexes(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl Default for DerivationPath {
+    fn default() -> Self {
+        DEFAULT_DERIVATION_PATH.parse().expect("a valid path")
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = DerivationPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || DerivationPathError(s.to_string());
+        let mut segments = s.split('/');
+
+        if segments.next() != Some("m") {
+            return Err(error());
+        }
+
+        segments
+            .map(|segment| {
+                let (index, hardened) = match segment.strip_suffix(['\'', 'h', 'H']) {
+                    Some(index) => (index, HARDENED),
+                    None => (segment, 0),
+                };
+
+                // only plain digits, so e.g. "+1" or "01" are rejected
+                let digits = index.chars().all(|c| c.is_ascii_digit())
+                    && (index == "0" || !index.starts_with('0'));
+
+                match index.parse::<u32>() {
+                    Ok(index) if digits && index < HARDENED => Ok(index | hardened),
+                    _ => Err(error()),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            match index & HARDENED {
+                0 => write!(f, "/{}", index)?,
+                _ => write!(f, "/{}'", index & !HARDENED)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DerivationPath({})", self)
+    }
+}
+
+impl Serialize for DerivationPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for DerivationPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl JsonSchema for DerivationPath {
+    fn schema_name() -> String {
+        "DerivationPath".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some("^m(/[0-9]+['hH]?)*$".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> Zeroizing<[u8; 64]> {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("hmac accepts any key length");
+    data.iter().for_each(|chunk| mac.update(chunk));
+    Zeroizing::new(mac.finalize().into_bytes().into())
+}
+
+/// Derives the private key at `path` from a BIP-39 seed, following BIP-32.
+pub fn derive_key(seed: &[u8], path: &DerivationPath) -> Result<PrivateKey, WalletError> {
+    let mut extended = hmac_sha512(b"Bitcoin seed", &[seed]);
+
+    for index in path.indexes() {
+        let key = PrivateKey::from_slice(&extended[..32])?;
+        let secret_key = key.secret_key()?;
+
+        let child = match index & HARDENED {
+            0 => {
+                let public_key = secret_key.public_key().to_encoded_point(true);
+                hmac_sha512(
+                    &extended[32..],
+                    &[public_key.as_bytes(), &index.to_be_bytes()],
+                )
+            }
+            _ => hmac_sha512(
+                &extended[32..],
+                &[&[0], key.as_bytes(), &index.to_be_bytes()],
+            ),
+        };
+
+        // BIP-32 skips to the next index when this fails, with a chance below 2^-127
+        let tweak: Option<Scalar> =
+            Scalar::from_repr(*k256::FieldBytes::from_slice(&child[..32])).into();
+        let scalar = tweak.ok_or(WalletError::InvalidKey)? + *secret_key.to_nonzero_scalar();
+        let scalar: Option<NonZeroScalar> = NonZeroScalar::new(scalar).into();
+        let child_key = SecretKey::from(scalar.ok_or(WalletError::InvalidKey)?);
+
+        extended[..32].copy_from_slice(&child_key.to_bytes());
+        extended[32..].copy_from_slice(&child[32..]);
+    }
+
+    PrivateKey::from_slice(&extended[..32])
+}
+
+fn default_derivation_paths() -> Vec<DerivationPath> {
+    vec![DerivationPath::default()]
+}
+
+/// A BIP-39 seed phrase, deriving one account per derivation path, so several
+/// addresses share a single secret.
+///
+/// The phrase is in plain text when `not_encrypted` is set, otherwise it's held as
+/// an Ethereum V3 keystore json, with the password read as for a
+/// [`super::wallet::Wallet`].
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Mnemonic {
+    #[schemars(with = "String")]
+    pub phrase: Secret,
+    pub not_encrypted: bool,
+    pub env_password: Option<String>,
+    #[serde(default = "default_derivation_paths")]
+    pub derivation_paths: Vec<DerivationPath>,
+}
+
+impl PartialEq for Mnemonic {
+    fn eq(&self, other: &Self) -> bool {
+        self.phrase == other.phrase
+            && self.not_encrypted == other.not_encrypted
+            && self.env_password == other.env_password
+            && self.derivation_paths == other.derivation_paths
+    }
+}
+
+impl Eq for Mnemonic {}
+
+impl Mnemonic {
+    pub fn password(
+        &self,
+        env: impl Fn(&str) -> Option<String>,
+        prompt: impl FnOnce(&str) -> io::Result<String>,
+    ) -> Result<Zeroizing<String>, WalletError> {
+        read_password(&self.env_password, env, prompt)
+    }
+
+    /// Decrypts the phrase with the given password, which is ignored for a phrase
+    /// that's `not_encrypted`.
+    pub fn decrypt_phrase(&self, password: &[u8]) -> Result<Zeroizing<Vec<u8>>, WalletError> {
+        match self.not_encrypted {
+            true => Ok(Zeroizing::new(self.phrase.reveal().to_vec())),
+            false => decrypt_keystore(&self.phrase, password),
+        }
+    }
+
+    /// Decrypts the phrase and derives the key of each derivation path.
+    pub fn decrypt(&self, password: &[u8]) -> Result<Vec<PrivateKey>, WalletError> {
+        let phrase = self.decrypt_phrase(password)?;
+        let phrase = std::str::from_utf8(&phrase)
+            .map_err(|_| WalletError::InvalidMnemonic("the phrase isn't utf-8".to_string()))?;
+        let mnemonic = bip39::Mnemonic::parse(phrase)
+            .map_err(|e| WalletError::InvalidMnemonic(e.to_string()))?;
+        let seed = Zeroizing::new(mnemonic.to_seed(""));
+
+        self.derivation_paths
+            .iter()
+            .map(|path| derive_key(seed.as_ref(), path))
+            .collect()
+    }
+
+    /// Unlocks the keys, reading the password from the environment or the terminal.
+    pub fn unlock(&self) -> Result<Vec<PrivateKey>, WalletError> {
+        if self.not_encrypted {
+            return self.decrypt(&[]);
+        }
+
+        let password = read_password_interactive(&self.env_password)?;
+        self.decrypt(password.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::authentication::keystore::{Kdf, Keystore};
+
+    use super::*;
+
+    // hardhat's default accounts; never hold funds with them
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+    const ADDRESSES: [&str; 2] = [
+        "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+    ];
+
+    fn mnemonic(phrase: &str, not_encrypted: bool, paths: &[&str]) -> Mnemonic {
+        Mnemonic {
+            phrase: Secret::from(phrase),
+            not_encrypted,
+            env_password: None,
+            derivation_paths: paths.iter().map(|path| path.parse().unwrap()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_derivation_path() {
+        let path: DerivationPath = "m/44'/60'/0'/0/1".parse().unwrap();
+        assert_eq!(
+            path.indexes(),
+            &[44 | HARDENED, 60 | HARDENED, HARDENED, 0, 1]
+        );
+        assert_eq!(path.to_string(), "m/44'/60'/0'/0/1");
+        assert_eq!("m/44h/60H/0'/0/1".parse::<DerivationPath>(), Ok(path));
+        assert!("m".parse::<DerivationPath>().unwrap().indexes().is_empty());
+        assert_eq!(
+            DerivationPath::default().to_string(),
+            DEFAULT_DERIVATION_PATH
+        );
+
+        for invalid in [
+            "44'/60'",
+            "m/",
+            "m/-1",
+            "m/+1",
+            "m/01",
+            "m/2147483648",
+            "m/1''",
+        ] {
+            assert!(
+                invalid.parse::<DerivationPath>().is_err(),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_derive_accounts() {
+        let keys = mnemonic(PHRASE, true, &["m/44'/60'/0'/0/0", "m/44'/60'/0'/0/1"])
+            .unlock()
+            .unwrap();
+        let addresses: Vec<String> = keys.iter().map(|key| key.address().to_string()).collect();
+        assert_eq!(addresses, ADDRESSES);
+    }
+
+    #[test]
+    fn test_decrypt_phrase() {
+        let keystore = Keystore::encrypt(PHRASE.as_bytes(), b"secret", Kdf::light()).unwrap();
+        let encrypted = mnemonic(&keystore.to_json(), false, &[DEFAULT_DERIVATION_PATH]);
+
+        let keys = encrypted.decrypt(b"secret").unwrap();
+        assert_eq!(keys[0].address().to_string(), ADDRESSES[0]);
+        assert!(matches!(
+            encrypted.decrypt(b"wrong"),
+            Err(WalletError::Keystore(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_phrase() {
+        let error = mnemonic(&PHRASE.replace("junk", "test"), true, &[])
+            .decrypt(&[])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid mnemonic: the mnemonic has an invalid checksum"
+        );
+
+        let error = mnemonic(&PHRASE.replace("junk", "jnuk"), true, &[])
+            .decrypt(&[])
+            .unwrap_err();
+        assert!(!error.to_string().contains("jnuk"));
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let mnemonic: Mnemonic = serde_json::from_value(serde_json::json!({
+            "phrase": PHRASE,
+            "not_encrypted": true,
+        }))
+        .unwrap();
+        assert_eq!(mnemonic.derivation_paths, vec![DerivationPath::default()]);
+
+        let error = serde_json::from_value::<Mnemonic>(serde_json::json!({
+            "phrase": PHRASE,
+            "not_encrypted": true,
+            "derivation_paths": ["m/44'/60'/x"],
+        }))
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("invalid derivation path 'm/44'/60'/x'"));
+    }
+}
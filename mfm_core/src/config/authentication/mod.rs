@@ -1,15 +1,118 @@
+pub mod keystore;
+pub mod mnemonic;
+pub mod remote;
 pub mod wallet;
 
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
 
-use self::wallet::Wallet;
+use self::{mnemonic::Mnemonic, remote::Remote, wallet::Wallet};
+use crate::signer::{local::LocalSigner, remote::RemoteSigner, Signer, SignerError};
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum Method {
     Wallet(Wallet),
+    Mnemonic(Mnemonic),
+    Remote(Remote),
     MetaMask, // TODO: the next auth method
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
-pub struct Methods(Vec<Method>);
+impl Method {
+    /// Unlocks the keys of the method into one signer per account, reading the
+    /// passwords from the environment or the terminal.
+    pub fn signers(&self) -> Result<Vec<Box<dyn Signer>>, SignerError> {
+        let local = |key| Box::new(LocalSigner::new(key)) as Box<dyn Signer>;
+
+        match self {
+            Self::Wallet(wallet) => Ok(vec![local(wallet.unlock()?)]),
+            Self::Mnemonic(mnemonic) => Ok(mnemonic.unlock()?.into_iter().map(local).collect()),
+            Self::Remote(remote) => Ok(vec![Box::new(RemoteSigner::new(remote))]),
+            Self::MetaMask => Err(SignerError::Unsupported(
+                "meta_mask signs in the browser".to_string(),
+            )),
+        }
+    }
+}
+
+/// A named entry of `auth_methods`, e.g. one per portfolio.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AuthMethod {
+    pub name: String,
+    /// The networks the method signs on; all of them when not set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub networks: Vec<String>,
+    #[serde(flatten)]
+    pub method: Method,
+}
+
+impl AuthMethod {
+    pub fn allows(&self, network_id: &str) -> bool {
+        self.networks.is_empty() || self.networks.iter().any(|id| id == network_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct Methods(Vec<AuthMethod>);
+impl Methods {
+    pub fn list(&self) -> &[AuthMethod] {
+        &self.0
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AuthMethod> {
+        self.0.iter().find(|method| method.name == name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::loader::from_toml_str;
+
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/config.toml");
+
+    #[test]
+    fn test_signers() {
+        let config = from_toml_str(FIXTURE, None).unwrap();
+        let addresses: Vec<Vec<String>> = config
+            .auth_methods
+            .list()
+            .iter()
+            .map(|auth_method| {
+                let signers = auth_method.method.signers().unwrap();
+                signers.iter().map(|s| s.address().to_string()).collect()
+            })
+            .collect();
+
+        assert_eq!(
+            addresses,
+            vec![
+                vec!["0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"],
+                vec![
+                    "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+                ],
+            ]
+        );
+
+        assert!(matches!(
+            Method::MetaMask.signers(),
+            Err(SignerError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_named_methods() {
+        let config = from_toml_str(FIXTURE, None).unwrap();
+        let hardhat = config.auth_methods.get("hardhat").unwrap();
+        assert!(matches!(hardhat.method, Method::Mnemonic(_)));
+        assert!(hardhat.allows("bsc"));
+        assert!(!hardhat.allows("eth"));
+
+        let deployer = config.auth_methods.get("deployer").unwrap();
+        assert!(deployer.networks.is_empty());
+        assert!(deployer.allows("eth"));
+        assert!(config.auth_methods.get("ledger").is_none());
+    }
+}
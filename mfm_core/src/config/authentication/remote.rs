@@ -0,0 +1,20 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::config::address::Address;
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+/// An external signer keeping the key out of the process, reached over HTTP
+/// JSON-RPC with the clef `account_*` methods.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Remote {
+    pub url: String,
+    /// The account the signer is asked to sign for.
+    pub address: Address,
+    /// How long a request waits, including for its manual approval.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
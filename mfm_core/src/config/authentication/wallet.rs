@@ -1,26 +1,311 @@
+use std::{fmt, io};
+
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use rand::rngs::OsRng;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tari_utilities::SafePassword;
+use zeroize::Zeroizing;
+
+use super::keystore::{Keystore, KeystoreError};
+use crate::config::{
+    address::{keccak256, Address},
+    secret::Secret,
+};
+
+#[derive(Debug)]
+pub enum WalletError {
+    MissingPassword(String),
+    Prompt(io::Error),
+    InvalidKey,
+    InvalidMnemonic(String),
+    Keystore(KeystoreError),
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPassword(var) => {
+                write!(f, "the wallet password env var {} is not set", var)
+            }
+            Self::Prompt(e) => write!(f, "failed to prompt the wallet password: {}", e),
+            // never echo the key, not even partially
+            Self::InvalidKey => write!(
+                f,
+                "a private key has 64 hex digits of a non zero secp256k1 scalar"
+            ),
+            Self::InvalidMnemonic(e) => write!(f, "invalid mnemonic: {}", e),
+            Self::Keystore(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for WalletError {}
+
+impl From<KeystoreError> for WalletError {
+    fn from(e: KeystoreError) -> Self {
+        Self::Keystore(e)
+    }
+}
+
+/// A secp256k1 private key, zeroized on drop.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PrivateKey(Zeroizing<[u8; 32]>);
+
+impl PrivateKey {
+    pub fn random() -> Self {
+        let secret = k256::SecretKey::random(&mut OsRng);
+        Self(Zeroizing::new(secret.to_bytes().into()))
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, WalletError> {
+        let bytes: &[u8; 32] = bytes.try_into().map_err(|_| WalletError::InvalidKey)?;
+        let key = Self(Zeroizing::new(*bytes));
+        key.secret_key()?;
+        Ok(key)
+    }
+
+    /// Parses 64 hex digits, with or without the 0x prefix.
+    pub fn from_hex(hex: &[u8]) -> Result<Self, WalletError> {
+        let hex = hex.trim_ascii();
+        let hex = hex.strip_prefix(b"0x").unwrap_or(hex);
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+        if hex.len() != 64 {
+            return Err(WalletError::InvalidKey);
+        }
+
+        let nibble = |c: u8| (c as char).to_digit(16).ok_or(WalletError::InvalidKey);
+        let mut key = Zeroizing::new([0u8; 32]);
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = (nibble(hex[2 * i])? << 4 | nibble(hex[2 * i + 1])?) as u8;
+        }
+
+        Self::from_slice(key.as_ref())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    pub(crate) fn secret_key(&self) -> Result<k256::SecretKey, WalletError> {
+        k256::SecretKey::from_bytes(self.0.as_ref().into()).map_err(|_| WalletError::InvalidKey)
+    }
+
+    /// The account address, from the last 20 bytes of the keccak256 hash of the
+    /// uncompressed public key.
+    pub fn address(&self) -> Address {
+        let public_key = self
+            .secret_key()
+            .expect("checked on construction")
+            .public_key()
+            .to_encoded_point(false);
+        let hash = keccak256(&public_key.as_bytes()[1..]);
+
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&hash[12..]);
+        Address::new(address)
+    }
+}
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PrivateKey(<redacted>)")
+    }
+}
+
+/// An EVM account key, either in plain hex when `not_encrypted` is set, or
+/// otherwise as an Ethereum V3 keystore json.
+///
+/// The keystore password is read from the env var named by `env_password`, or
+/// prompted for when it's not set.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Wallet {
-    #[serde(deserialize_with = "deserialize_safe_password")]
-    pub private_key: SafePassword,
+    #[schemars(with = "String")]
+    pub private_key: Secret,
     pub not_encrypted: bool,
     pub env_password: Option<String>,
 }
 
 impl PartialEq for Wallet {
     fn eq(&self, other: &Self) -> bool {
-        self.not_encrypted == other.not_encrypted && self.env_password == other.env_password
+        self.private_key == other.private_key
+            && self.not_encrypted == other.not_encrypted
+            && self.env_password == other.env_password
     }
 }
 
 impl Eq for Wallet {}
 
-fn deserialize_safe_password<'de, D>(deserializer: D) -> Result<SafePassword, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let password: String = Deserialize::deserialize(deserializer)?;
-    Ok(SafePassword::from(password))
+/// The keystore password, from the `env_password` env var when set, or from
+/// `prompt` otherwise.
+pub fn read_password(
+    env_password: &Option<String>,
+    env: impl Fn(&str) -> Option<String>,
+    prompt: impl FnOnce(&str) -> io::Result<String>,
+) -> Result<Zeroizing<String>, WalletError> {
+    match env_password {
+        Some(var) => env(var)
+            .map(Zeroizing::new)
+            .ok_or_else(|| WalletError::MissingPassword(var.clone())),
+        None => prompt("wallet password: ")
+            .map(Zeroizing::new)
+            .map_err(WalletError::Prompt),
+    }
+}
+
+/// Reads the password from the environment, or prompts for it on the terminal.
+pub(crate) fn read_password_interactive(
+    env_password: &Option<String>,
+) -> Result<Zeroizing<String>, WalletError> {
+    read_password(
+        env_password,
+        |var| std::env::var(var).ok(),
+        |prompt: &str| rpassword::prompt_password(prompt),
+    )
+}
+
+/// Decrypts a secret held as a V3 keystore json.
+pub(crate) fn decrypt_keystore(
+    keystore: &Secret,
+    password: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, WalletError> {
+    let keystore = std::str::from_utf8(keystore.reveal())
+        .map_err(|e| KeystoreError::Json(e.to_string()))
+        .and_then(Keystore::from_json)?;
+    Ok(keystore.decrypt(password)?)
+}
+
+impl Wallet {
+    pub fn password(
+        &self,
+        env: impl Fn(&str) -> Option<String>,
+        prompt: impl FnOnce(&str) -> io::Result<String>,
+    ) -> Result<Zeroizing<String>, WalletError> {
+        read_password(&self.env_password, env, prompt)
+    }
+
+    /// Decrypts the key with the given password, which is ignored for a key
+    /// that's `not_encrypted`.
+    pub fn decrypt(&self, password: &[u8]) -> Result<PrivateKey, WalletError> {
+        match self.not_encrypted {
+            true => PrivateKey::from_hex(self.private_key.reveal()),
+            false => PrivateKey::from_slice(&decrypt_keystore(&self.private_key, password)?),
+        }
+    }
+
+    /// Unlocks the key, reading the password from the environment or the terminal.
+    pub fn unlock(&self) -> Result<PrivateKey, WalletError> {
+        if self.not_encrypted {
+            return self.decrypt(&[]);
+        }
+
+        let password = read_password_interactive(&self.env_password)?;
+        self.decrypt(password.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::authentication::keystore::Kdf;
+
+    use super::*;
+
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    fn encrypted_wallet(password: &str, env_password: Option<&str>) -> Wallet {
+        let key = PrivateKey::from_hex(KEY.as_bytes()).unwrap();
+        let keystore =
+            Keystore::encrypt(key.as_bytes(), password.as_bytes(), Kdf::light()).unwrap();
+
+        Wallet {
+            private_key: Secret::from(keystore.to_json()),
+            not_encrypted: false,
+            env_password: env_password.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn test_private_key_from_hex() {
+        let key = PrivateKey::from_hex(format!("0x{}\n", KEY).as_bytes()).unwrap();
+        assert_eq!(key.as_bytes()[0], 0xac);
+        assert_eq!(format!("{:?}", key), "PrivateKey(<redacted>)");
+
+        assert!(matches!(
+            PrivateKey::from_hex(&KEY.as_bytes()[1..]),
+            Err(WalletError::InvalidKey)
+        ));
+        assert!(matches!(
+            PrivateKey::from_hex(KEY.replace('a', "z").as_bytes()),
+            Err(WalletError::InvalidKey)
+        ));
+        assert!(matches!(
+            PrivateKey::from_slice(&[0u8; 32]),
+            Err(WalletError::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn test_address() {
+        let key = PrivateKey::from_hex(KEY.as_bytes()).unwrap();
+        assert_eq!(
+            key.address().to_string(),
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+        );
+        assert_ne!(PrivateKey::random(), PrivateKey::random());
+    }
+
+    #[test]
+    fn test_unlock_not_encrypted() {
+        let wallet = Wallet {
+            private_key: Secret::from(KEY),
+            not_encrypted: true,
+            env_password: None,
+        };
+        assert_eq!(
+            wallet.unlock().unwrap(),
+            PrivateKey::from_hex(KEY.as_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_password_sources() {
+        let from_env = encrypted_wallet("secret", Some("MFM_WALLET_PASSWORD"));
+        let password = from_env
+            .password(
+                |var| (var == "MFM_WALLET_PASSWORD").then(|| "secret".to_string()),
+                |_| panic!("the env var is set"),
+            )
+            .unwrap();
+        assert_eq!(password.as_str(), "secret");
+
+        let missing = from_env.password(|_| None, |_| Ok("prompted".to_string()));
+        assert!(matches!(missing, Err(WalletError::MissingPassword(_))));
+
+        let prompted = encrypted_wallet("secret", None)
+            .password(|_| panic!("no env var"), |_| Ok("prompted".to_string()))
+            .unwrap();
+        assert_eq!(prompted.as_str(), "prompted");
+    }
+
+    #[test]
+    fn test_decrypt_keystore() {
+        let wallet = encrypted_wallet("secret", None);
+        assert_eq!(
+            wallet.decrypt(b"secret").unwrap(),
+            PrivateKey::from_hex(KEY.as_bytes()).unwrap()
+        );
+        assert!(matches!(
+            wallet.decrypt(b"wrong"),
+            Err(WalletError::Keystore(KeystoreError::WrongPassword))
+        ));
+
+        let not_a_keystore = Wallet {
+            private_key: Secret::from(KEY),
+            not_encrypted: false,
+            env_password: None,
+        };
+        assert!(matches!(
+            not_a_keystore.decrypt(b"secret"),
+            Err(WalletError::Keystore(KeystoreError::Json(_)))
+        ));
+    }
 }
@@ -1,21 +1,24 @@
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+use super::address::Address;
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub enum Kind {
     UniswapV2,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct Dex {
     pub name: String,
     pub kind: Kind,
-    pub router_address: String,
-    pub factory_address: String,
+    pub router_address: Address,
+    pub factory_address: Address,
     pub network_id: String,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct Dexes(HashMap<String, Dex>);
 impl Dexes {
     pub fn hashmap(&self) -> &HashMap<String, Dex> {
@@ -0,0 +1,919 @@
+use std::{
+    fmt, fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::contexts::{ConfigFormat, ConfigSource};
+
+use super::Config;
+
+/// Environment variables with this prefix override config values, e.g.
+/// `MFM_NETWORKS__BSC__NODE_URL` sets `networks.bsc.node_url`.
+pub const ENV_PREFIX: &str = "MFM_";
+/// Separates the path segments in the name of an override environment variable.
+pub const ENV_SEPARATOR: &str = "__";
+/// Selects the profile to apply; it isn't an override.
+pub const PROFILE_ENV: &str = "MFM_PROFILE";
+/// The top level keys of a [`Config`], the only ones an environment variable
+/// overrides; other `MFM_`-prefixed variables, e.g. a keystore password, are ignored.
+pub const ENV_OVERRIDE_KEYS: [&str; 4] = ["networks", "dexes", "tokens", "auth_methods"];
+/// Top level key listing the files a config source includes.
+pub const INCLUDE_KEY: &str = "include";
+/// Top level key of the named overlays, e.g. `[profiles.testnet.networks.bsc]`.
+pub const PROFILES_KEY: &str = "profiles";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(ParseError),
+}
+
+/// A config that couldn't be deserialized, with the position of the problem when known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// the file path, or `<inline>`, `<stdin>`, `<merged>` or the environment variable name
+    pub origin: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "{}:{}:{}: {}", self.origin, line, column, self.message)
+            }
+            _ => write!(f, "{}: {}", self.origin, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "failed to read config {}: {}", path.display(), e),
+            Self::Parse(e) => write!(f, "failed to parse config {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, e) => Some(e),
+            Self::Parse(e) => Some(e),
+        }
+    }
+}
+
+// 1-based line and column of a byte offset
+pub(crate) fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
+
+// finds `key` as a whole word, so `bsc` doesn't match `bsc_fork`
+fn find_key(content: &str, key: &str) -> Option<usize> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+
+    content.match_indices(key).map(|(i, _)| i).find(|&i| {
+        let before = content[..i].chars().next_back();
+        let after = content[i + key.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
+
+/// Best effort 1-based line and column of a dotted config path, like the ones of
+/// the validation errors, in the content of a config file.
+///
+/// Each key is looked up after the previous one, which fits the usual layout of
+/// the TOML tables and the YAML or JSON objects. When a key is missing, the position
+/// of the closest parent found is returned.
+pub fn locate(content: &str, path: &str) -> Option<(usize, usize)> {
+    let mut found = None;
+    let mut offset = 0;
+
+    for key in path.split('.') {
+        match find_key(&content[offset..], key) {
+            Some(position) => {
+                offset += position;
+                found = Some(offset);
+            }
+            None => break,
+        }
+    }
+
+    found.map(|offset| line_column(content, offset))
+}
+
+fn parse_error(
+    origin: &str,
+    position: Option<(usize, usize)>,
+    message: impl ToString,
+) -> ConfigError {
+    ConfigError::Parse(ParseError {
+        origin: origin.to_string(),
+        line: position.map(|(line, _)| line),
+        column: position.map(|(_, column)| column),
+        message: message.to_string().trim().to_string(),
+    })
+}
+
+/// The content of a single config source.
+pub struct Layer {
+    pub format: ConfigFormat,
+    pub content: String,
+    pub origin: String,
+    /// the file the content was read from, if any
+    pub path: Option<PathBuf>,
+    /// where the included files are looked up
+    pub dir: Option<PathBuf>,
+}
+
+impl Layer {
+    pub fn new(format: ConfigFormat, content: String, origin: &str) -> Self {
+        Self {
+            format,
+            content,
+            origin: origin.to_string(),
+            path: None,
+            dir: None,
+        }
+    }
+
+    pub fn from_file(format: ConfigFormat, path: &Path) -> Result<Self, ConfigError> {
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            dir: path.parent().map(Path::to_path_buf),
+            ..Self::new(format, read_file(path)?, &origin_of(path))
+        })
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, ConfigError> {
+        let content = &self.content;
+
+        match self.format {
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| {
+                let position = e.span().map(|span| line_column(content, span.start));
+                parse_error(&self.origin, position, e.message())
+            }),
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| {
+                let position = e.location().map(|l| (l.line(), l.column()));
+                parse_error(&self.origin, position, e)
+            }),
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| {
+                let position = Some((e.line(), e.column())).filter(|(line, _)| *line > 0);
+                parse_error(&self.origin, position, e)
+            }),
+        }
+    }
+}
+
+pub fn from_toml_str(source: &str, path: Option<&Path>) -> Result<Config, ConfigError> {
+    let origin = path
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| "<inline>".to_string());
+    Layer::new(ConfigFormat::Toml, source.to_string(), &origin).parse()
+}
+
+pub fn read_file(path: &Path) -> Result<String, ConfigError> {
+    fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))
+}
+
+// objects are merged key by key; anything else in the overlay replaces the base
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+struct EnvOverride {
+    name: String,
+    path: Vec<String>,
+    value: Value,
+}
+
+// values are parsed as JSON when valid (so `56` is a number and `'"56"'` a string),
+// and taken as strings otherwise
+fn env_overrides(env: &[(String, String)]) -> Vec<EnvOverride> {
+    let mut overrides: Vec<EnvOverride> = env
+        .iter()
+        .filter(|(name, _)| name != PROFILE_ENV)
+        .filter_map(|(name, value)| {
+            let path: Vec<String> = name
+                .strip_prefix(ENV_PREFIX)?
+                .split(ENV_SEPARATOR)
+                .map(str::to_lowercase)
+                .collect();
+
+            if path.iter().any(String::is_empty) {
+                return None;
+            }
+            if !ENV_OVERRIDE_KEYS.contains(&path[0].as_str()) {
+                tracing::warn!(
+                    variable = %name,
+                    "ignored, '{}' isn't a config key to override",
+                    path[0]
+                );
+                return None;
+            }
+
+            Some(EnvOverride {
+                name: name.clone(),
+                path,
+                value: serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.clone())),
+            })
+        })
+        .collect();
+
+    // the less specific overrides are applied first, so
+    // `MFM_NETWORKS__BSC__NODE_URL` wins over a whole `MFM_NETWORKS__BSC`
+    overrides.sort_by(|a, b| a.path.len().cmp(&b.path.len()).then(a.name.cmp(&b.name)));
+    overrides
+}
+
+fn apply_env_override(root: &mut Value, env_override: EnvOverride) -> Result<(), ConfigError> {
+    let mut current = root;
+
+    for segment in env_override.path.iter() {
+        current = match current {
+            Value::Array(items) => {
+                let item = segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get_mut(index));
+
+                match item {
+                    Some(item) => item,
+                    None => {
+                        return Err(parse_error(
+                            &env_override.name,
+                            None,
+                            format!("'{}' is not an index of the overridden list", segment),
+                        ))
+                    }
+                }
+            }
+            value => {
+                if !value.is_object() {
+                    *value = Value::Object(Map::new());
+                }
+
+                match value {
+                    Value::Object(map) => map.entry(segment.clone()).or_insert(Value::Null),
+                    _ => unreachable!("value was replaced by an object"),
+                }
+            }
+        };
+    }
+
+    merge(current, env_override.value);
+    Ok(())
+}
+
+fn origin_of(path: &Path) -> String {
+    path.display().to_string()
+}
+
+fn includes(layer: &Layer, value: &mut Value) -> Result<Vec<PathBuf>, ConfigError> {
+    let include = match value.as_object_mut().and_then(|m| m.remove(INCLUDE_KEY)) {
+        Some(include) => include,
+        None => return Ok(vec![]),
+    };
+
+    let paths: Vec<String> = match include {
+        Value::String(path) => vec![path],
+        include => serde_json::from_value(include).map_err(|_| {
+            parse_error(
+                &layer.origin,
+                None,
+                "`include` must be a path or a list of paths",
+            )
+        })?,
+    };
+
+    let dir = layer.dir.clone().unwrap_or_default();
+    Ok(paths.iter().map(|path| dir.join(path)).collect())
+}
+
+// the origin and content of the layer setting the value at `path`: the layer itself,
+// or else the files it includes, the last one first
+fn find_layer(
+    layer: &Layer,
+    path: &[String],
+    stack: &mut Vec<PathBuf>,
+) -> Option<(String, String)> {
+    let mut value: Value = layer.parse().ok()?;
+    let included = includes(layer, &mut value).ok()?;
+
+    let found = path.iter().try_fold(&value, |value, key| match value {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        value => value.get(key),
+    });
+    if found.is_some() {
+        return Some((layer.origin.clone(), layer.content.clone()));
+    }
+
+    included.iter().rev().find_map(|file| {
+        let key = fs::canonicalize(file).unwrap_or_else(|_| file.clone());
+        let format = ConfigFormat::from_path(file)?;
+        if stack.contains(&key) {
+            return None;
+        }
+
+        stack.push(key);
+        let found = Layer::from_file(format, file)
+            .ok()
+            .and_then(|included| find_layer(&included, path, stack));
+        stack.pop();
+        found
+    })
+}
+
+/// Loads a [`Config`] from a [`ConfigSource`].
+///
+/// The precedence, from lowest to highest, is:
+/// - the files listed in the `include` key of a source, in order, each one below the
+///   source including it; their paths are relative to the including file
+/// - the sources of a `ConfigSource::Layered`, in order
+/// - the `profiles.<name>` section selected with `ConfigSource::Profile`, or else the
+///   `MFM_PROFILE` environment variable
+/// - the `MFM_`-prefixed environment variables of the [`ENV_OVERRIDE_KEYS`]
+///
+/// Maps, like `networks`, `dexes` and `tokens`, are merged entry by entry and field by
+/// field, while lists, like `auth_methods`, and plain values are replaced.
+pub struct ConfigLoader {
+    env: Vec<(String, String)>,
+    stdin: Option<String>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        Self {
+            env: std::env::vars().collect(),
+            stdin: None,
+        }
+    }
+
+    /// Replaces the environment variables read from the process.
+    pub fn env<I: IntoIterator<Item = (String, String)>>(mut self, vars: I) -> Self {
+        self.env = vars.into_iter().collect();
+        self
+    }
+
+    /// Replaces the content read from the process stdin.
+    pub fn stdin(mut self, content: impl Into<String>) -> Self {
+        self.stdin = Some(content.into());
+        self
+    }
+
+    fn read_stdin(&self) -> Result<String, ConfigError> {
+        if let Some(content) = &self.stdin {
+            return Ok(content.clone());
+        }
+
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| ConfigError::Io(PathBuf::from("<stdin>"), e))?;
+        Ok(content)
+    }
+
+    pub fn layers(&self, source: &ConfigSource) -> Result<Vec<Layer>, ConfigError> {
+        let file =
+            |format, path: &String| Layer::from_file(format, Path::new(path)).map(|l| vec![l]);
+
+        match source {
+            ConfigSource::TomlFile(path) => file(ConfigFormat::Toml, path),
+            ConfigSource::YamlFile(path) => file(ConfigFormat::Yaml, path),
+            ConfigSource::JsonFile(path) => file(ConfigFormat::Json, path),
+            ConfigSource::Inline(format, content) => {
+                Ok(vec![Layer::new(*format, content.clone(), "<inline>")])
+            }
+            ConfigSource::Stdin(format) => {
+                Ok(vec![Layer::new(*format, self.read_stdin()?, "<stdin>")])
+            }
+            ConfigSource::Layered(sources) => sources.iter().try_fold(Vec::new(), |mut acc, s| {
+                acc.extend(self.layers(s)?);
+                Ok(acc)
+            }),
+            ConfigSource::Profile(_, source) => self.layers(source),
+        }
+    }
+
+    // the last profile found in the source wins over the environment
+    fn profile(&self, source: &ConfigSource) -> Option<String> {
+        fn find(source: &ConfigSource) -> Option<&String> {
+            match source {
+                ConfigSource::Profile(name, source) => find(source).or(Some(name)),
+                ConfigSource::Layered(sources) => sources.iter().rev().find_map(find),
+                _ => None,
+            }
+        }
+
+        find(source).cloned().or_else(|| {
+            self.env
+                .iter()
+                .find(|(name, _)| name == PROFILE_ENV)
+                .map(|(_, profile)| profile.clone())
+        })
+    }
+
+    // parses a layer, merged over the files it includes
+    fn resolve(&self, layer: &Layer, stack: &mut Vec<PathBuf>) -> Result<Value, ConfigError> {
+        let mut value: Value = layer.parse()?;
+        let mut merged = Value::Object(Map::new());
+
+        for path in includes(layer, &mut value)? {
+            let key = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if stack.contains(&key) {
+                return Err(parse_error(
+                    &layer.origin,
+                    None,
+                    format!("include cycle through {}", origin_of(&path)),
+                ));
+            }
+
+            let format = ConfigFormat::from_path(&path).ok_or_else(|| {
+                parse_error(
+                    &layer.origin,
+                    None,
+                    format!(
+                        "unknown format of the included {}; expected a .toml, .yaml, .yml or .json file",
+                        origin_of(&path)
+                    ),
+                )
+            })?;
+
+            stack.push(key);
+            merge(
+                &mut merged,
+                self.resolve(&Layer::from_file(format, &path)?, stack)?,
+            );
+            stack.pop();
+        }
+
+        merge(&mut merged, value);
+        Ok(merged)
+    }
+
+    pub fn load(&self, source: &ConfigSource) -> Result<Config, ConfigError> {
+        let layers = self.layers(source)?;
+        let overrides = env_overrides(&self.env);
+        let profile = self.profile(source);
+
+        // a plain single source is deserialized straight from its content,
+        // to keep the line and column of the errors
+        if let ([layer], true, None) = (layers.as_slice(), overrides.is_empty(), &profile) {
+            let value: Value = layer.parse()?;
+            if value.get(INCLUDE_KEY).is_none() && value.get(PROFILES_KEY).is_none() {
+                return layer.parse();
+            }
+        }
+
+        serde_json::from_value(self.merged(&layers, overrides, profile)?)
+            .map_err(|e| parse_error("<merged>", None, e))
+    }
+
+    /// The config document once its layers, includes, profile and environment
+    /// overrides are merged, before it's deserialized.
+    pub fn load_value(&self, source: &ConfigSource) -> Result<Value, ConfigError> {
+        let layers = self.layers(source)?;
+        self.merged(&layers, env_overrides(&self.env), self.profile(source))
+    }
+
+    /// The origin, and the line and column in it, of the value at a dotted `path` of
+    /// [`Self::load_value`], looked up in the file or included file whose value wins
+    /// the merge.
+    ///
+    /// `None` when an environment variable sets the value, or no source has it.
+    pub fn position(&self, source: &ConfigSource, path: &str) -> Option<(String, usize, usize)> {
+        let keys: Vec<String> = path
+            .split('.')
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect();
+        let overridden = env_overrides(&self.env)
+            .iter()
+            .any(|o| keys.starts_with(&o.path));
+        if keys.is_empty() || overridden {
+            return None;
+        }
+
+        // the profile section wins over the rest of the document
+        let mut paths = vec![keys.clone()];
+        if let Some(profile) = self.profile(source) {
+            paths.insert(0, [vec![PROFILES_KEY.to_string(), profile], keys].concat());
+        }
+
+        let layers = self.layers(source).ok()?;
+        paths.iter().find_map(|path| {
+            let (origin, content) = layers
+                .iter()
+                .rev()
+                .find_map(|layer| find_layer(layer, path, &mut vec![]))?;
+            let (line, column) = locate(&content, &path.join("."))?;
+            Some((origin, line, column))
+        })
+    }
+
+    fn merged(
+        &self,
+        layers: &[Layer],
+        overrides: Vec<EnvOverride>,
+        profile: Option<String>,
+    ) -> Result<Value, ConfigError> {
+        let mut merged = Value::Object(Map::new());
+        for layer in layers.iter() {
+            let mut stack: Vec<PathBuf> = layer
+                .path
+                .iter()
+                .map(|path| fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+                .collect();
+            merge(&mut merged, self.resolve(layer, &mut stack)?);
+        }
+
+        let profiles = merged.as_object_mut().and_then(|m| m.remove(PROFILES_KEY));
+
+        if let Some(profile) = profile {
+            let overlay = profiles
+                .as_ref()
+                .and_then(|profiles| profiles.get(&profile))
+                .ok_or_else(|| {
+                    parse_error(
+                        PROFILES_KEY,
+                        None,
+                        format!("profile '{}' is not defined", profile),
+                    )
+                })?;
+            merge(&mut merged, overlay.clone());
+        }
+
+        for env_override in overrides {
+            apply_env_override(&mut merged, env_override)?;
+        }
+
+        Ok(merged)
+    }
+}
+
+pub fn load(source: &ConfigSource) -> Result<Config, ConfigError> {
+    ConfigLoader::new().load(source)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    pub const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/config.toml");
+    pub const YAML_FIXTURE: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/config.yaml");
+    pub const JSON_FIXTURE: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/config.json");
+
+    fn loader() -> ConfigLoader {
+        ConfigLoader::new().env(vec![])
+    }
+
+    #[test]
+    fn test_load_toml_file() {
+        let config = loader()
+            .load(&ConfigSource::TomlFile(FIXTURE.to_string()))
+            .unwrap();
+
+        let bsc = config.networks.get("bsc").unwrap();
+        assert_eq!(bsc.chain_id, 56);
+        assert_eq!(
+            config.dexes.get("pancake_swap_v2").unwrap().network_id,
+            "bsc"
+        );
+        assert!(config.tokens.get("wbnb").is_some());
+    }
+
+    #[test]
+    fn test_load_all_formats() {
+        let toml = loader()
+            .load(&ConfigSource::TomlFile(FIXTURE.to_string()))
+            .unwrap();
+        let yaml = loader()
+            .load(&ConfigSource::YamlFile(YAML_FIXTURE.to_string()))
+            .unwrap();
+        let json = loader()
+            .load(&ConfigSource::JsonFile(JSON_FIXTURE.to_string()))
+            .unwrap();
+        let stdin = loader()
+            .stdin(read_file(Path::new(JSON_FIXTURE)).unwrap())
+            .load(&ConfigSource::Stdin(ConfigFormat::Json))
+            .unwrap();
+
+        assert_eq!(toml, yaml);
+        assert_eq!(toml, json);
+        assert_eq!(toml, stdin);
+    }
+
+    #[test]
+    fn test_load_layered_with_env_overrides() {
+        let source = ConfigSource::Layered(vec![
+            ConfigSource::TomlFile(FIXTURE.to_string()),
+            ConfigSource::Inline(
+                ConfigFormat::Yaml,
+                "networks:\n  bsc:\n    chain_id: 97\n    min_balance_coin: 1.5\n".to_string(),
+            ),
+        ]);
+
+        let config = loader()
+            .env(vec![
+                (
+                    "MFM_NETWORKS__BSC__CHAIN_ID".to_string(),
+                    "1337".to_string(),
+                ),
+                (
+                    "MFM_NETWORKS__BSC__NODE_URL".to_string(),
+                    "http://localhost:8545".to_string(),
+                ),
+                (
+                    "MFM_AUTH_METHODS__0__ENV_PASSWORD".to_string(),
+                    "WALLET_PASSWORD".to_string(),
+                ),
+                ("OTHER_NETWORKS__BSC__CHAIN_ID".to_string(), "1".to_string()),
+                // not a config key, e.g. a password read by an auth method
+                ("MFM_KEYSTORE_PASSWORD".to_string(), "hunter2".to_string()),
+            ])
+            .load(&source)
+            .unwrap();
+
+        let bsc = config.networks.get("bsc").unwrap();
+        // the env wins over the inline layer, which wins over the file
+        assert_eq!(bsc.chain_id, 1337);
+        assert_eq!(bsc.min_balance_coin.to_string(), "1.5");
+        assert_eq!(bsc.node_url, "http://localhost:8545");
+        // untouched values come from the file
+        assert_eq!(bsc.symbol, "bnb");
+    }
+
+    #[test]
+    fn test_env_override_errors() {
+        let result = loader()
+            .env(vec![(
+                "MFM_AUTH_METHODS__3__ENV_PASSWORD".to_string(),
+                "WALLET_PASSWORD".to_string(),
+            )])
+            .load(&ConfigSource::TomlFile(FIXTURE.to_string()));
+
+        match result {
+            Err(ConfigError::Parse(e)) => {
+                assert_eq!(e.origin, "MFM_AUTH_METHODS__3__ENV_PASSWORD")
+            }
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_env_overrides_only_config_keys() {
+        let env = [
+            ("MFM_TOKENS__BUSD__KIND", "ERC20"),
+            ("MFM_KEYSTORE_PASSWORD", "hunter2"),
+            ("MFM_INCLUDE", "[\"other.toml\"]"),
+            ("MFM_PROFILE", "testnet"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        let overrides = env_overrides(&env);
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides[0].path, vec!["tokens", "busd", "kind"]);
+    }
+
+    const PROFILES_FIXTURE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/profiles/config.toml"
+    );
+
+    #[test]
+    fn test_load_includes() {
+        let config = loader()
+            .load(&ConfigSource::TomlFile(PROFILES_FIXTURE.to_string()))
+            .unwrap();
+        let single_file = loader()
+            .load(&ConfigSource::TomlFile(FIXTURE.to_string()))
+            .unwrap();
+
+        // the including file wins over the included ones
+        let bsc = config.networks.get("bsc").unwrap();
+        assert_eq!(bsc.min_balance_coin.to_string(), "0.5");
+        assert_eq!(bsc.chain_id, 56);
+        assert_eq!(config.tokens, single_file.tokens);
+        assert_eq!(config.dexes, single_file.dexes);
+        assert_eq!(config.auth_methods, single_file.auth_methods);
+    }
+
+    #[test]
+    fn test_load_profiles() {
+        let profile = |name: &str| {
+            ConfigSource::Profile(
+                name.to_string(),
+                Box::new(ConfigSource::TomlFile(PROFILES_FIXTURE.to_string())),
+            )
+        };
+
+        let mainnet = loader().load(&profile("mainnet")).unwrap();
+        assert_eq!(mainnet.networks.get("bsc").unwrap().chain_id, 56);
+
+        let testnet = loader().load(&profile("testnet")).unwrap();
+        let bsc = testnet.networks.get("bsc").unwrap();
+        assert_eq!(bsc.chain_id, 97);
+        assert_eq!(bsc.min_balance_coin.to_string(), "0.5");
+        assert_eq!(bsc.symbol, "bnb");
+
+        // the env selects the profile when the source doesn't, and overrides it
+        let local = loader()
+            .env(vec![
+                ("MFM_PROFILE".to_string(), "local".to_string()),
+                (
+                    "MFM_NETWORKS__BSC__CHAIN_ID".to_string(),
+                    "31337".to_string(),
+                ),
+            ])
+            .load(&ConfigSource::TomlFile(PROFILES_FIXTURE.to_string()))
+            .unwrap();
+        let bsc = local.networks.get("bsc").unwrap();
+        assert_eq!(bsc.node_url, "http://localhost:8545");
+        assert_eq!(bsc.chain_id, 31337);
+
+        let source_wins = loader()
+            .env(vec![("MFM_PROFILE".to_string(), "local".to_string())])
+            .load(&profile("testnet"))
+            .unwrap();
+        assert_eq!(source_wins, testnet);
+
+        match loader().load(&profile("staging")) {
+            Err(ConfigError::Parse(e)) => {
+                assert_eq!(e.to_string(), "profiles: profile 'staging' is not defined")
+            }
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let cycle = loader().load(&ConfigSource::TomlFile(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/profiles/cycle_a.toml"
+            )
+            .to_string(),
+        ));
+        match cycle {
+            Err(ConfigError::Parse(e)) => assert!(e.message.starts_with("include cycle")),
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+
+        let unknown_format = loader().load(&ConfigSource::Inline(
+            ConfigFormat::Toml,
+            "include = [\"config.ini\"]".to_string(),
+        ));
+        match unknown_format {
+            Err(ConfigError::Parse(e)) => assert!(e.message.starts_with("unknown format")),
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+
+        let missing = loader().load(&ConfigSource::Inline(
+            ConfigFormat::Toml,
+            "include = \"not_found.toml\"".to_string(),
+        ));
+        assert!(matches!(missing, Err(ConfigError::Io(_, _))));
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let result = loader().load(&ConfigSource::TomlFile("not_found.toml".to_string()));
+        assert!(matches!(result, Err(ConfigError::Io(_, _))));
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let source = "[networks.bsc]\nname = \"bsc\"\nchain_id = \"fifty six\"\n";
+
+        match from_toml_str(source, Some(Path::new("bad.toml"))) {
+            Err(ConfigError::Parse(e)) => {
+                assert_eq!(e.line, Some(3));
+                assert_eq!(e.column, Some(12));
+                assert!(e.to_string().starts_with("bad.toml:3:12: "));
+            }
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+
+        // addresses are checked at load time
+        let bad_address = read_file(Path::new(FIXTURE)).unwrap().replace(
+            "0x10ED43C718714eb63d5aA57B78B54704E256024E",
+            "0x10ED43C718714eb63d5aA57B78B54704E256024e",
+        );
+        match from_toml_str(&bad_address, Some(Path::new("bad.toml"))) {
+            Err(ConfigError::Parse(e)) => {
+                assert_eq!(e.line, Some(25));
+                assert!(e.message.contains("invalid EIP-55 checksum"));
+            }
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+
+        let yaml = Layer::new(
+            ConfigFormat::Yaml,
+            "networks:\n  - [".to_string(),
+            "bad.yaml",
+        );
+        match yaml.parse::<Config>() {
+            Err(ConfigError::Parse(e)) => assert_eq!(e.line, Some(2)),
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+
+        let json = Layer::new(
+            ConfigFormat::Json,
+            "{\n  \"networks\": 1\n}".to_string(),
+            "bad.json",
+        );
+        match json.parse::<Config>() {
+            Err(ConfigError::Parse(e)) => assert_eq!(e.line, Some(2)),
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_locate() {
+        let toml = read_file(Path::new(FIXTURE)).unwrap();
+        assert_eq!(locate(&toml, "networks.bsc.chain_id"), Some((6, 1)));
+        assert_eq!(
+            locate(&toml, "tokens.busd.networks.bsc.slippage"),
+            Some((46, 1))
+        );
+        // the closest parent of a missing key
+        assert_eq!(locate(&toml, "tokens.busd.decimals"), Some((39, 9)));
+        assert_eq!(locate(&toml, "exchanges.uniswap"), None);
+
+        let yaml = read_file(Path::new(YAML_FIXTURE)).unwrap();
+        assert_eq!(
+            locate(&yaml, "tokens.busd.networks.bsc.slippage"),
+            Some((47, 9))
+        );
+
+        let json = read_file(Path::new(JSON_FIXTURE)).unwrap();
+        assert_eq!(
+            locate(&json, "dexes.pancake_swap_v2.router_address"),
+            Some((31, 8))
+        );
+    }
+
+    #[test]
+    fn test_position() {
+        let content =
+            "[networks.bsc]\nchain_id = 56\n\n[profiles.testnet.networks.bsc]\nchain_id = 97\n";
+        let source = ConfigSource::Inline(ConfigFormat::Toml, content.to_string());
+        let profile = ConfigSource::Profile("testnet".to_string(), Box::new(source.clone()));
+
+        assert_eq!(
+            loader().position(&source, "networks.bsc.chain_id"),
+            Some(("<inline>".to_string(), 2, 1))
+        );
+        // the profile sets the value
+        assert_eq!(
+            loader().position(&profile, "networks.bsc.chain_id"),
+            Some(("<inline>".to_string(), 5, 1))
+        );
+        assert_eq!(loader().position(&source, "dexes.uniswap"), None);
+
+        let env = vec![("MFM_NETWORKS__BSC__CHAIN_ID".to_string(), "1".to_string())];
+        assert_eq!(
+            loader().env(env).position(&source, "networks.bsc.chain_id"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_line_column() {
+        assert_eq!(line_column("abc", 0), (1, 1));
+        assert_eq!(line_column("abc\ndef", 5), (2, 2));
+    }
+}
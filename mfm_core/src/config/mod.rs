@@ -1,9 +1,16 @@
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
 
+pub mod address;
+pub mod amount;
 pub mod authentication;
 pub mod dexes;
+pub mod loader;
 pub mod network;
+pub mod schema;
+pub mod secret;
 pub mod token;
+pub mod validation;
 
 use dexes::Dexes;
 use network::Networks;
@@ -11,7 +18,7 @@ use token::Tokens;
 
 use self::authentication::Methods;
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct Config {
     pub networks: Networks,
     pub dexes: Dexes,
@@ -1,13 +1,15 @@
-use super::token::Token;
-use serde_derive::{Deserialize, Serialize};
+use super::{amount::Amount, token::Token};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_derive::Serialize;
 use std::collections::HashMap;
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub enum Kind {
     EVM,
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct Network {
     pub name: String,
     pub kind: Kind,
@@ -15,13 +17,44 @@ pub struct Network {
     pub decimals: Option<u8>,
     pub chain_id: u32,
     pub node_url: String,
-    pub node_url_failover: Option<String>,
+    /// Tried in order when `node_url` fails; a single url or a list.
+    #[serde(default, deserialize_with = "one_or_many")]
+    #[schemars(with = "Option<OneOrMany>")]
+    pub node_url_failover: Vec<String>,
     pub blockexplorer_url: Option<String>,
-    pub min_balance_coin: f64,
+    pub min_balance_coin: Amount,
     pub wrapped_asset: Option<Token>,
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+impl Network {
+    /// The node urls by priority: `node_url`, then the failovers.
+    pub fn node_urls(&self) -> Vec<String> {
+        std::iter::once(&self.node_url)
+            .chain(self.node_url_failover.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        Some(OneOrMany::One(url)) => vec![url],
+        Some(OneOrMany::Many(urls)) => urls,
+        None => vec![],
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct Networks(HashMap<String, Network>);
 impl Networks {
     pub fn get(&self, key: &str) -> Option<&Network> {
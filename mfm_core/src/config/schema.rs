@@ -0,0 +1,142 @@
+use schemars::{schema::RootSchema, schema_for};
+use serde_json::Value;
+
+use super::{
+    address::{is_address, ADDRESS_FORMAT},
+    authentication::AuthMethod,
+    validation::{ValidationError, ValidationErrorKind},
+    Config,
+};
+
+/// JSON Schema of a config file, with the networks, dexes, tokens and
+/// authentication methods in its definitions.
+pub fn config_schema() -> RootSchema {
+    schema_for!(Config)
+}
+
+/// JSON Schema of a single entry of `auth_methods`.
+pub fn method_schema() -> RootSchema {
+    schema_for!(AuthMethod)
+}
+
+/// Checks a config document, as merged by the loader, against [`config_schema`],
+/// returning every problem found at the dotted path of the offending value.
+pub fn validate_schema(config: &Value) -> Result<(), Vec<ValidationError>> {
+    let schema = serde_json::to_value(config_schema()).expect("the schema serializes");
+    let validator = jsonschema::options()
+        .should_validate_formats(true)
+        .with_format(ADDRESS_FORMAT, is_address)
+        .build(&schema)
+        .expect("the config schema is a valid JSON Schema");
+
+    let errors: Vec<ValidationError> = validator
+        .iter_errors(config)
+        .map(|e| ValidationError {
+            path: e
+                .instance_path
+                .as_str()
+                .trim_start_matches('/')
+                .replace('/', "."),
+            kind: ValidationErrorKind::Schema,
+            message: e.to_string(),
+        })
+        .collect();
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::loader::ConfigLoader;
+    use crate::contexts::{ConfigFormat, ConfigSource};
+
+    #[test]
+    fn test_config_schema() {
+        let schema = serde_json::to_value(config_schema()).unwrap();
+
+        assert_eq!(
+            schema["required"],
+            json!(["auth_methods", "dexes", "networks", "tokens"])
+        );
+        for definition in ["Network", "Dex", "Token", "TokenNetwork", "AuthMethod"] {
+            assert!(
+                schema["definitions"].get(definition).is_some(),
+                "missing the {} definition",
+                definition
+            );
+        }
+        // the wallet is inlined in the internally tagged method, next to the name
+        let auth_method = &schema["definitions"]["AuthMethod"];
+        assert_eq!(auth_method["required"], json!(["name"]));
+        assert_eq!(
+            auth_method["oneOf"][0]["properties"]["private_key"]["type"],
+            json!("string")
+        );
+    }
+
+    #[test]
+    fn test_validate_schema() {
+        let loader = ConfigLoader::new().env(vec![]);
+        for (path, format) in [
+            ("config.toml", ConfigFormat::Toml),
+            ("config.yaml", ConfigFormat::Yaml),
+            ("config.json", ConfigFormat::Json),
+            ("profiles/config.toml", ConfigFormat::Toml),
+        ] {
+            let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), path);
+            let value = loader
+                .load_value(&ConfigSource::file(&path, format))
+                .unwrap();
+            assert_eq!(validate_schema(&value), Ok(()), "{}", path);
+        }
+
+        let path = format!("{}/tests/fixtures/config.toml", env!("CARGO_MANIFEST_DIR"));
+        let mut value = loader
+            .load_value(&ConfigSource::file(&path, ConfigFormat::Toml))
+            .unwrap();
+        // a wrong EIP-55 checksum, as the address parser rejects it
+        value["tokens"]["busd"]["networks"]["bsc"]["address"] =
+            json!("0xE9e7CEA3DedcA5984780Bafc599bD69ADd087D56");
+        value["networks"]["bsc"]["chain_id"] = json!("56");
+
+        let errors = validate_schema(&value).unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["networks.bsc.chain_id", "tokens.busd.networks.bsc.address"]
+        );
+        assert!(errors.iter().all(|e| e.kind == ValidationErrorKind::Schema));
+        assert!(value["tokens"]["busd"]["networks"]["bsc"]["address"]
+            .as_str()
+            .unwrap()
+            .parse::<crate::config::address::Address>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_method_schema() {
+        let schema = serde_json::to_value(method_schema()).unwrap();
+        let tags: Vec<_> = schema["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| variant["properties"]["type"]["enum"][0].clone())
+            .collect();
+
+        assert_eq!(
+            tags,
+            vec![
+                json!("wallet"),
+                json!("mnemonic"),
+                json!("remote"),
+                json!("meta_mask")
+            ]
+        );
+    }
+}
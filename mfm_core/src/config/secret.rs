@@ -0,0 +1,279 @@
+use std::{
+    fmt,
+    sync::{Arc, OnceLock},
+};
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use hmac::{Hmac, Mac};
+use mfm_machine::state::context::{REDACTED, SECRET_MARKER};
+use rand::{rngs::OsRng, RngCore};
+use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use tari_utilities::SafePassword;
+use zeroize::Zeroizing;
+
+use crate::rpc::{from_hex, to_hex};
+
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+const IV_LEN: usize = 16;
+const TAG_LEN: usize = 32;
+
+// drawn once per process and never serialized: the contexts hold the secrets sealed
+// with it, so they only open in the process that loaded the config
+struct SealingKey {
+    cipher: [u8; 32],
+    mac: [u8; 32],
+}
+
+fn sealing_key() -> &'static SealingKey {
+    static KEY: OnceLock<SealingKey> = OnceLock::new();
+    KEY.get_or_init(|| {
+        let mut key = SealingKey {
+            cipher: [0; 32],
+            mac: [0; 32],
+        };
+        OsRng.fill_bytes(&mut key.cipher);
+        OsRng.fill_bytes(&mut key.mac);
+        key
+    })
+}
+
+fn tag(data: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(&sealing_key().mac).expect("hmac accepts any key length");
+    mac.update(data);
+    mac
+}
+
+// iv || ciphertext || hmac-sha256 of both
+fn seal(secret: &[u8]) -> Vec<u8> {
+    let mut sealed = vec![0; IV_LEN];
+    OsRng.fill_bytes(&mut sealed);
+    sealed.extend_from_slice(secret);
+
+    let (iv, ciphertext) = sealed.split_at_mut(IV_LEN);
+    Aes256Ctr::new((&sealing_key().cipher).into(), (&*iv).into()).apply_keystream(ciphertext);
+
+    let tag = tag(&sealed).finalize().into_bytes();
+    sealed.extend_from_slice(&tag);
+    sealed
+}
+
+fn open(sealed: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+    if sealed.len() < IV_LEN + TAG_LEN {
+        return None;
+    }
+    let (data, expected) = sealed.split_at(sealed.len() - TAG_LEN);
+    // in constant time
+    tag(data).verify_slice(expected).ok()?;
+
+    let (iv, ciphertext) = data.split_at(IV_LEN);
+    let mut secret = Zeroizing::new(ciphertext.to_vec());
+    Aes256Ctr::new((&sealing_key().cipher).into(), iv.into()).apply_keystream(&mut secret);
+    Some(secret)
+}
+
+/// A secret of the config, like a private key or a mnemonic phrase, zeroized once
+/// the last of its clones is dropped.
+///
+/// It debug prints as redacted, and serializes as `{"$secret": "<sealed>"}`, the
+/// secret encrypted with a key drawn by this process and never serialized: a config
+/// round trips through a context, but serializing or dumping it leaks no key
+/// material, and the sealed secret is redacted from the dumps as any value marked
+/// secret.
+#[derive(Clone)]
+pub struct Secret {
+    value: Arc<SafePassword>,
+}
+
+impl Secret {
+    pub fn reveal(&self) -> &[u8] {
+        self.value.reveal()
+    }
+}
+
+impl<S: Into<String>> From<S> for Secret {
+    fn from(secret: S) -> Self {
+        Self {
+            value: Arc::new(SafePassword::from(secret)),
+        }
+    }
+}
+
+// in constant time, as the secrets are compared to find the signers unlocked already
+impl PartialEq for Secret {
+    fn eq(&self, other: &Self) -> bool {
+        self.reveal().ct_eq(other.reveal()).into()
+    }
+}
+
+impl Eq for Secret {}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(SECRET_MARKER, &to_hex(&seal(self.reveal())))?;
+        map.end()
+    }
+}
+
+// `{"$secret": "<sealed>"}`, or `{"$secret": "<redacted>"}` once dumped
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MarkedRepr {
+    Sealed {
+        #[serde(rename = "$secret")]
+        secret: String,
+    },
+    Other {
+        #[serde(rename = "$secret")]
+        _secret: de::IgnoredAny,
+    },
+}
+
+// the config holds the secret itself, and a context the sealed one
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SecretRepr {
+    Text(String),
+    Marked(MarkedRepr),
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let not_sealed = || {
+            de::Error::custom(
+                "the secret wasn't sealed by this process; load it from the config again",
+            )
+        };
+        let sealed = match SecretRepr::deserialize(deserializer)? {
+            SecretRepr::Text(secret) => return Ok(Self::from(secret)),
+            SecretRepr::Marked(MarkedRepr::Sealed { secret }) if secret == REDACTED => {
+                return Err(de::Error::custom(
+                    "the secret was redacted; load it from the config again",
+                ))
+            }
+            SecretRepr::Marked(MarkedRepr::Sealed { secret }) => secret,
+            SecretRepr::Marked(MarkedRepr::Other { .. }) => return Err(not_sealed()),
+        };
+
+        let mut secret = from_hex(&sealed)
+            .ok()
+            .and_then(|sealed| open(&sealed))
+            .ok_or_else(not_sealed)?;
+        let secret = String::from_utf8(std::mem::take(&mut *secret))
+            .map_err(|_| de::Error::custom("the sealed secret isn't utf-8"))?;
+        Ok(Self::from(secret))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mfm_machine::state::context::{redact_secrets, wrap_context, Local};
+    use mfm_machine::state::{Label, Tag};
+    use mfm_machine::state_machine::tracker::{HashMapTracker, Index, Tracker};
+    use serde_json::json;
+
+    use crate::config::loader::from_toml_str;
+    use crate::contexts::{ConfigSource, ReadConfig, READ_CONFIG};
+
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../tests/fixtures/config.toml");
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_round_trip() {
+        let secret = Secret::from(KEY);
+        assert_eq!(format!("{:?}", secret), "Secret(<redacted>)");
+
+        let value = serde_json::to_value(&secret).unwrap();
+        assert!(value[SECRET_MARKER].is_string());
+        assert!(!value.to_string().contains(&KEY[..16]));
+        // a new iv each time
+        assert_ne!(value, serde_json::to_value(&secret).unwrap());
+
+        let back: Secret = serde_json::from_value(value).unwrap();
+        assert_eq!(back.reveal(), KEY.as_bytes());
+        assert_eq!(back, secret);
+        assert_ne!(Secret::from(PHRASE), secret);
+
+        let text: Secret = serde_json::from_value(json!(KEY)).unwrap();
+        assert_eq!(text.reveal(), KEY.as_bytes());
+    }
+
+    #[test]
+    fn test_unsealed_secrets() {
+        let redacted = serde_json::from_value::<Secret>(json!({ "$secret": "<redacted>" }));
+        assert!(redacted.unwrap_err().to_string().contains("was redacted"));
+
+        let mut value = serde_json::to_value(Secret::from(KEY)).unwrap();
+        let mut sealed = from_hex(value[SECRET_MARKER].as_str().unwrap()).unwrap();
+        sealed[IV_LEN] ^= 1;
+        value[SECRET_MARKER] = json!(to_hex(&sealed));
+        // a tampered secret, a truncated one, and a handle to a vault
+        for value in [value, json!({ "$secret": "0x00" }), json!({ "$secret": 0 })] {
+            let tampered = serde_json::from_value::<Secret>(value);
+            assert!(tampered
+                .unwrap_err()
+                .to_string()
+                .contains("wasn't sealed by this process"));
+        }
+    }
+
+    #[test]
+    fn test_config_dump_has_no_key_bytes() {
+        let config = from_toml_str(FIXTURE, None).unwrap();
+
+        let debug = format!("{:?}", config);
+        let serialized = serde_json::to_string(&config).unwrap();
+
+        let context = wrap_context(Local::default());
+        READ_CONFIG
+            .write(
+                &context,
+                &ReadConfig {
+                    config_source: ConfigSource::TomlFile("config.toml".to_string()),
+                    config: config.clone(),
+                },
+            )
+            .unwrap();
+        let tracker: &mut dyn Tracker = &mut HashMapTracker::new();
+        tracker
+            .track(
+                Index::new(
+                    0,
+                    Label::new("read_config").unwrap(),
+                    vec![Tag::new("setup").unwrap()],
+                ),
+                context.clone(),
+            )
+            .unwrap();
+        let history = format!("{:?}", tracker.history());
+        let dump = context.lock().unwrap().dump().unwrap().to_string();
+
+        for output in [&debug, &serialized, &history, &dump] {
+            assert!(!output.contains(&KEY[..16]));
+            assert!(!output.contains(&PHRASE[..14]));
+        }
+        assert!(serialized.contains(SECRET_MARKER));
+        assert_eq!(dump.matches(REDACTED).count(), 2);
+
+        // the context still gives the keys to the states
+        let read = READ_CONFIG.read(&context).unwrap().config;
+        let signers = read.auth_methods.get("hardhat").unwrap().method.signers();
+        assert_eq!(signers.unwrap().len(), 2);
+
+        let mut value = serde_json::to_value(&read).unwrap();
+        assert_eq!(redact_secrets(&mut value), 2);
+    }
+}
@@ -1,30 +1,42 @@
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+use super::{address::Address, amount::Percentage};
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub enum Kind {
     ERC20,
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct TokenNetwork {
     pub name: String,
     pub network_id: String,
-    pub address: String,
-    pub slippage: f64,
+    pub address: Address,
+    pub slippage: Percentage,
     pub path_asset: String,
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct TokenNetworks(HashMap<String, TokenNetwork>);
+impl TokenNetworks {
+    pub fn hashmap(&self) -> &HashMap<String, TokenNetwork> {
+        &self.0
+    }
+
+    pub fn get(&self, key: &str) -> Option<&TokenNetwork> {
+        self.0.get(key)
+    }
+}
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct Token {
     pub kind: Kind,
     pub networks: TokenNetworks,
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct Tokens(HashMap<String, Token>);
 impl Tokens {
     pub fn hashmap(&self) -> &HashMap<String, Token> {
@@ -0,0 +1,327 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use rust_decimal::Decimal;
+
+use super::{
+    network::Network,
+    token::{Token, TokenNetwork},
+    Config,
+};
+
+/// Slippages are percentages, accepted in `(0, MAX_SLIPPAGE]`.
+pub const MAX_SLIPPAGE: Decimal = Decimal::from_parts(50, 0, 0, false, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    DanglingReference,
+    SlippageOutOfBounds,
+    DuplicatedChainId,
+    MissingWrappedAsset,
+    DuplicatedName,
+    /// Against the JSON Schema of the config.
+    Schema,
+}
+
+/// A problem found in a [`Config`], at the dotted path of the offending value,
+/// e.g. `tokens.busd.networks.bsc.network_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub path: String,
+    pub kind: ValidationErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+// hash maps are walked in key order, so the errors are reported deterministically
+fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    entries
+}
+
+struct Validator<'a> {
+    config: &'a Config,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&mut self, path: String, kind: ValidationErrorKind, message: String) {
+        self.errors.push(ValidationError {
+            path,
+            kind,
+            message,
+        });
+    }
+
+    fn network_ref(&mut self, path: String, network_id: &str) {
+        if self.config.networks.get(network_id).is_none() {
+            self.error(
+                path,
+                ValidationErrorKind::DanglingReference,
+                format!("network '{}' is not defined in networks", network_id),
+            );
+        }
+    }
+
+    fn networks(&mut self) {
+        let mut chain_ids: HashMap<u32, &String> = HashMap::new();
+
+        for (id, network) in sorted(self.config.networks.hashmap()) {
+            let path = format!("networks.{}", id);
+
+            match chain_ids.get(&network.chain_id) {
+                Some(other) => self.error(
+                    format!("{}.chain_id", path),
+                    ValidationErrorKind::DuplicatedChainId,
+                    format!(
+                        "chain id {} is already used by network '{}'",
+                        network.chain_id, other
+                    ),
+                ),
+                None => {
+                    chain_ids.insert(network.chain_id, id);
+                }
+            }
+
+            self.wrapped_asset(&path, id, network);
+        }
+    }
+
+    fn wrapped_asset(&mut self, path: &str, id: &str, network: &Network) {
+        let path = format!("{}.wrapped_asset", path);
+
+        match &network.wrapped_asset {
+            Some(token) if token.networks.get(id).is_some() => self.token(&path, token),
+            Some(token) => {
+                self.error(
+                    format!("{}.networks", path),
+                    ValidationErrorKind::MissingWrappedAsset,
+                    format!("the wrapped asset has no entry for network '{}'", id),
+                );
+                self.token(&path, token);
+            }
+            None => self.error(
+                path,
+                ValidationErrorKind::MissingWrappedAsset,
+                format!("network '{}' has no wrapped asset", id),
+            ),
+        }
+    }
+
+    fn dexes(&mut self) {
+        for (id, dex) in sorted(self.config.dexes.hashmap()) {
+            let path = format!("dexes.{}", id);
+
+            self.network_ref(format!("{}.network_id", path), &dex.network_id);
+        }
+    }
+
+    fn auth_methods(&mut self) {
+        let mut names = HashSet::new();
+
+        for (index, auth_method) in self.config.auth_methods.list().iter().enumerate() {
+            let path = format!("auth_methods.{}", index);
+
+            // signers are picked by name, so it must be unambiguous
+            if !names.insert(&auth_method.name) {
+                self.error(
+                    format!("{}.name", path),
+                    ValidationErrorKind::DuplicatedName,
+                    format!("auth method '{}' is defined twice", auth_method.name),
+                );
+            }
+
+            for (network_index, network_id) in auth_method.networks.iter().enumerate() {
+                self.network_ref(format!("{}.networks.{}", path, network_index), network_id);
+            }
+        }
+    }
+
+    fn tokens(&mut self) {
+        for (id, token) in sorted(self.config.tokens.hashmap()) {
+            self.token(&format!("tokens.{}", id), token);
+        }
+    }
+
+    fn token(&mut self, path: &str, token: &Token) {
+        for (network_key, token_network) in sorted(token.networks.hashmap()) {
+            let path = format!("{}.networks.{}", path, network_key);
+            self.token_network(&path, token_network);
+        }
+    }
+
+    fn token_network(&mut self, path: &str, token_network: &TokenNetwork) {
+        let network_id = &token_network.network_id;
+
+        self.network_ref(format!("{}.network_id", path), network_id);
+
+        let slippage = token_network.slippage.value();
+        if !(slippage > Decimal::ZERO && slippage <= MAX_SLIPPAGE) {
+            self.error(
+                format!("{}.slippage", path),
+                ValidationErrorKind::SlippageOutOfBounds,
+                format!(
+                    "slippage {} is out of the (0, {}] percent bounds",
+                    slippage, MAX_SLIPPAGE
+                ),
+            );
+        }
+
+        // swaps are routed through the path asset, so it must exist on the same network
+        let path_asset = &token_network.path_asset;
+        let path_asset_networks = self
+            .config
+            .tokens
+            .get(path_asset)
+            .map(|token| &token.networks);
+
+        match path_asset_networks {
+            Some(networks) if networks.get(network_id).is_none() => self.error(
+                format!("{}.path_asset", path),
+                ValidationErrorKind::DanglingReference,
+                format!(
+                    "token '{}' has no entry for network '{}'",
+                    path_asset, network_id
+                ),
+            ),
+            Some(_) => (),
+            None => self.error(
+                format!("{}.path_asset", path),
+                ValidationErrorKind::DanglingReference,
+                format!("token '{}' is not defined in tokens", path_asset),
+            ),
+        }
+    }
+}
+
+impl Config {
+    /// Checks the references between the config entries and the values the
+    /// deserialization can't, returning every problem found.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut validator = Validator {
+            config: self,
+            errors: Vec::new(),
+        };
+
+        validator.networks();
+        validator.dexes();
+        validator.tokens();
+        validator.auth_methods();
+
+        match validator.errors.is_empty() {
+            true => Ok(()),
+            false => Err(validator.errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::loader::from_toml_str;
+
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../tests/fixtures/config.toml");
+
+    fn errors(config: &str) -> Vec<(String, ValidationErrorKind)> {
+        from_toml_str(config, None)
+            .unwrap()
+            .validate()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (e.path, e.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config() {
+        assert_eq!(from_toml_str(FIXTURE, None).unwrap().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_reports_all_errors() {
+        let config = FIXTURE
+            .replace(
+                "name = \"busd\"\nnetwork_id = \"bsc\"",
+                "name = \"busd\"\nnetwork_id = \"eth\"",
+            )
+            .replace(
+                "address = \"0xe9e7CEA3DedcA5984780Bafc599bD69ADd087D56\"\nslippage = 0.5\npath_asset = \"wbnb\"",
+                "address = \"0xe9e7CEA3DedcA5984780Bafc599bD69ADd087D56\"\nslippage = 75.0\npath_asset = \"weth\"",
+            )
+            .replace("name = \"hardhat\"\nnetworks = [\"bsc\"]", "name = \"deployer\"\nnetworks = [\"bsc\", \"eth\"]")
+            + "\n[networks.bsc_fork]\nname = \"bsc_fork\"\nkind = \"EVM\"\nsymbol = \"bnb\"\nchain_id = 56\nnode_url = \"http://localhost:8545\"\nmin_balance_coin = 0.0\n";
+
+        assert_eq!(
+            errors(&config),
+            vec![
+                (
+                    "networks.bsc_fork.chain_id".to_string(),
+                    ValidationErrorKind::DuplicatedChainId
+                ),
+                (
+                    "networks.bsc_fork.wrapped_asset".to_string(),
+                    ValidationErrorKind::MissingWrappedAsset
+                ),
+                (
+                    "tokens.busd.networks.bsc.network_id".to_string(),
+                    ValidationErrorKind::DanglingReference
+                ),
+                (
+                    "tokens.busd.networks.bsc.slippage".to_string(),
+                    ValidationErrorKind::SlippageOutOfBounds
+                ),
+                (
+                    "tokens.busd.networks.bsc.path_asset".to_string(),
+                    ValidationErrorKind::DanglingReference
+                ),
+                (
+                    "auth_methods.1.name".to_string(),
+                    ValidationErrorKind::DuplicatedName
+                ),
+                (
+                    "auth_methods.1.networks.1".to_string(),
+                    ValidationErrorKind::DanglingReference
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrapped_asset_on_another_network() {
+        let config = FIXTURE.replace(
+            "[networks.bsc.wrapped_asset.networks.bsc]",
+            "[networks.bsc.wrapped_asset.networks.eth]",
+        );
+        assert_eq!(
+            errors(&config),
+            vec![(
+                "networks.bsc.wrapped_asset.networks".to_string(),
+                ValidationErrorKind::MissingWrappedAsset
+            ),]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let error = ValidationError {
+            path: "dexes.pancake_swap_v2.network_id".to_string(),
+            kind: ValidationErrorKind::DanglingReference,
+            message: "network 'eth' is not defined in networks".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "dexes.pancake_swap_v2.network_id: network 'eth' is not defined in networks"
+        );
+    }
+}
@@ -1,10 +1,61 @@
-use crate::config::Config;
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use crate::{
+    config::{
+        address::Address,
+        amount::{Amount, BaseUnits},
+        Config,
+    },
+    rpc::{
+        pool::EndpointStatus,
+        types::{TransactionReceipt, TxHash},
+    },
+};
+use mfm_machine::state::context::ContextKey;
 use serde_derive::{Deserialize, Serialize};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Guesses the format of a file from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ConfigSource {
     TomlFile(String),
+    YamlFile(String),
+    JsonFile(String),
+    Inline(ConfigFormat, String),
+    Stdin(ConfigFormat),
+    /// Merged in order, each source overriding the ones before it.
+    Layered(Vec<ConfigSource>),
+    /// Applies the named profile of the loaded config over it.
+    Profile(String, Box<ConfigSource>),
+}
+
+impl ConfigSource {
+    pub fn file(path: &str, format: ConfigFormat) -> Self {
+        match format {
+            ConfigFormat::Toml => Self::TomlFile(path.to_string()),
+            ConfigFormat::Yaml => Self::YamlFile(path.to_string()),
+            ConfigFormat::Json => Self::JsonFile(path.to_string()),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
@@ -12,4 +63,161 @@ pub struct ReadConfig {
     pub config_source: ConfigSource,
     pub config: Config,
 }
-pub const READ_CONFIG: &str = "read_config";
+
+/// Where the `ReadConfig` state loads the config from.
+pub const CONFIG_SOURCE: ContextKey<ConfigSource> = ContextKey::new("config_source");
+/// The config loaded by the `ReadConfig` state.
+pub const READ_CONFIG: ContextKey<ReadConfig> = ContextKey::new("read_config");
+
+/// The networks used by the pipeline; all the configured ones when not set.
+pub const NETWORKS: ContextKey<Vec<String>> = ContextKey::new("networks");
+/// The endpoints of each network checked by the `VerifyChainIds` state.
+pub const VERIFY_CHAIN_IDS: ContextKey<BTreeMap<String, Vec<EndpointStatus>>> =
+    ContextKey::new("verify_chain_ids");
+/// The auth methods used by the pipeline, by name; all the configured ones when not set.
+pub const AUTH_METHODS: ContextKey<Vec<String>> = ContextKey::new("auth_methods");
+/// The accounts of each network resolved by the `ResolveSigners` state.
+pub const SIGNERS: ContextKey<BTreeMap<String, Vec<Account>>> = ContextKey::new("signers");
+/// The address of the active wallet.
+pub const ACCOUNT: ContextKey<Address> = ContextKey::new("account");
+/// The gas limits of the approvals and swaps left to send on each network, estimated
+/// by the `CheckBalances` state.
+pub const GAS_ESTIMATES: ContextKey<BTreeMap<String, u64>> = ContextKey::new("gas_estimates");
+/// The native balances checked by the `CheckBalances` state.
+pub const CHECK_BALANCES: ContextKey<BTreeMap<String, NativeBalance>> =
+    ContextKey::new("check_balances");
+/// The ERC-20 tokens the `swap_orders` sell and buy, by network then token, read
+/// by the `ReadTokens` state.
+pub const TOKENS: ContextKey<BTreeMap<String, BTreeMap<String, TokenBalance>>> =
+    ContextKey::new("tokens");
+/// The swaps of the pipeline.
+pub const SWAP_ORDERS: ContextKey<Vec<SwapOrder>> = ContextKey::new("swap_orders");
+/// The quotes of the `swap_orders` by the `QuoteSwaps` state, in their order.
+pub const QUOTES: ContextKey<Vec<Quote>> = ContextKey::new("quotes");
+/// The swaps of the `quotes` sent by the `ExecuteSwaps` state, in their order.
+pub const SWAPS: ContextKey<Vec<Swap>> = ContextKey::new("swaps");
+/// The approvals sent by the `ApproveTokens` state.
+pub const APPROVALS: ContextKey<Vec<Approval>> = ContextKey::new("approvals");
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NativeBalance {
+    pub address: Address,
+    pub balance: Amount,
+    /// `min_balance_coin` plus the estimated gas fees.
+    pub required: Amount,
+    pub sufficient: bool,
+}
+
+/// An account an auth method signs for.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Account {
+    pub auth_method: String,
+    pub address: Address,
+}
+
+/// An ERC-20 token, and the balance of the `account` in it.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TokenBalance {
+    pub address: Address,
+    pub symbol: String,
+    pub decimals: u8,
+    /// In base units, as a token can hold more than an [`Amount`] does.
+    pub balance: BaseUnits,
+}
+
+impl fmt::Display for TokenBalance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            self.balance.to_decimal_string(self.decimals),
+            self.symbol
+        )
+    }
+}
+
+/// Which amount of a swap is fixed, the other one being quoted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapSide {
+    #[default]
+    ExactIn,
+    ExactOut,
+}
+
+/// A swap between two tokens on the network of a dex, by id.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SwapOrder {
+    pub dex: String,
+    pub token_in: String,
+    pub token_out: String,
+    #[serde(default)]
+    pub side: SwapSide,
+    /// Of `token_in` when selling an exact amount, of `token_out` when buying one.
+    pub amount: BaseUnits,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Quote {
+    pub order: SwapOrder,
+    /// The tokens swapped through, by id, from `token_in` to `token_out`.
+    pub path: Vec<String>,
+    /// The amount of each token of the path.
+    pub amounts: Vec<BaseUnits>,
+}
+
+impl Quote {
+    pub fn amount_in(&self) -> BaseUnits {
+        self.amounts.first().copied().unwrap_or_default()
+    }
+
+    pub fn amount_out(&self) -> BaseUnits {
+        self.amounts.last().copied().unwrap_or_default()
+    }
+}
+
+/// A transaction of a state, written to the context as soon as it's signed, so a
+/// recovery broadcasts it again instead of signing another one.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SentTransaction {
+    /// Signed, and maybe broadcast.
+    Signed {
+        hash: TxHash,
+        nonce: u64,
+        /// The raw transaction, hex encoded.
+        raw: String,
+    },
+    Mined(TransactionReceipt),
+}
+
+impl SentTransaction {
+    pub fn hash(&self) -> TxHash {
+        match self {
+            Self::Signed { hash, .. } => *hash,
+            Self::Mined(receipt) => receipt.transaction_hash,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Swap {
+    pub quote: Quote,
+    /// The least bought when selling an exact amount, or the most sold when
+    /// buying one.
+    pub limit: BaseUnits,
+    /// The unix time after which the router reverts the swap.
+    pub deadline: u64,
+    pub transaction: SentTransaction,
+}
+
+/// An allowance of a router to transfer a token of the `account`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Approval {
+    pub network_id: String,
+    pub token: String,
+    pub spender: Address,
+    /// `None` for an unlimited one.
+    pub amount: Option<BaseUnits>,
+    pub transaction: SentTransaction,
+}
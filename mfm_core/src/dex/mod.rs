@@ -0,0 +1,61 @@
+//! Clients of the dex contracts configured in `dexes`.
+
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::config::amount::Percentage;
+
+pub mod uniswap_v2;
+
+// the slippages are applied in billionths
+const SCALE: u128 = 1_000_000_000;
+
+// the part of `amount` a slippage stands for, rounded up
+fn slippage_of(amount: u128, slippage: Percentage) -> Option<u128> {
+    let fraction = slippage.fraction();
+    if fraction.is_sign_negative() || fraction > Decimal::ONE {
+        return None;
+    }
+
+    let parts = (fraction * Decimal::from(SCALE)).floor().to_u128()?;
+    let (whole, rest) = (amount / SCALE, amount % SCALE);
+    Some(whole * parts + (rest * parts).div_ceil(SCALE))
+}
+
+/// The least to accept for a quoted `amount` with a slippage, rounded down;
+/// `None` when the slippage isn't between 0 and 100%.
+pub fn min_amount(amount: u128, slippage: Percentage) -> Option<u128> {
+    slippage_of(amount, slippage).map(|part| amount - part)
+}
+
+/// The most to pay for a quoted `amount` with a slippage, rounded up.
+pub fn max_amount(amount: u128, slippage: Percentage) -> Option<u128> {
+    amount.checked_add(slippage_of(amount, slippage)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn percentage(value: &str) -> Percentage {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_slippage() {
+        assert_eq!(min_amount(1_000, percentage("0.5")), Some(995));
+        assert_eq!(max_amount(1_000, percentage("0.5")), Some(1_005));
+        assert_eq!(min_amount(999, percentage("0.5")), Some(994));
+        assert_eq!(max_amount(999, percentage("0.5")), Some(1_004));
+        assert_eq!(min_amount(1_000, percentage("0")), Some(1_000));
+        assert_eq!(min_amount(1_000, percentage("100")), Some(0));
+
+        assert_eq!(
+            min_amount(u128::MAX, percentage("1")),
+            Some(u128::MAX - u128::MAX / 100 - 1)
+        );
+        assert_eq!(max_amount(u128::MAX, percentage("1")), None);
+
+        assert_eq!(min_amount(1_000, percentage("-1")), None);
+        assert_eq!(min_amount(1_000, percentage("101")), None);
+    }
+}
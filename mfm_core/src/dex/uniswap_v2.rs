@@ -0,0 +1,302 @@
+use crate::config::{address::Address, dexes::Dex};
+use crate::rpc::{
+    abi::{self, Token},
+    client::EvmClient,
+    types::{BlockId, CallRequest},
+    RpcError,
+};
+
+/// The router of a UniswapV2 dex, swapping along a path of tokens with a pair
+/// between each of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Router {
+    address: Address,
+}
+
+impl Router {
+    pub fn new(address: Address) -> Self {
+        Self { address }
+    }
+
+    pub fn for_dex(dex: &Dex) -> Self {
+        Self::new(dex.router_address)
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// The amount of each token of `path` when selling `amount_in` of the first one.
+    pub fn get_amounts_out(
+        &self,
+        client: &EvmClient,
+        amount_in: u128,
+        path: &[Address],
+    ) -> Result<Vec<u128>, RpcError> {
+        self.amounts(client, "getAmountsOut(uint256,address[])", amount_in, path)
+    }
+
+    /// The amount of each token of `path` needed to buy `amount_out` of the last one.
+    pub fn get_amounts_in(
+        &self,
+        client: &EvmClient,
+        amount_out: u128,
+        path: &[Address],
+    ) -> Result<Vec<u128>, RpcError> {
+        self.amounts(client, "getAmountsIn(uint256,address[])", amount_out, path)
+    }
+
+    fn amounts(
+        &self,
+        client: &EvmClient,
+        signature: &str,
+        amount: u128,
+        path: &[Address],
+    ) -> Result<Vec<u128>, RpcError> {
+        let data = abi::encode_call(
+            signature,
+            &[Token::Uint(amount), Token::Addresses(path.to_vec())],
+        );
+        let output = client.call(&CallRequest::new(self.address, data), BlockId::Latest)?;
+
+        let amounts = abi::decode_uint_array(&output, 0)?;
+        if amounts.len() != path.len() {
+            return Err(RpcError::InvalidResponse(format!(
+                "expected {} amounts from {}, found {}",
+                path.len(),
+                signature,
+                amounts.len()
+            )));
+        }
+        Ok(amounts)
+    }
+}
+
+/// The amounts of a swap: the fixed one, and the limit of the other one the
+/// router reverts past.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapAmounts {
+    ExactIn {
+        amount_in: u128,
+        amount_out_min: u128,
+    },
+    ExactOut {
+        amount_out: u128,
+        amount_in_max: u128,
+    },
+}
+
+/// A swap along a path of the router.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapCall {
+    pub amounts: SwapAmounts,
+    pub path: Vec<Address>,
+    pub recipient: Address,
+    /// The unix time after which the router reverts the swap.
+    pub deadline: u64,
+    /// Whether the native coin is sold instead of the first token of the path,
+    /// its wrapped one.
+    pub native_in: bool,
+    /// Whether the native coin is bought instead of the last token of the path.
+    pub native_out: bool,
+}
+
+impl SwapCall {
+    /// The input data and the value of the router call, one of the
+    /// `swap{Exact,}{Tokens,ETH}For{Exact,}{Tokens,ETH}` functions.
+    pub fn encode(&self) -> (Vec<u8>, u128) {
+        let path = Token::Addresses(self.path.clone());
+        let recipient = Token::Address(self.recipient);
+        let deadline = Token::Uint(self.deadline.into());
+
+        let (signature, tokens, value) = match (self.amounts, self.native_in, self.native_out) {
+            (
+                SwapAmounts::ExactIn {
+                    amount_in,
+                    amount_out_min,
+                },
+                true,
+                _,
+            ) => (
+                "swapExactETHForTokens(uint256,address[],address,uint256)",
+                vec![Token::Uint(amount_out_min), path, recipient, deadline],
+                amount_in,
+            ),
+            (
+                SwapAmounts::ExactIn {
+                    amount_in,
+                    amount_out_min,
+                },
+                false,
+                native_out,
+            ) => (
+                match native_out {
+                    true => "swapExactTokensForETH(uint256,uint256,address[],address,uint256)",
+                    false => "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+                },
+                vec![
+                    Token::Uint(amount_in),
+                    Token::Uint(amount_out_min),
+                    path,
+                    recipient,
+                    deadline,
+                ],
+                0,
+            ),
+            // the router refunds what's left of the value
+            (
+                SwapAmounts::ExactOut {
+                    amount_out,
+                    amount_in_max,
+                },
+                true,
+                _,
+            ) => (
+                "swapETHForExactTokens(uint256,address[],address,uint256)",
+                vec![Token::Uint(amount_out), path, recipient, deadline],
+                amount_in_max,
+            ),
+            (
+                SwapAmounts::ExactOut {
+                    amount_out,
+                    amount_in_max,
+                },
+                false,
+                native_out,
+            ) => (
+                match native_out {
+                    true => "swapTokensForExactETH(uint256,uint256,address[],address,uint256)",
+                    false => "swapTokensForExactTokens(uint256,uint256,address[],address,uint256)",
+                },
+                vec![
+                    Token::Uint(amount_out),
+                    Token::Uint(amount_in_max),
+                    path,
+                    recipient,
+                    deadline,
+                ],
+                0,
+            ),
+        };
+
+        (abi::encode_call(signature, &tokens), value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::{mock::MockNode, pool::EndpointPool};
+
+    const ROUTER: &str = "0x10ED43C718714eb63d5aA57B78B54704E256024E";
+    const BUSD: &str = "0xe9e7CEA3DedcA5984780Bafc599bD69ADd087D56";
+    const WBNB: &str = "0xbb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c";
+
+    #[test]
+    fn test_get_amounts_out() {
+        let node = MockNode::start(56);
+        let path = [BUSD.parse().unwrap(), WBNB.parse().unwrap()];
+        let expected = [
+            "d06ca61f".to_string(),
+            format!("{:0>64}", "3e8"),
+            format!("{:0>64}", "40"),
+            format!("{:0>64}", "2"),
+            format!("{:0>64}", &BUSD[2..]),
+            format!("{:0>64}", &WBNB[2..]),
+        ]
+        .concat()
+        .to_lowercase();
+        node.set_contract(ROUTER.parse().unwrap(), |_| {
+            Ok(abi::encode(&[
+                Token::Uint(32),
+                Token::Uint(2),
+                Token::Uint(1_000),
+                Token::Uint(3),
+            ]))
+        });
+
+        let client = EvmClient::new(EndpointPool::new(56, vec![node.url()], Default::default()));
+        let router = Router::new(ROUTER.parse().unwrap());
+        assert_eq!(
+            router.get_amounts_out(&client, 1_000, &path).unwrap(),
+            vec![1_000, 3]
+        );
+        let calls = node.calls();
+        let (_, params) = calls
+            .iter()
+            .find(|(method, _)| method == "eth_call")
+            .unwrap();
+        assert_eq!(params[0]["data"], format!("0x{}", expected));
+
+        // a path of 3 tokens, answered with the amounts of 2
+        let path = [path[0], path[1], path[0]];
+        assert!(matches!(
+            router.get_amounts_out(&client, 1_000, &path),
+            Err(RpcError::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_encode_swaps() {
+        let path = vec![BUSD.parse().unwrap(), WBNB.parse().unwrap()];
+        let mut swap = SwapCall {
+            amounts: SwapAmounts::ExactIn {
+                amount_in: 1_000,
+                amount_out_min: 990,
+            },
+            path: path.clone(),
+            recipient: BUSD.parse().unwrap(),
+            deadline: 1_700_000_000,
+            native_in: false,
+            native_out: false,
+        };
+        let args = |tokens: &[Token]| abi::encode(tokens);
+        let tail = |amounts: &[u128]| {
+            let mut tokens: Vec<Token> = amounts.iter().copied().map(Token::Uint).collect();
+            tokens.extend([
+                Token::Addresses(path.clone()),
+                Token::Address(BUSD.parse().unwrap()),
+                Token::Uint(1_700_000_000),
+            ]);
+            args(&tokens)
+        };
+
+        let (data, value) = swap.encode();
+        assert_eq!(data[..4], [0x38, 0xed, 0x17, 0x39]);
+        assert_eq!(data[4..], tail(&[1_000, 990]));
+        assert_eq!(value, 0);
+
+        swap.native_out = true;
+        let (data, value) = swap.encode();
+        assert_eq!(data[..4], [0x18, 0xcb, 0xaf, 0xe5]);
+        assert_eq!(data[4..], tail(&[1_000, 990]));
+        assert_eq!(value, 0);
+
+        // the amount sold is the value of the call
+        swap.native_out = false;
+        swap.native_in = true;
+        let (data, value) = swap.encode();
+        assert_eq!(data[..4], [0x7f, 0xf3, 0x6a, 0xb5]);
+        assert_eq!(data[4..], tail(&[990]));
+        assert_eq!(value, 1_000);
+
+        swap.amounts = SwapAmounts::ExactOut {
+            amount_out: 500,
+            amount_in_max: 1_010,
+        };
+        let (data, value) = swap.encode();
+        assert_eq!(data[..4], [0xfb, 0x3b, 0xdb, 0x41]);
+        assert_eq!(data[4..], tail(&[500]));
+        assert_eq!(value, 1_010);
+
+        swap.native_in = false;
+        let (data, value) = swap.encode();
+        assert_eq!(data[..4], [0x88, 0x03, 0xdb, 0xee]);
+        assert_eq!(data[4..], tail(&[500, 1_010]));
+        assert_eq!(value, 0);
+
+        swap.native_out = true;
+        let (data, _) = swap.encode();
+        assert_eq!(data[..4], [0x4a, 0x25, 0xd9, 0x4a]);
+    }
+}
@@ -0,0 +1,170 @@
+//! Reads of the ERC-20 tokens, and the calldata of their approvals.
+
+use crate::config::address::Address;
+use crate::rpc::{
+    abi::{self, Token, MAX_UINT},
+    client::EvmClient,
+    types::{BlockId, CallRequest},
+    RpcError,
+};
+
+/// How much of a token a spender is allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Allowance {
+    Exact(u128),
+    /// The largest `uint256`, which routers never decrease.
+    Unlimited,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Erc20 {
+    address: Address,
+}
+
+impl Erc20 {
+    pub fn new(address: Address) -> Self {
+        Self { address }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    fn call(
+        &self,
+        client: &EvmClient,
+        signature: &str,
+        tokens: &[Token],
+    ) -> Result<Vec<u8>, RpcError> {
+        let data = abi::encode_call(signature, tokens);
+        client.call(&CallRequest::new(self.address, data), BlockId::Latest)
+    }
+
+    pub fn balance_of(&self, client: &EvmClient, owner: &Address) -> Result<u128, RpcError> {
+        let output = self.call(client, "balanceOf(address)", &[Token::Address(*owner)])?;
+        abi::decode_uint(&output, 0)
+    }
+
+    /// What `spender` can still transfer from `owner`, capped at `u128::MAX`.
+    pub fn allowance(
+        &self,
+        client: &EvmClient,
+        owner: &Address,
+        spender: &Address,
+    ) -> Result<u128, RpcError> {
+        let output = self.call(
+            client,
+            "allowance(address,address)",
+            &[Token::Address(*owner), Token::Address(*spender)],
+        )?;
+        abi::decode_uint_saturating(&output, 0)
+    }
+
+    pub fn decimals(&self, client: &EvmClient) -> Result<u8, RpcError> {
+        let output = self.call(client, "decimals()", &[])?;
+        let decimals = abi::decode_uint(&output, 0)?;
+        u8::try_from(decimals)
+            .map_err(|_| RpcError::InvalidResponse(format!("invalid decimals {}", decimals)))
+    }
+
+    pub fn symbol(&self, client: &EvmClient) -> Result<String, RpcError> {
+        let output = self.call(client, "symbol()", &[])?;
+        // a few early tokens answer a bytes32 rather than a string
+        if output.len() == 32 {
+            let end = output.iter().position(|byte| *byte == 0).unwrap_or(32);
+            return String::from_utf8(output[..end].to_vec()).map_err(|_| {
+                RpcError::InvalidResponse("the symbol isn't valid UTF-8".to_string())
+            });
+        }
+        abi::decode_string(&output, 0)
+    }
+
+    /// The input data of the call allowing `spender` to transfer `allowance`.
+    pub fn approve_data(spender: &Address, allowance: Allowance) -> Vec<u8> {
+        let amount = match allowance {
+            Allowance::Exact(amount) => Token::Uint(amount),
+            Allowance::Unlimited => Token::Word(MAX_UINT),
+        };
+        abi::encode_call(
+            "approve(address,uint256)",
+            &[Token::Address(*spender), amount],
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::{mock::MockNode, pool::EndpointPool};
+
+    const BUSD: &str = "0xe9e7CEA3DedcA5984780Bafc599bD69ADd087D56";
+    const MKR: &str = "0x9f8F72aA9304c8B593d555F12eF6589cC3A579A2";
+    const OWNER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const ROUTER: &str = "0x10ED43C718714eb63d5aA57B78B54704E256024E";
+
+    fn client(node: &MockNode) -> EvmClient {
+        EvmClient::new(EndpointPool::new(56, vec![node.url()], Default::default()))
+    }
+
+    #[test]
+    fn test_reads() {
+        let node = MockNode::start(56);
+        node.set_contract(BUSD.parse().unwrap(), |data| {
+            let selector: [u8; 4] = data[..4].try_into().unwrap();
+            Ok(match selector {
+                [0x70, 0xa0, 0x82, 0x31] => abi::encode(&[Token::Uint(5 * 10u128.pow(21))]),
+                [0xdd, 0x62, 0xed, 0x3e] => MAX_UINT.to_vec(),
+                [0x31, 0x3c, 0xe5, 0x67] => abi::encode(&[Token::Uint(18)]),
+                [0x95, 0xd8, 0x9b, 0x41] => {
+                    let mut output = abi::encode(&[Token::Uint(32), Token::Uint(4)]);
+                    output.extend(format!("{:\0<32}", "BUSD").as_bytes());
+                    output
+                }
+                _ => return Err((-32000, "execution reverted".to_string())),
+            })
+        });
+        // answering its symbol as a bytes32
+        node.set_contract(MKR.parse().unwrap(), |_| {
+            Ok(format!("{:\0<32}", "MKR").into_bytes())
+        });
+
+        let client = client(&node);
+        let busd = Erc20::new(BUSD.parse().unwrap());
+        let owner = OWNER.parse().unwrap();
+        assert_eq!(
+            busd.balance_of(&client, &owner).unwrap(),
+            5 * 10u128.pow(21)
+        );
+        assert_eq!(
+            busd.allowance(&client, &owner, &ROUTER.parse().unwrap())
+                .unwrap(),
+            u128::MAX
+        );
+        assert_eq!(busd.decimals(&client).unwrap(), 18);
+        assert_eq!(busd.symbol(&client).unwrap(), "BUSD");
+
+        let mkr = Erc20::new(MKR.parse().unwrap());
+        assert_eq!(mkr.symbol(&client).unwrap(), "MKR");
+
+        // no contract at the address
+        let none = Erc20::new(OWNER.parse().unwrap());
+        assert!(matches!(
+            none.decimals(&client),
+            Err(RpcError::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_approve_data() {
+        let spender = ROUTER.parse().unwrap();
+        let exact = Erc20::approve_data(&spender, Allowance::Exact(1_000));
+        assert_eq!(exact[..4], [0x09, 0x5e, 0xa7, 0xb3]);
+        assert_eq!(
+            exact[4..],
+            abi::encode(&[Token::Address(spender), Token::Uint(1_000)])
+        );
+
+        let unlimited = Erc20::approve_data(&spender, Allowance::Unlimited);
+        assert_eq!(unlimited[36..], MAX_UINT);
+    }
+}
@@ -1,3 +1,7 @@
 pub mod config;
 pub mod contexts;
+pub mod dex;
+pub mod erc20;
+pub mod rpc;
+pub mod signer;
 pub mod states;
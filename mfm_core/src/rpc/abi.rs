@@ -0,0 +1,255 @@
+//! The subset of the Solidity ABI the contract calls need: static words, address
+//! arrays, and the decoding of the uints, uint arrays and strings they answer.
+
+use super::RpcError;
+use crate::config::address::{keccak256, Address};
+
+/// The largest `uint256`, e.g. for an unlimited approval.
+pub const MAX_UINT: [u8; 32] = [0xff; 32];
+
+/// The first 4 bytes of the hash of a function signature, e.g. `balanceOf(address)`.
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Uint(u128),
+    /// A raw 32 bytes word, for the uints past u128.
+    Word([u8; 32]),
+    Address(Address),
+    Addresses(Vec<Address>),
+}
+
+fn uint_word(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn address_word(address: &Address) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address.as_bytes());
+    word
+}
+
+/// Encodes the arguments of a call: the static ones in place, and the dynamic
+/// ones appended after them, in place of their offset.
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+    let mut head = Vec::with_capacity(tokens.len() * 32);
+    let mut tail = Vec::new();
+
+    for token in tokens {
+        match token {
+            Token::Uint(value) => head.extend(uint_word(*value)),
+            Token::Word(word) => head.extend(word),
+            Token::Address(address) => head.extend(address_word(address)),
+            Token::Addresses(addresses) => {
+                head.extend(uint_word((tokens.len() * 32 + tail.len()) as u128));
+                tail.extend(uint_word(addresses.len() as u128));
+                for address in addresses {
+                    tail.extend(address_word(address));
+                }
+            }
+        }
+    }
+
+    head.extend(tail);
+    head
+}
+
+/// The input data of a call to `signature`.
+pub fn encode_call(signature: &str, tokens: &[Token]) -> Vec<u8> {
+    [&selector(signature)[..], &encode(tokens)].concat()
+}
+
+/// The 32 bytes word at `index` of a call output.
+pub fn decode_word(output: &[u8], index: usize) -> Result<[u8; 32], RpcError> {
+    index
+        .checked_mul(32)
+        .and_then(|start| output.get(start..start.checked_add(32)?))
+        .map(|word| word.try_into().expect("32 bytes slice"))
+        .ok_or_else(|| {
+            RpcError::InvalidResponse(format!(
+                "expected a word at {} in a {} bytes output",
+                index,
+                output.len()
+            ))
+        })
+}
+
+pub fn decode_uint(output: &[u8], index: usize) -> Result<u128, RpcError> {
+    let word = decode_word(output, index)?;
+    if word[..16].iter().any(|byte| *byte != 0) {
+        return Err(RpcError::InvalidResponse(format!(
+            "the uint at {} doesn't fit in 128 bits",
+            index
+        )));
+    }
+    Ok(u128::from_be_bytes(
+        word[16..].try_into().expect("16 bytes"),
+    ))
+}
+
+/// Decodes a uint, capped at `u128::MAX`, e.g. for what's left of an unlimited
+/// allowance.
+pub fn decode_uint_saturating(output: &[u8], index: usize) -> Result<u128, RpcError> {
+    let word = decode_word(output, index)?;
+    match word[..16].iter().any(|byte| *byte != 0) {
+        true => Ok(u128::MAX),
+        false => Ok(u128::from_be_bytes(
+            word[16..].try_into().expect("16 bytes"),
+        )),
+    }
+}
+
+// the word index of the content of a dynamic value whose offset is at `index`
+fn dynamic_start(output: &[u8], index: usize) -> Result<usize, RpcError> {
+    let offset = decode_uint(output, index)?;
+    if !offset.is_multiple_of(32) {
+        return Err(RpcError::InvalidResponse(format!(
+            "misaligned offset {}",
+            offset
+        )));
+    }
+    Ok(usize::try_from(offset / 32).unwrap_or(usize::MAX))
+}
+
+/// Decodes the `string` whose offset is at `index`.
+pub fn decode_string(output: &[u8], index: usize) -> Result<String, RpcError> {
+    let start = dynamic_start(output, index)?;
+    let len = usize::try_from(decode_uint(output, start)?).unwrap_or(usize::MAX);
+
+    let bytes = (start + 1)
+        .checked_mul(32)
+        .and_then(|from| output.get(from..from.checked_add(len)?))
+        .ok_or_else(|| {
+            RpcError::InvalidResponse(format!(
+                "a string of {} bytes overflows a {} bytes output",
+                len,
+                output.len()
+            ))
+        })?;
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| RpcError::InvalidResponse("the string isn't valid UTF-8".to_string()))
+}
+
+/// Decodes the `uint256[]` whose offset is at `index`.
+pub fn decode_uint_array(output: &[u8], index: usize) -> Result<Vec<u128>, RpcError> {
+    let start = dynamic_start(output, index)?;
+    let len = usize::try_from(decode_uint(output, start)?).unwrap_or(usize::MAX);
+    // checked before decoding, so a bogus length allocates nothing
+    if len > output.len() / 32 {
+        return Err(RpcError::InvalidResponse(format!(
+            "an array of {} uints overflows a {} bytes output",
+            len,
+            output.len()
+        )));
+    }
+
+    (0..len)
+        .map(|i| decode_uint(output, start + 1 + i))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::from_hex;
+
+    const BUSD: &str = "0xe9e7CEA3DedcA5984780Bafc599bD69ADd087D56";
+    const WBNB: &str = "0xbb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c";
+
+    fn word(hex: &str) -> String {
+        format!("{:0>64}", hex)
+    }
+
+    #[test]
+    fn test_selector() {
+        assert_eq!(selector("balanceOf(address)"), [0x70, 0xa0, 0x82, 0x31]);
+        assert_eq!(
+            selector("approve(address,uint256)"),
+            [0x09, 0x5e, 0xa7, 0xb3]
+        );
+    }
+
+    #[test]
+    fn test_encode_call() {
+        let data = encode_call(
+            "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+            &[
+                Token::Uint(1_000),
+                Token::Uint(990),
+                Token::Addresses(vec![BUSD.parse().unwrap(), WBNB.parse().unwrap()]),
+                Token::Address(WBNB.parse().unwrap()),
+                Token::Word(MAX_UINT),
+            ],
+        );
+
+        let expected = [
+            "38ed1739".to_string(),
+            word("3e8"),
+            word("3de"),
+            // the array comes after the 5 words of the head
+            word("a0"),
+            word("bb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c"),
+            "f".repeat(64),
+            word("2"),
+            word("e9e7cea3dedca5984780bafc599bd69add087d56"),
+            word("bb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c"),
+        ]
+        .concat();
+        assert_eq!(data, from_hex(&expected).unwrap());
+    }
+
+    #[test]
+    fn test_decode() {
+        let output = from_hex(
+            &[
+                word("20"),
+                word("3"),
+                word("de0b6b3a7640000"),
+                word("1"),
+                word("ffffffffffffffffffffffffffffffff"),
+            ]
+            .concat(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            decode_uint_array(&output, 0).unwrap(),
+            vec![10u128.pow(18), 1, u128::MAX]
+        );
+        assert_eq!(decode_uint(&output, 2).unwrap(), 10u128.pow(18));
+        assert!(decode_uint(&output, 5).is_err());
+
+        let too_large = from_hex(&"1".repeat(64)).unwrap();
+        assert!(matches!(
+            decode_uint(&too_large, 0),
+            Err(RpcError::InvalidResponse(_))
+        ));
+        assert_eq!(decode_word(&too_large, 0).unwrap()[0], 0x11);
+
+        // a length past the output, and an offset past it
+        let truncated = from_hex(&[word("20"), word("3"), word("1")].concat()).unwrap();
+        assert!(decode_uint_array(&truncated, 0).is_err());
+        assert!(decode_uint_array(&from_hex(&word("40")).unwrap(), 0).is_err());
+        assert_eq!(decode_uint_saturating(&too_large, 0).unwrap(), u128::MAX);
+        assert_eq!(decode_uint_saturating(&output, 2).unwrap(), 10u128.pow(18));
+    }
+
+    #[test]
+    fn test_decode_string() {
+        // "WBNB", as answered by its symbol()
+        let output =
+            from_hex(&[word("20"), word("4"), format!("{:0<64}", "57424e42")].concat()).unwrap();
+        assert_eq!(decode_string(&output, 0).unwrap(), "WBNB");
+
+        let truncated = from_hex(&[word("20"), word("21"), "0".repeat(64)].concat()).unwrap();
+        assert!(decode_string(&truncated, 0).is_err());
+        let invalid =
+            from_hex(&[word("20"), word("1"), format!("{:0<64}", "ff")].concat()).unwrap();
+        assert!(decode_string(&invalid, 0).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+use serde_json::{json, Value};
+
+use super::{
+    from_hex, parse_quantity, parse_quantity_u64,
+    pool::{EndpointPool, PoolOptions},
+    to_hex,
+    types::{BlockId, CallRequest, FeeHistory, TransactionReceipt, TxHash},
+    RpcError,
+};
+use crate::config::{address::Address, network::Network};
+
+/// A client of the EVM JSON-RPC API of a network, failing over between its endpoints.
+///
+/// The quantities, like balances and fees, are in wei.
+pub struct EvmClient {
+    pool: EndpointPool,
+}
+
+impl EvmClient {
+    pub fn new(pool: EndpointPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn for_network(network: &Network, options: PoolOptions) -> Self {
+        Self::new(EndpointPool::for_network(network, options))
+    }
+
+    pub fn pool(&self) -> &EndpointPool {
+        &self.pool
+    }
+
+    pub fn request(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        self.pool.request(method, params)
+    }
+
+    fn quantity(&self, method: &str, params: Value) -> Result<u128, RpcError> {
+        parse_quantity(&self.request(method, params)?)
+    }
+
+    fn quantity_u64(&self, method: &str, params: Value) -> Result<u64, RpcError> {
+        parse_quantity_u64(&self.request(method, params)?)
+    }
+
+    pub fn chain_id(&self) -> Result<u64, RpcError> {
+        self.quantity_u64("eth_chainId", json!([]))
+    }
+
+    pub fn block_number(&self) -> Result<u64, RpcError> {
+        self.quantity_u64("eth_blockNumber", json!([]))
+    }
+
+    pub fn get_balance(&self, address: &Address, block: BlockId) -> Result<u128, RpcError> {
+        self.quantity(
+            "eth_getBalance",
+            json!([address.to_string(), block.to_value()]),
+        )
+    }
+
+    pub fn get_transaction_count(
+        &self,
+        address: &Address,
+        block: BlockId,
+    ) -> Result<u64, RpcError> {
+        self.quantity_u64(
+            "eth_getTransactionCount",
+            json!([address.to_string(), block.to_value()]),
+        )
+    }
+
+    /// Runs a message call without creating a transaction, returning its output.
+    pub fn call(&self, call: &CallRequest, block: BlockId) -> Result<Vec<u8>, RpcError> {
+        let output = self.request("eth_call", json!([call.to_value(), block.to_value()]))?;
+        output
+            .as_str()
+            .ok_or_else(|| {
+                RpcError::InvalidResponse(format!("expected hex data, found {}", output))
+            })
+            .and_then(from_hex)
+    }
+
+    pub fn estimate_gas(&self, call: &CallRequest) -> Result<u128, RpcError> {
+        self.quantity("eth_estimateGas", json!([call.to_value()]))
+    }
+
+    pub fn gas_price(&self) -> Result<u128, RpcError> {
+        self.quantity("eth_gasPrice", json!([]))
+    }
+
+    pub fn max_priority_fee_per_gas(&self) -> Result<u128, RpcError> {
+        self.quantity("eth_maxPriorityFeePerGas", json!([]))
+    }
+
+    pub fn fee_history(
+        &self,
+        block_count: u64,
+        newest_block: BlockId,
+        reward_percentiles: &[f64],
+    ) -> Result<FeeHistory, RpcError> {
+        let history = self.request(
+            "eth_feeHistory",
+            json!([
+                super::to_quantity(block_count.into()),
+                newest_block.to_value(),
+                reward_percentiles
+            ]),
+        )?;
+        FeeHistory::from_value(&history)
+    }
+
+    /// Broadcasts a signed transaction, returning its hash.
+    pub fn send_raw_transaction(&self, transaction: &[u8]) -> Result<TxHash, RpcError> {
+        let hash = self.request("eth_sendRawTransaction", json!([to_hex(transaction)]))?;
+        hash.as_str()
+            .ok_or_else(|| RpcError::InvalidResponse(format!("expected an hash, found {}", hash)))?
+            .parse()
+    }
+
+    /// The receipt of a mined transaction, `None` while it's pending or unknown.
+    pub fn get_transaction_receipt(
+        &self,
+        hash: &TxHash,
+    ) -> Result<Option<TransactionReceipt>, RpcError> {
+        match self.request("eth_getTransactionReceipt", json!([hash.to_string()]))? {
+            Value::Null => Ok(None),
+            receipt => TransactionReceipt::from_value(&receipt).map(Some),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::address::keccak256, rpc::mock::MockNode};
+
+    const ACCOUNT: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const ROUTER: &str = "0x10ED43C718714eb63d5aA57B78B54704E256024E";
+
+    fn client(node: &MockNode) -> EvmClient {
+        EvmClient::new(EndpointPool::new(
+            56,
+            vec![node.url()],
+            PoolOptions::default(),
+        ))
+    }
+
+    #[test]
+    fn test_chain_queries() {
+        let node = MockNode::start(56);
+        node.set_block_number(1234);
+        node.set_gas_price(5_000_000_000);
+        node.set_base_fee(3_000_000_000);
+        node.set_priority_fee(1_000_000_000);
+        let account: Address = ACCOUNT.parse().unwrap();
+        node.set_balance(account, 2 * 10u128.pow(18));
+        let client = client(&node);
+
+        assert_eq!(client.chain_id(), Ok(56));
+        assert_eq!(client.block_number(), Ok(1234));
+        assert_eq!(
+            client.get_balance(&account, BlockId::Latest),
+            Ok(2 * 10u128.pow(18))
+        );
+        assert_eq!(
+            client.get_balance(&ROUTER.parse().unwrap(), BlockId::Number(1)),
+            Ok(0)
+        );
+        assert_eq!(
+            client.get_transaction_count(&account, BlockId::Pending),
+            Ok(0)
+        );
+        assert_eq!(client.gas_price(), Ok(5_000_000_000));
+        assert_eq!(client.max_priority_fee_per_gas(), Ok(1_000_000_000));
+
+        let history = client.fee_history(2, BlockId::Latest, &[50.0]).unwrap();
+        assert_eq!(history.oldest_block, 1233);
+        assert_eq!(history.next_base_fee(), Some(3_000_000_000));
+        assert_eq!(history.reward, vec![vec![1_000_000_000]; 2]);
+
+        let calls = node.calls();
+        assert_eq!(
+            calls.iter().find(|(method, _)| method == "eth_getBalance"),
+            Some(&("eth_getBalance".to_string(), json!([ACCOUNT, "latest"])))
+        );
+    }
+
+    #[test]
+    fn test_call_and_estimate_gas() {
+        let node = MockNode::start(56);
+        let router: Address = ROUTER.parse().unwrap();
+        node.set_contract(router, |data| match data {
+            [0xde, 0xad, ..] => Err((3, "execution reverted".to_string())),
+            data => Ok(data.iter().rev().copied().collect()),
+        });
+        node.set_estimate_gas(150_000);
+        let client = client(&node);
+
+        let call = CallRequest::new(router, vec![1, 2, 3]);
+        assert_eq!(client.call(&call, BlockId::Latest), Ok(vec![3, 2, 1]));
+        assert_eq!(client.estimate_gas(&call), Ok(150_000));
+        assert_eq!(
+            client.call(&CallRequest::new(router, vec![0xde, 0xad]), BlockId::Latest),
+            Err(RpcError::Rpc(3, "execution reverted".to_string()))
+        );
+
+        // accounts without code answer nothing
+        let account = CallRequest::new(ACCOUNT.parse().unwrap(), vec![1]);
+        assert_eq!(client.call(&account, BlockId::Latest), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_send_and_receipt() {
+        let node = MockNode::start(56);
+        node.set_block_number(10);
+        node.set_auto_mine(false);
+        let client = client(&node);
+
+        let raw = vec![0x02, 0xf8, 0x01];
+        let hash = client.send_raw_transaction(&raw).unwrap();
+        assert_eq!(hash, TxHash(keccak256(&raw)));
+        assert_eq!(node.transactions(), vec![raw]);
+        assert_eq!(client.get_transaction_receipt(&hash), Ok(None));
+
+        node.mine();
+        let receipt = client.get_transaction_receipt(&hash).unwrap().unwrap();
+        assert_eq!(receipt.transaction_hash, hash);
+        assert_eq!(receipt.block_number, 11);
+        assert!(receipt.status);
+
+        node.set_auto_mine(true);
+        node.set_revert_transactions(true);
+        let hash = client.send_raw_transaction(&[0x02, 0xf8, 0x02]).unwrap();
+        let receipt = client.get_transaction_receipt(&hash).unwrap().unwrap();
+        assert_eq!(receipt.block_number, 12);
+        assert!(!receipt.status);
+    }
+
+    #[test]
+    fn test_invalid_responses() {
+        let node = MockNode::start(56);
+        node.handle("eth_blockNumber", |_| Ok(json!(12)));
+        node.handle("eth_getTransactionReceipt", |_| {
+            Ok(json!({"status": "0x1"}))
+        });
+        let client = client(&node);
+
+        assert!(matches!(
+            client.block_number(),
+            Err(RpcError::InvalidResponse(_))
+        ));
+        assert!(matches!(
+            client.get_transaction_receipt(&TxHash::default()),
+            Err(RpcError::InvalidResponse(_))
+        ));
+
+        // past 64 bits, rather than wrapped
+        node.handle("eth_chainId", |_| Ok(json!("0x10000000000000038")));
+        assert!(matches!(
+            client.chain_id(),
+            Err(RpcError::InvalidResponse(_))
+        ));
+    }
+}
@@ -0,0 +1,465 @@
+//! An in-process JSON-RPC node, to test the RPC clients and states without a network.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+use super::{from_hex, parse_quantity, to_hex, to_quantity, types::TxHash};
+use crate::config::address::{keccak256, Address};
+use crate::signer::Transaction;
+
+/// Answers the params of a call with a result, or a JSON-RPC error code and message.
+pub type MethodHandler = Box<dyn Fn(&Value) -> Result<Value, (i64, String)> + Send + Sync>;
+/// Answers the input data of an `eth_call` to a contract with its output.
+pub type ContractHandler = Box<dyn Fn(&[u8]) -> Result<Vec<u8>, (i64, String)> + Send + Sync>;
+/// Executes the input data of a transaction to a contract, for real when it's mined
+/// and as a dry run when its gas is estimated, or reverts it with an error.
+pub type TransactionHandler = Box<dyn Fn(&[u8], bool) -> Result<(), (i64, String)> + Send + Sync>;
+
+struct MockState {
+    chain_id: AtomicU64,
+    block_number: AtomicU64,
+    gas_price: AtomicU64,
+    base_fee: AtomicU64,
+    priority_fee: AtomicU64,
+    estimate_gas: AtomicU64,
+    delay_ms: AtomicU64,
+    auto_mine: AtomicBool,
+    revert_transactions: AtomicBool,
+    http_status: Mutex<Option<u16>>,
+    balances: Mutex<HashMap<Address, u128>>,
+    nonces: Mutex<HashMap<Address, u64>>,
+    contracts: Mutex<HashMap<Address, ContractHandler>>,
+    executed: Mutex<HashMap<Address, TransactionHandler>>,
+    handlers: Mutex<HashMap<String, MethodHandler>>,
+    transactions: Mutex<Vec<Vec<u8>>>,
+    pending: Mutex<Vec<(TxHash, Vec<u8>)>>,
+    receipts: Mutex<HashMap<TxHash, Value>>,
+    calls: Mutex<Vec<(String, Value)>>,
+}
+
+impl Default for MockState {
+    fn default() -> Self {
+        Self {
+            chain_id: AtomicU64::new(1),
+            block_number: AtomicU64::new(0),
+            gas_price: AtomicU64::new(1_000_000_000),
+            base_fee: AtomicU64::new(1_000_000_000),
+            priority_fee: AtomicU64::new(1_000_000_000),
+            estimate_gas: AtomicU64::new(21_000),
+            delay_ms: AtomicU64::new(0),
+            auto_mine: AtomicBool::new(true),
+            revert_transactions: AtomicBool::new(false),
+            http_status: Mutex::default(),
+            balances: Mutex::default(),
+            nonces: Mutex::default(),
+            contracts: Mutex::default(),
+            executed: Mutex::default(),
+            handlers: Mutex::default(),
+            transactions: Mutex::default(),
+            pending: Mutex::default(),
+            receipts: Mutex::default(),
+            calls: Mutex::default(),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn invalid_params(e: impl ToString) -> (i64, String) {
+    (-32602, e.to_string())
+}
+
+fn address_param(params: &Value, index: usize) -> Result<Address, (i64, String)> {
+    params[index]
+        .as_str()
+        .ok_or_else(|| invalid_params("expected an address"))?
+        .parse()
+        .map_err(invalid_params)
+}
+
+impl MockState {
+    fn load(value: &AtomicU64) -> Value {
+        quantity(value.load(Ordering::SeqCst).into())
+    }
+
+    fn call(&self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        lock(&self.calls).push((method.to_string(), params.clone()));
+
+        if let Some(handler) = lock(&self.handlers).get(method) {
+            return handler(params);
+        }
+
+        match method {
+            "eth_chainId" => Ok(Self::load(&self.chain_id)),
+            "eth_blockNumber" => Ok(Self::load(&self.block_number)),
+            "eth_gasPrice" => Ok(Self::load(&self.gas_price)),
+            "eth_maxPriorityFeePerGas" => Ok(Self::load(&self.priority_fee)),
+            "eth_estimateGas" => {
+                let to = params[0]["to"].as_str().and_then(|to| to.parse().ok());
+                let data =
+                    from_hex(params[0]["data"].as_str().unwrap_or("0x")).map_err(invalid_params)?;
+                self.execute(to, &data, false)?;
+                Ok(Self::load(&self.estimate_gas))
+            }
+            "eth_getBalance" => {
+                let address = address_param(params, 0)?;
+                let balance = lock(&self.balances).get(&address).copied();
+                Ok(quantity(balance.unwrap_or_default()))
+            }
+            "eth_getTransactionCount" => {
+                let address = address_param(params, 0)?;
+                let nonce = lock(&self.nonces).get(&address).copied();
+                Ok(quantity(nonce.unwrap_or_default().into()))
+            }
+            "eth_feeHistory" => self.fee_history(params),
+            "eth_call" => {
+                let to = params[0]["to"]
+                    .as_str()
+                    .ok_or_else(|| invalid_params("expected a call with a to address"))?
+                    .parse::<Address>()
+                    .map_err(invalid_params)?;
+                let data =
+                    from_hex(params[0]["data"].as_str().unwrap_or("0x")).map_err(invalid_params)?;
+
+                match lock(&self.contracts).get(&to) {
+                    Some(contract) => contract(&data).map(|output| json!(to_hex(&output))),
+                    None => Ok(json!("0x")),
+                }
+            }
+            "eth_sendRawTransaction" => {
+                let raw =
+                    from_hex(params[0].as_str().unwrap_or_default()).map_err(invalid_params)?;
+                let hash = TxHash(keccak256(&raw));
+
+                lock(&self.transactions).push(raw.clone());
+                lock(&self.pending).push((hash, raw));
+                if self.auto_mine.load(Ordering::SeqCst) {
+                    self.mine();
+                }
+                Ok(json!(hash.to_string()))
+            }
+            "eth_getTransactionReceipt" => {
+                let hash: TxHash = params[0]
+                    .as_str()
+                    .unwrap_or_default()
+                    .parse()
+                    .map_err(invalid_params)?;
+                Ok(lock(&self.receipts)
+                    .get(&hash)
+                    .cloned()
+                    .unwrap_or(Value::Null))
+            }
+            _ => Err((
+                -32601,
+                format!("the method {} does not exist/is not available", method),
+            )),
+        }
+    }
+
+    fn fee_history(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let block_count = parse_quantity(&params[0]).map_err(invalid_params)? as u64;
+        let newest = self.block_number.load(Ordering::SeqCst);
+        let percentiles = params[2].as_array().map_or(0, Vec::len);
+
+        let base_fee = Self::load(&self.base_fee);
+        let priority_fee = Self::load(&self.priority_fee);
+
+        Ok(json!({
+            "oldestBlock": quantity((newest + 1).saturating_sub(block_count).into()),
+            "baseFeePerGas": vec![base_fee; block_count as usize + 1],
+            "gasUsedRatio": vec![0.5; block_count as usize],
+            "reward": vec![vec![priority_fee; percentiles]; block_count as usize],
+        }))
+    }
+
+    // runs a transaction through the contract it's sent to, if it has one
+    fn execute(&self, to: Option<Address>, data: &[u8], mined: bool) -> Result<(), (i64, String)> {
+        match to.and_then(|to| {
+            lock(&self.executed)
+                .get(&to)
+                .map(|execute| execute(data, mined))
+        }) {
+            Some(result) => result,
+            None => Ok(()),
+        }
+    }
+
+    // includes the pending transactions in a new block
+    fn mine(&self) {
+        let block_number = self.block_number.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let mut receipts = lock(&self.receipts);
+        for (hash, raw) in lock(&self.pending).drain(..) {
+            // the raw data the tests send as is executes nothing
+            let executed = match Transaction::decode_signed(&raw) {
+                Some(tx) => self.execute(tx.to, &tx.data, true).is_ok(),
+                None => true,
+            };
+            let status = match executed && !self.revert_transactions.load(Ordering::SeqCst) {
+                true => "0x1",
+                false => "0x0",
+            };
+            receipts.insert(
+                hash,
+                json!({
+                    "transactionHash": hash.to_string(),
+                    "blockNumber": quantity(block_number.into()),
+                    "status": status,
+                    "gasUsed": Self::load(&self.estimate_gas),
+                    "effectiveGasPrice": Self::load(&self.gas_price),
+                    "contractAddress": null,
+                }),
+            );
+        }
+    }
+}
+
+fn quantity(value: u128) -> Value {
+    json!(to_quantity(value))
+}
+
+/// A JSON-RPC node listening on a local port, stopped when dropped.
+///
+/// It simulates a chain good enough for the client: balances, nonces, gas prices,
+/// contracts answering `eth_call`, and the raw transactions sent, mined in a new block
+/// right away unless the auto mining is off. A handler registered with
+/// [`MockNode::handle`] answers its method instead.
+pub struct MockNode {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    shutdown: Arc<AtomicBool>,
+    server: Option<JoinHandle<()>>,
+}
+
+impl MockNode {
+    pub fn start(chain_id: u64) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind a local port");
+        listener
+            .set_nonblocking(true)
+            .expect("set a non blocking listener");
+
+        let addr = listener.local_addr().expect("local address");
+        let state = Arc::new(MockState::default());
+        state.chain_id.store(chain_id, Ordering::SeqCst);
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let server = {
+            let state = state.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || serve(listener, state, shutdown))
+        };
+
+        Self {
+            addr,
+            state,
+            shutdown,
+            server: Some(server),
+        }
+    }
+
+    /// An url nothing listens on.
+    pub fn unreachable_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind a local port");
+        format!("http://{}", listener.local_addr().expect("local address"))
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn set_chain_id(&self, chain_id: u64) {
+        self.state.chain_id.store(chain_id, Ordering::SeqCst);
+    }
+
+    pub fn set_block_number(&self, block_number: u64) {
+        self.state
+            .block_number
+            .store(block_number, Ordering::SeqCst);
+    }
+
+    pub fn set_gas_price(&self, wei: u64) {
+        self.state.gas_price.store(wei, Ordering::SeqCst);
+    }
+
+    pub fn set_base_fee(&self, wei: u64) {
+        self.state.base_fee.store(wei, Ordering::SeqCst);
+    }
+
+    pub fn set_priority_fee(&self, wei: u64) {
+        self.state.priority_fee.store(wei, Ordering::SeqCst);
+    }
+
+    pub fn set_estimate_gas(&self, gas: u64) {
+        self.state.estimate_gas.store(gas, Ordering::SeqCst);
+    }
+
+    pub fn set_balance(&self, address: Address, wei: u128) {
+        lock(&self.state.balances).insert(address, wei);
+    }
+
+    pub fn set_nonce(&self, address: Address, nonce: u64) {
+        lock(&self.state.nonces).insert(address, nonce);
+    }
+
+    /// Deploys a contract answering the `eth_call`s to `address`.
+    pub fn set_contract<F>(&self, address: Address, contract: F)
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>, (i64, String)> + Send + Sync + 'static,
+    {
+        lock(&self.state.contracts).insert(address, Box::new(contract));
+    }
+
+    /// Executes the transactions to `address`, as their estimates and once mined.
+    pub fn set_contract_transactions<F>(&self, address: Address, execute: F)
+    where
+        F: Fn(&[u8], bool) -> Result<(), (i64, String)> + Send + Sync + 'static,
+    {
+        lock(&self.state.executed).insert(address, Box::new(execute));
+    }
+
+    /// Whether the sent transactions are mined right away.
+    pub fn set_auto_mine(&self, auto_mine: bool) {
+        self.state.auto_mine.store(auto_mine, Ordering::SeqCst);
+    }
+
+    /// Whether the mined transactions revert.
+    pub fn set_revert_transactions(&self, revert: bool) {
+        self.state
+            .revert_transactions
+            .store(revert, Ordering::SeqCst);
+    }
+
+    /// Mines the pending transactions in a new block.
+    pub fn mine(&self) {
+        self.state.mine();
+    }
+
+    /// The raw transactions sent so far.
+    pub fn transactions(&self) -> Vec<Vec<u8>> {
+        lock(&self.state.transactions).clone()
+    }
+
+    /// Delays every answer, e.g. to make the clients time out.
+    pub fn set_delay(&self, delay: Duration) {
+        self.state
+            .delay_ms
+            .store(delay.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    /// Answers every request with this HTTP status, or normally again with `None`.
+    pub fn set_http_status(&self, status: Option<u16>) {
+        *lock(&self.state.http_status) = status;
+    }
+
+    pub fn handle<F>(&self, method: &str, handler: F)
+    where
+        F: Fn(&Value) -> Result<Value, (i64, String)> + Send + Sync + 'static,
+    {
+        lock(&self.state.handlers).insert(method.to_string(), Box::new(handler));
+    }
+
+    /// The methods and params received so far.
+    pub fn calls(&self) -> Vec<(String, Value)> {
+        lock(&self.state.calls).clone()
+    }
+
+    pub fn calls_to(&self, method: &str) -> usize {
+        self.calls().iter().filter(|(m, _)| m == method).count()
+    }
+}
+
+impl Drop for MockNode {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(server) = self.server.take() {
+            let _ = server.join();
+        }
+    }
+}
+
+fn serve(listener: TcpListener, state: Arc<MockState>, shutdown: Arc<AtomicBool>) {
+    while !shutdown.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let state = state.clone();
+                thread::spawn(move || {
+                    let _ = handle_connection(stream, &state);
+                });
+            }
+            Err(_) => thread::sleep(Duration::from_millis(5)),
+        }
+    }
+}
+
+fn handle_connection(stream: TcpStream, state: &MockState) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line == "\r\n" {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or_default();
+            }
+        }
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+
+    thread::sleep(Duration::from_millis(state.delay_ms.load(Ordering::SeqCst)));
+
+    let status = *lock(&state.http_status);
+    let (status, response) = match status {
+        Some(status) => (status, String::new()),
+        None => (200, respond(state, &body).to_string()),
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} MOCK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        response.len(),
+        response
+    )?;
+    stream.flush()
+}
+
+fn respond(state: &MockState, body: &[u8]) -> Value {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => {
+            return json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32700, "message": e.to_string()}})
+        }
+    };
+
+    let id = request["id"].clone();
+    let method = request["method"].as_str().unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(json!([]));
+
+    match state.call(method, &params) {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err((code, message)) => {
+            json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+        }
+    }
+}
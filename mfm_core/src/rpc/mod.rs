@@ -0,0 +1,151 @@
+use std::fmt;
+
+use anyhow::anyhow;
+use mfm_machine::state::{StateError, StateErrorRecoverability};
+
+pub mod abi;
+pub mod client;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod pool;
+pub mod transport;
+pub mod types;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcError {
+    /// The node couldn't be reached, or didn't answer in time.
+    Transport(String, String),
+    /// The node answered with a non success HTTP status.
+    Http(String, u16),
+    /// The node answered with a JSON-RPC error object.
+    Rpc(i64, String),
+    /// The node answered with something that isn't a valid JSON-RPC response.
+    InvalidResponse(String),
+    /// No endpoint of a pool could serve the request; the error of each one, by url.
+    AllEndpointsFailed(Vec<(String, RpcError)>),
+}
+
+impl RpcError {
+    /// Errors of the endpoint itself rather than of the request, worth trying another endpoint.
+    pub fn is_endpoint_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::Transport(_, _) | Self::Http(_, _) | Self::InvalidResponse(_)
+        )
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(url, e) => write!(f, "failed to reach {}: {}", url, e),
+            Self::Http(url, status) => write!(f, "{} answered with HTTP status {}", url, status),
+            Self::Rpc(code, message) => write!(f, "JSON-RPC error {}: {}", code, message),
+            Self::InvalidResponse(e) => write!(f, "invalid JSON-RPC response: {}", e),
+            Self::AllEndpointsFailed(errors) => {
+                write!(f, "all the endpoints failed")?;
+                for (url, e) in errors {
+                    write!(f, "; {}: {}", url, e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+// another attempt can reach a healthy endpoint, while the node answer stays the same
+impl From<RpcError> for StateError {
+    fn from(e: RpcError) -> Self {
+        let recoverability = match e {
+            RpcError::Rpc(_, _) => StateErrorRecoverability::Unrecoverable,
+            _ => StateErrorRecoverability::Recoverable,
+        };
+        StateError::RpcConnection(recoverability, anyhow!(e))
+    }
+}
+
+/// Parses an hex encoded JSON-RPC quantity, e.g. `"0x38"`.
+pub fn parse_quantity(value: &serde_json::Value) -> Result<u128, RpcError> {
+    value
+        .as_str()
+        .and_then(|s| s.strip_prefix("0x"))
+        .and_then(|hex| u128::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| {
+            RpcError::InvalidResponse(format!("expected an hex quantity, found {}", value))
+        })
+}
+
+/// Parses a quantity that fits in 64 bits, like a chain id, a block number or a nonce.
+pub fn parse_quantity_u64(value: &serde_json::Value) -> Result<u64, RpcError> {
+    let quantity = parse_quantity(value)?;
+    u64::try_from(quantity).map_err(|_| {
+        RpcError::InvalidResponse(format!("the quantity {} doesn't fit in 64 bits", quantity))
+    })
+}
+
+/// Encodes a JSON-RPC quantity, e.g. `0x38`.
+pub fn to_quantity(value: u128) -> String {
+    format!("0x{:x}", value)
+}
+
+/// Encodes JSON-RPC data, e.g. `0x0a0b`.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", hex)
+}
+
+/// Decodes JSON-RPC data; the `0x` prefix is optional.
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, RpcError> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    let invalid = || RpcError::InvalidResponse(format!("invalid hex data '{}'", hex));
+
+    if !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(parse_quantity(&json!("0x38")), Ok(56));
+        assert!(parse_quantity(&json!("56")).is_err());
+        assert!(parse_quantity(&json!(56)).is_err());
+        assert_eq!(
+            parse_quantity_u64(&json!("0xffffffffffffffff")),
+            Ok(u64::MAX)
+        );
+        assert!(matches!(
+            parse_quantity_u64(&json!("0x10000000000000000")),
+            Err(RpcError::InvalidResponse(_))
+        ));
+        assert_eq!(to_quantity(0), "0x0");
+        assert_eq!(to_hex(&[0x0a, 0xbc]), "0x0abc");
+        assert_eq!(from_hex("0x0abc"), Ok(vec![0x0a, 0xbc]));
+        assert_eq!(from_hex("0x"), Ok(vec![]));
+        assert!(from_hex("0xabc").is_err());
+    }
+
+    #[test]
+    fn test_into_state_error() {
+        let unreachable: StateError =
+            RpcError::Transport("http://node".to_string(), "refused".to_string()).into();
+        assert!(matches!(unreachable, StateError::RpcConnection(_, _)));
+        assert!(unreachable.is_recoverable());
+
+        let reverted: StateError = RpcError::Rpc(3, "execution reverted".to_string()).into();
+        assert!(matches!(reverted, StateError::RpcConnection(_, _)));
+        assert!(!reverted.is_recoverable());
+    }
+}
@@ -0,0 +1,537 @@
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{
+    parse_quantity_u64,
+    transport::{HttpTransport, DEFAULT_TIMEOUT},
+    RpcError,
+};
+use crate::config::network::Network;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolOptions {
+    /// How long to wait for an answer before failing over.
+    pub timeout: Duration,
+    /// How many blocks an endpoint can be behind the most advanced one and stay healthy.
+    pub max_block_lag: u64,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            max_block_lag: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum EndpointHealth {
+    /// Not checked yet.
+    Unknown,
+    Healthy,
+    Lagging {
+        blocks: u64,
+    },
+    /// Connected to another chain; it's never used.
+    WrongChain {
+        chain_id: u64,
+    },
+    Unreachable {
+        error: String,
+    },
+}
+
+impl EndpointHealth {
+    // the endpoints are tried from the lowest rank; `None` is never tried
+    fn rank(&self) -> Option<u8> {
+        match self {
+            Self::Healthy | Self::Unknown => Some(0),
+            Self::Lagging { .. } => Some(1),
+            Self::Unreachable { .. } => Some(2),
+            Self::WrongChain { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for EndpointHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown => write!(f, "unknown"),
+            Self::Healthy => write!(f, "healthy"),
+            Self::Lagging { blocks } => write!(f, "lagging {} blocks behind", blocks),
+            Self::WrongChain { chain_id } => write!(f, "wrong chain id {}", chain_id),
+            Self::Unreachable { error } => write!(f, "unreachable: {}", error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointStatus {
+    pub url: String,
+    pub health: EndpointHealth,
+    pub block_number: Option<u64>,
+    pub latency_ms: Option<u64>,
+    /// Consecutive failed requests.
+    pub failures: u32,
+    /// Whether the requests are sent to this endpoint first.
+    pub active: bool,
+}
+
+impl fmt::Display for EndpointStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = if self.active { "*" } else { " " };
+        write!(f, "{} {}: {}", marker, self.url, self.health)?;
+        if let Some(block_number) = self.block_number {
+            write!(f, ", block {}", block_number)?;
+        }
+        if let Some(latency_ms) = self.latency_ms {
+            write!(f, ", {} ms", latency_ms)?;
+        }
+        if self.failures > 0 {
+            write!(f, ", {} failures", self.failures)?;
+        }
+        Ok(())
+    }
+}
+
+struct PoolState {
+    statuses: Vec<EndpointStatus>,
+    active: usize,
+    /// whether each endpoint answered with the chain id of the pool
+    verified: Vec<bool>,
+}
+
+/// The RPC endpoints of a network, by priority.
+///
+/// Requests go to the active endpoint and fail over to the next usable ones when it
+/// can't be reached, times out or answers with an HTTP error; a JSON-RPC error is the
+/// answer of the node and is returned as it is. [`EndpointPool::check_health`] verifies
+/// the chain id and the block height of every endpoint, and picks the first healthy one
+/// as active; an endpoint it hasn't verified has its chain id checked the first time a
+/// request uses it, so no request reaches a node of another chain.
+pub struct EndpointPool {
+    chain_id: u64,
+    options: PoolOptions,
+    transports: Vec<HttpTransport>,
+    state: Mutex<PoolState>,
+}
+
+impl EndpointPool {
+    pub fn new(chain_id: u64, urls: Vec<String>, options: PoolOptions) -> Self {
+        let transports: Vec<HttpTransport> = urls
+            .iter()
+            .map(|url| HttpTransport::new(url, options.timeout))
+            .collect();
+
+        let verified = vec![false; urls.len()];
+        let statuses = urls
+            .into_iter()
+            .enumerate()
+            .map(|(i, url)| EndpointStatus {
+                url,
+                health: EndpointHealth::Unknown,
+                block_number: None,
+                latency_ms: None,
+                failures: 0,
+                active: i == 0,
+            })
+            .collect();
+
+        Self {
+            chain_id,
+            options,
+            transports,
+            state: Mutex::new(PoolState {
+                statuses,
+                active: 0,
+                verified,
+            }),
+        }
+    }
+
+    pub fn for_network(network: &Network, options: PoolOptions) -> Self {
+        Self::new(network.chain_id.into(), network.node_urls(), options)
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState> {
+        // the state is always left consistent, so a poisoned lock is still usable
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn set_active(state: &mut PoolState, active: usize) {
+        state.active = active;
+        for (i, status) in state.statuses.iter_mut().enumerate() {
+            status.active = i == active;
+        }
+    }
+
+    pub fn status(&self) -> Vec<EndpointStatus> {
+        self.lock().statuses.clone()
+    }
+
+    /// One line per endpoint, the active one marked with `*`.
+    pub fn report(&self) -> String {
+        self.status()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn active_url(&self) -> String {
+        let state = self.lock();
+        state.statuses[state.active].url.clone()
+    }
+
+    fn probe(&self, transport: &HttpTransport) -> Result<(u64, u64, Duration), EndpointHealth> {
+        let unreachable = |e: RpcError| EndpointHealth::Unreachable {
+            error: e.to_string(),
+        };
+        let request = |method| {
+            transport
+                .request(method, json!([]))
+                .and_then(|value| parse_quantity_u64(&value))
+                .map_err(unreachable)
+        };
+
+        let started = Instant::now();
+        let chain_id = request("eth_chainId")?;
+        let block_number = request("eth_blockNumber")?;
+        Ok((chain_id, block_number, started.elapsed()))
+    }
+
+    /// Checks every endpoint, then makes the first healthy one active.
+    pub fn check_health(&self) -> Vec<EndpointStatus> {
+        let probes: Vec<_> = self.transports.iter().map(|t| self.probe(t)).collect();
+
+        let highest_block = probes
+            .iter()
+            .filter_map(|probe| match probe {
+                Ok((chain_id, block_number, _)) if *chain_id == self.chain_id => {
+                    Some(*block_number)
+                }
+                _ => None,
+            })
+            .max();
+
+        let mut state = self.lock();
+        let PoolState {
+            statuses, verified, ..
+        } = &mut *state;
+        for ((status, verified), probe) in statuses.iter_mut().zip(verified).zip(probes) {
+            match probe {
+                Ok((chain_id, block_number, latency)) => {
+                    *verified = chain_id == self.chain_id;
+                    let lag = highest_block
+                        .unwrap_or(block_number)
+                        .saturating_sub(block_number);
+
+                    status.health = match (chain_id == self.chain_id, lag) {
+                        (false, _) => EndpointHealth::WrongChain { chain_id },
+                        (true, lag) if lag > self.options.max_block_lag => {
+                            EndpointHealth::Lagging { blocks: lag }
+                        }
+                        (true, _) => EndpointHealth::Healthy,
+                    };
+                    status.block_number = Some(block_number);
+                    status.latency_ms = Some(latency.as_millis() as u64);
+                    status.failures = 0;
+                }
+                Err(health) => {
+                    status.health = health;
+                    status.latency_ms = None;
+                    status.failures += 1;
+                }
+            }
+        }
+
+        let best = (0..state.statuses.len())
+            .filter_map(|i| state.statuses[i].health.rank().map(|rank| (rank, i)))
+            .min();
+        if let Some((_, best)) = best {
+            Self::set_active(&mut state, best);
+        }
+
+        state.statuses.clone()
+    }
+
+    // the active endpoint first, then the others by rank and priority
+    fn candidates(&self) -> (Vec<usize>, Vec<(String, RpcError)>) {
+        let state = self.lock();
+        let mut candidates = Vec::new();
+        let mut skipped = Vec::new();
+
+        for (i, status) in state.statuses.iter().enumerate() {
+            match status.health.rank() {
+                Some(rank) => candidates.push((i != state.active, rank, i)),
+                None => skipped.push((
+                    status.url.clone(),
+                    RpcError::InvalidResponse(format!(
+                        "{}, expected {}",
+                        status.health, self.chain_id
+                    )),
+                )),
+            }
+        }
+
+        candidates.sort();
+        (candidates.into_iter().map(|(_, _, i)| i).collect(), skipped)
+    }
+
+    // checks the chain id of an endpoint not verified yet, marking it on another chain
+    fn verify(&self, i: usize) -> Result<(), RpcError> {
+        if self.lock().verified[i] {
+            return Ok(());
+        }
+
+        // an error answer fails over too, as the chain stays unknown
+        let chain_id = self.transports[i]
+            .request("eth_chainId", json!([]))
+            .and_then(|value| parse_quantity_u64(&value))
+            .map_err(|e| match e {
+                RpcError::Rpc(..) => {
+                    RpcError::InvalidResponse(format!("failed to get the chain id: {}", e))
+                }
+                e => e,
+            })?;
+
+        let mut state = self.lock();
+        if chain_id != self.chain_id {
+            let status = &mut state.statuses[i];
+            status.health = EndpointHealth::WrongChain { chain_id };
+            return Err(RpcError::InvalidResponse(format!(
+                "{}, expected {}",
+                status.health, self.chain_id
+            )));
+        }
+
+        state.verified[i] = true;
+        Ok(())
+    }
+
+    pub fn request(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let (candidates, mut errors) = self.candidates();
+
+        for i in candidates {
+            let started = Instant::now();
+            let result = self
+                .verify(i)
+                .and_then(|()| self.transports[i].request(method, params.clone()));
+
+            let mut state = self.lock();
+            match result {
+                Err(e) if matches!(state.statuses[i].health, EndpointHealth::WrongChain { .. }) => {
+                    errors.push((state.statuses[i].url.clone(), e));
+                }
+                Err(e) if e.is_endpoint_failure() => {
+                    let status = &mut state.statuses[i];
+                    status.health = EndpointHealth::Unreachable {
+                        error: e.to_string(),
+                    };
+                    status.failures += 1;
+                    errors.push((status.url.clone(), e));
+                }
+                result => {
+                    let status = &mut state.statuses[i];
+                    status.failures = 0;
+                    status.latency_ms = Some(started.elapsed().as_millis() as u64);
+                    if let EndpointHealth::Unreachable { .. } = status.health {
+                        status.health = EndpointHealth::Unknown;
+                    }
+
+                    if state.active != i {
+                        Self::set_active(&mut state, i);
+                    }
+                    return result;
+                }
+            }
+        }
+
+        Err(RpcError::AllEndpointsFailed(errors))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::mock::MockNode;
+
+    fn pool(nodes: &[&str], options: PoolOptions) -> EndpointPool {
+        EndpointPool::new(
+            56,
+            nodes.iter().map(|url| url.to_string()).collect(),
+            options,
+        )
+    }
+
+    #[test]
+    fn test_check_health() {
+        let healthy = MockNode::start(56);
+        healthy.set_block_number(100);
+        let wrong_chain = MockNode::start(1);
+        wrong_chain.set_block_number(100);
+        let lagging = MockNode::start(56);
+        lagging.set_block_number(90);
+        let unreachable = MockNode::unreachable_url();
+
+        let pool = pool(
+            &[
+                &unreachable,
+                &wrong_chain.url(),
+                &lagging.url(),
+                &healthy.url(),
+            ],
+            PoolOptions::default(),
+        );
+        let health: Vec<_> = pool
+            .check_health()
+            .into_iter()
+            .map(|status| (status.health, status.block_number, status.active))
+            .collect();
+
+        assert!(matches!(
+            health[0],
+            (EndpointHealth::Unreachable { .. }, None, false)
+        ));
+        assert_eq!(
+            health[1..],
+            [
+                (EndpointHealth::WrongChain { chain_id: 1 }, Some(100), false),
+                (EndpointHealth::Lagging { blocks: 10 }, Some(90), false),
+                (EndpointHealth::Healthy, Some(100), true),
+            ]
+        );
+        assert_eq!(pool.active_url(), healthy.url());
+
+        let report = pool.report();
+        assert!(report.contains(&format!("* {}: healthy, block 100", healthy.url())));
+        assert!(report.contains(&format!("  {}: wrong chain id 1", wrong_chain.url())));
+
+        // the wrong chain is never used, even when nothing else answers
+        drop(healthy);
+        drop(lagging);
+        match pool.request("eth_blockNumber", json!([])) {
+            Err(RpcError::AllEndpointsFailed(errors)) => {
+                assert_eq!(errors.len(), 4);
+                assert_eq!(errors[0].0, wrong_chain.url());
+            }
+            result => panic!("expected all the endpoints to fail, got {:?}", result),
+        }
+        assert_eq!(wrong_chain.calls_to("eth_blockNumber"), 1);
+    }
+
+    #[test]
+    fn test_failover() {
+        let primary = MockNode::start(56);
+        primary.set_delay(Duration::from_millis(500));
+        let unavailable = MockNode::start(56);
+        unavailable.set_http_status(Some(503));
+        let failover = MockNode::start(56);
+        failover.set_block_number(7);
+
+        let options = PoolOptions {
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let pool = pool(
+            &[&primary.url(), &unavailable.url(), &failover.url()],
+            options,
+        );
+
+        assert_eq!(pool.request("eth_blockNumber", json!([])), Ok(json!("0x7")));
+        assert_eq!(pool.active_url(), failover.url());
+
+        let status = pool.status();
+        assert!(matches!(
+            status[0].health,
+            EndpointHealth::Unreachable { .. }
+        ));
+        assert_eq!(status[1].failures, 1);
+        assert!(status[2].active);
+
+        // the active endpoint is tried first from now on
+        assert_eq!(pool.request("eth_blockNumber", json!([])), Ok(json!("0x7")));
+        assert_eq!(unavailable.calls_to("eth_blockNumber"), 0);
+    }
+
+    #[test]
+    fn test_verify_on_first_use() {
+        let wrong_chain = MockNode::start(1);
+        let failover = MockNode::start(56);
+        failover.set_block_number(7);
+
+        // no health check ran, e.g. the nodes were down while the chain ids were verified
+        let pool = pool(
+            &[&wrong_chain.url(), &failover.url()],
+            PoolOptions::default(),
+        );
+        assert_eq!(pool.request("eth_blockNumber", json!([])), Ok(json!("0x7")));
+        assert_eq!(pool.request("eth_blockNumber", json!([])), Ok(json!("0x7")));
+
+        assert_eq!(wrong_chain.calls_to("eth_blockNumber"), 0);
+        assert_eq!(
+            pool.status()[0].health,
+            EndpointHealth::WrongChain { chain_id: 1 }
+        );
+        assert_eq!(pool.active_url(), failover.url());
+        // each endpoint is checked once
+        assert_eq!(wrong_chain.calls_to("eth_chainId"), 1);
+        assert_eq!(failover.calls_to("eth_chainId"), 1);
+
+        // a health check verifies the endpoints already
+        let pool = self::pool(&[&failover.url()], PoolOptions::default());
+        pool.check_health();
+        pool.request("eth_blockNumber", json!([])).unwrap();
+        assert_eq!(failover.calls_to("eth_chainId"), 2);
+    }
+
+    #[test]
+    fn test_rpc_errors_dont_fail_over() {
+        let primary = MockNode::start(56);
+        primary.handle("eth_call", |_| Err((3, "execution reverted".to_string())));
+        let failover = MockNode::start(56);
+
+        let pool = pool(&[&primary.url(), &failover.url()], PoolOptions::default());
+        assert_eq!(
+            pool.request("eth_call", json!([])),
+            Err(RpcError::Rpc(3, "execution reverted".to_string()))
+        );
+        assert_eq!(failover.calls().len(), 0);
+        assert_eq!(pool.active_url(), primary.url());
+    }
+
+    #[test]
+    fn test_for_network() {
+        let network: Network = toml::from_str(
+            r#"
+            name = "bsc"
+            kind = "EVM"
+            symbol = "bnb"
+            chain_id = 56
+            node_url = "http://primary"
+            node_url_failover = ["http://first", "http://second"]
+            min_balance_coin = 0.2
+            "#,
+        )
+        .unwrap();
+
+        let urls: Vec<_> = EndpointPool::for_network(&network, PoolOptions::default())
+            .status()
+            .into_iter()
+            .map(|status| status.url)
+            .collect();
+        assert_eq!(urls, ["http://primary", "http://first", "http://second"]);
+    }
+}
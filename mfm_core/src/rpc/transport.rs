@@ -0,0 +1,115 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+use super::RpcError;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sends JSON-RPC 2.0 requests to a single node over HTTP.
+#[derive(Debug)]
+pub struct HttpTransport {
+    url: String,
+    agent: ureq::Agent,
+    next_id: AtomicU64,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str, timeout: Duration) -> Self {
+        Self {
+            url: url.to_string(),
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn request(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let body = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+
+        let response = self
+            .agent
+            .post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string())
+            .map_err(|e| match e {
+                ureq::Error::Status(status, _) => RpcError::Http(self.url.clone(), status),
+                ureq::Error::Transport(e) => RpcError::Transport(self.url.clone(), e.to_string()),
+            })?;
+
+        let response: Value = response
+            .into_string()
+            .map_err(|e| RpcError::Transport(self.url.clone(), e.to_string()))
+            .and_then(|body| {
+                serde_json::from_str(&body).map_err(|e| RpcError::InvalidResponse(e.to_string()))
+            })?;
+
+        if let Some(error) = response.get("error") {
+            return Err(RpcError::Rpc(
+                error["code"].as_i64().unwrap_or_default(),
+                error["message"].as_str().unwrap_or_default().to_string(),
+            ));
+        }
+
+        match response.get("result") {
+            Some(result) if response["id"] == json!(id) => Ok(result.clone()),
+            Some(_) => Err(RpcError::InvalidResponse(format!(
+                "expected the id {}, found {}",
+                id, response["id"]
+            ))),
+            None => Err(RpcError::InvalidResponse(
+                "missing both result and error".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::rpc::mock::MockNode;
+
+    #[test]
+    fn test_request() {
+        let node = MockNode::start(56);
+        let transport = HttpTransport::new(&node.url(), DEFAULT_TIMEOUT);
+
+        assert_eq!(
+            transport.request("eth_chainId", json!([])),
+            Ok(json!("0x38"))
+        );
+        assert_eq!(
+            transport.request("eth_unknown", json!([])),
+            Err(RpcError::Rpc(
+                -32601,
+                "the method eth_unknown does not exist/is not available".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_transport_errors() {
+        let node = MockNode::start(56);
+        node.set_delay(Duration::from_millis(500));
+        let transport = HttpTransport::new(&node.url(), Duration::from_millis(50));
+        assert!(matches!(
+            transport.request("eth_chainId", json!([])),
+            Err(RpcError::Transport(_, _))
+        ));
+
+        let unreachable = HttpTransport::new(&MockNode::unreachable_url(), DEFAULT_TIMEOUT);
+        assert!(matches!(
+            unreachable.request("eth_chainId", json!([])),
+            Err(RpcError::Transport(_, _))
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+
+use super::{from_hex, parse_quantity, parse_quantity_u64, to_hex, to_quantity, RpcError};
+use crate::config::address::Address;
+
+/// The block a state is read at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockId {
+    #[default]
+    Latest,
+    Pending,
+    Number(u64),
+}
+
+impl BlockId {
+    pub fn to_value(&self) -> Value {
+        match self {
+            Self::Latest => json!("latest"),
+            Self::Pending => json!("pending"),
+            Self::Number(number) => json!(to_quantity((*number).into())),
+        }
+    }
+}
+
+/// A 32 bytes transaction hash.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TxHash(pub [u8; 32]);
+
+impl fmt::Display for TxHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", to_hex(&self.0))
+    }
+}
+
+impl fmt::Debug for TxHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TxHash({})", to_hex(&self.0))
+    }
+}
+
+impl FromStr for TxHash {
+    type Err = RpcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = from_hex(s)?;
+        <[u8; 32]>::try_from(bytes.as_slice())
+            .map(Self)
+            .map_err(|_| RpcError::InvalidResponse(format!("invalid transaction hash '{}'", s)))
+    }
+}
+
+impl Serialize for TxHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for TxHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// The message of an `eth_call` or `eth_estimateGas`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CallRequest {
+    pub from: Option<Address>,
+    pub to: Address,
+    pub data: Vec<u8>,
+    /// In wei.
+    pub value: Option<u128>,
+    pub gas: Option<u128>,
+}
+
+impl CallRequest {
+    pub fn new(to: Address, data: Vec<u8>) -> Self {
+        Self {
+            to,
+            data,
+            ..Default::default()
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        let mut call = json!({"to": self.to.to_string(), "data": to_hex(&self.data)});
+        if let Some(from) = self.from {
+            call["from"] = json!(from.to_string());
+        }
+        if let Some(value) = self.value {
+            call["value"] = json!(to_quantity(value));
+        }
+        if let Some(gas) = self.gas {
+            call["gas"] = json!(to_quantity(gas));
+        }
+        call
+    }
+}
+
+/// The answer of `eth_feeHistory`; the fees are in wei.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeHistory {
+    pub oldest_block: u64,
+    /// One more than the requested blocks, the last one being the next block.
+    pub base_fee_per_gas: Vec<u128>,
+    pub gas_used_ratio: Vec<f64>,
+    /// The priority fees at the requested percentiles, per block.
+    pub reward: Vec<Vec<u128>>,
+}
+
+impl FeeHistory {
+    pub fn from_value(value: &Value) -> Result<Self, RpcError> {
+        let quantities = |value: &Value| -> Result<Vec<u128>, RpcError> {
+            value
+                .as_array()
+                .map(|values| values.iter().map(parse_quantity).collect())
+                .unwrap_or_else(|| Ok(vec![]))
+        };
+
+        Ok(Self {
+            oldest_block: parse_quantity_u64(&value["oldestBlock"])?,
+            base_fee_per_gas: quantities(&value["baseFeePerGas"])?,
+            gas_used_ratio: value["gasUsedRatio"]
+                .as_array()
+                .map(|ratios| ratios.iter().filter_map(Value::as_f64).collect())
+                .unwrap_or_default(),
+            reward: value["reward"]
+                .as_array()
+                .map(|rewards| rewards.iter().map(quantities).collect())
+                .unwrap_or_else(|| Ok(vec![]))?,
+        })
+    }
+
+    /// The base fee of the next block.
+    pub fn next_base_fee(&self) -> Option<u128> {
+        self.base_fee_per_gas.last().copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionReceipt {
+    pub transaction_hash: TxHash,
+    pub block_number: u64,
+    /// Whether the transaction succeeded rather than reverted.
+    pub status: bool,
+    pub gas_used: u128,
+    pub effective_gas_price: Option<u128>,
+    pub contract_address: Option<Address>,
+}
+
+impl TransactionReceipt {
+    pub fn from_value(value: &Value) -> Result<Self, RpcError> {
+        let invalid = |field: &str| {
+            RpcError::InvalidResponse(format!("invalid receipt field {}: {}", field, value[field]))
+        };
+        let optional_quantity = |field: &str| match &value[field] {
+            Value::Null => Ok(None),
+            field_value => parse_quantity(field_value)
+                .map(Some)
+                .map_err(|_| invalid(field)),
+        };
+
+        Ok(Self {
+            transaction_hash: value["transactionHash"]
+                .as_str()
+                .and_then(|hash| hash.parse().ok())
+                .ok_or_else(|| invalid("transactionHash"))?,
+            block_number: parse_quantity_u64(&value["blockNumber"])
+                .map_err(|_| invalid("blockNumber"))?,
+            status: parse_quantity(&value["status"]).map_err(|_| invalid("status"))? == 1,
+            gas_used: parse_quantity(&value["gasUsed"]).map_err(|_| invalid("gasUsed"))?,
+            effective_gas_price: optional_quantity("effectiveGasPrice")?,
+            contract_address: match &value["contractAddress"] {
+                Value::Null => None,
+                address => Some(
+                    address
+                        .as_str()
+                        .and_then(|address| address.parse().ok())
+                        .ok_or_else(|| invalid("contractAddress"))?,
+                ),
+            },
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+
+use k256::ecdsa::SigningKey;
+
+use super::{message_hash, Signature, SignedTransaction, Signer, SignerError, Transaction};
+use crate::config::{address::Address, authentication::wallet::PrivateKey};
+
+/// Signs with a private key held in memory, as unlocked from a wallet or derived
+/// from a mnemonic.
+pub struct LocalSigner {
+    key: PrivateKey,
+    address: Address,
+}
+
+impl LocalSigner {
+    pub fn new(key: PrivateKey) -> Self {
+        Self {
+            address: key.address(),
+            key,
+        }
+    }
+
+    fn sign_hash(&self, hash: &[u8; 32]) -> Result<Signature, SignerError> {
+        let signing_key = SigningKey::from(self.key.secret_key()?);
+        let (signature, recovery_id) = signing_key
+            .sign_prehash_recoverable(hash)
+            .map_err(|e| SignerError::InvalidTransaction(e.to_string()))?;
+
+        let (r, s) = signature.split_bytes();
+        Ok(Signature {
+            r: r.into(),
+            s: s.into(),
+            y_parity: recovery_id.is_y_odd(),
+        })
+    }
+}
+
+impl fmt::Debug for LocalSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalSigner")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Signer for LocalSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn sign_transaction(&self, tx: &Transaction) -> Result<SignedTransaction, SignerError> {
+        if tx.chain_id == 0 {
+            return Err(SignerError::InvalidTransaction(
+                "the chain id is missing".to_string(),
+            ));
+        }
+
+        let signature = self.sign_hash(&tx.signing_hash())?;
+        Ok(SignedTransaction::new(tx.encode_signed(&signature)))
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.sign_hash(&message_hash(message))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        rpc::{from_hex, to_hex},
+        signer::Fees,
+    };
+
+    use super::*;
+
+    fn signer(key: &str) -> LocalSigner {
+        LocalSigner::new(PrivateKey::from_hex(key.as_bytes()).unwrap())
+    }
+
+    fn address(address: &str) -> Option<Address> {
+        Some(address.parse().unwrap())
+    }
+
+    // the example of EIP-155
+    #[test]
+    fn test_sign_legacy() {
+        let signer = signer("0x4646464646464646464646464646464646464646464646464646464646464646");
+        let tx = Transaction {
+            chain_id: 1,
+            nonce: 9,
+            fees: Fees::Legacy {
+                gas_price: 20_000_000_000,
+            },
+            gas: 21_000,
+            to: address("0x3535353535353535353535353535353535353535"),
+            value: 1_000_000_000_000_000_000,
+            data: vec![],
+        };
+
+        assert_eq!(
+            to_hex(&tx.signing_hash()),
+            "0xdaf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+        );
+
+        let signed = signer.sign_transaction(&tx).unwrap();
+        assert_eq!(
+            to_hex(&signed.raw),
+            "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(
+            signed.hash.0,
+            crate::config::address::keccak256(&signed.raw)
+        );
+        assert_eq!(Transaction::decode_signed(&signed.raw), Some(tx));
+    }
+
+    // checked against an independent implementation
+    #[test]
+    fn test_sign_eip1559() {
+        let signer = signer("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+        let tx = Transaction {
+            chain_id: 56,
+            nonce: 1,
+            fees: Fees::Eip1559 {
+                max_fee_per_gas: 5_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+            },
+            gas: 100_000,
+            to: address("0xe9e7CEA3DedcA5984780Bafc599bD69ADd087D56"),
+            value: 0,
+            // transfer(0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266, 1e18)
+            data: from_hex("0xa9059cbb000000000000000000000000f39fd6e51aad88f6f4ce6ab8827279cfffb922660000000000000000000000000000000000000000000000000de0b6b3a7640000").unwrap(),
+        };
+
+        assert_eq!(
+            to_hex(&tx.signing_hash()),
+            "0xdf4aa4a81448b75b042267f5ed2ed4e2cc94d75bd4cc07966ff3cd3605310c27"
+        );
+
+        let signed = signer.sign_transaction(&tx).unwrap();
+        assert_eq!(
+            to_hex(&signed.raw),
+            "0x02f8b13801843b9aca0085012a05f200830186a094e9e7cea3dedca5984780bafc599bd69add087d5680b844a9059cbb000000000000000000000000f39fd6e51aad88f6f4ce6ab8827279cfffb922660000000000000000000000000000000000000000000000000de0b6b3a7640000c080a0064c823b8b6b87b831238a8e2f9ef9cbc7e77dec1e80d5a039b999eb6de5a745a05249457f70f0d48990cf3095eb817b20cdd9e354b8670706b6d8f3d856f1ca6b"
+        );
+        assert_eq!(
+            signed.hash.to_string(),
+            "0xc4825b76fb8a14a6ab26b7dad11445813fd3c91a01c3430a3c9c5f7f6a320778"
+        );
+        assert_eq!(Transaction::decode_signed(&signed.raw), Some(tx));
+        assert_eq!(Transaction::decode_signed(&signed.raw[1..]), None);
+    }
+
+    // the example of viem's signMessage
+    #[test]
+    fn test_sign_message() {
+        let signer = signer("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+        let signature = signer.sign_message(b"hello world").unwrap();
+        assert_eq!(
+            to_hex(&signature.to_bytes()),
+            "0xa461f509887bd19e312c0c58467ce8ff8e300d3c1a90b608a760c5b80318eaf15fe57c96f9175d6cd4daad4663763baa7e78836e067d0163e9a2ccf2ff753f5b1b"
+        );
+    }
+
+    #[test]
+    fn test_missing_chain_id() {
+        let signer = signer("0x4646464646464646464646464646464646464646464646464646464646464646");
+        assert!(matches!(
+            signer.sign_transaction(&Transaction::default()),
+            Err(SignerError::InvalidTransaction(_))
+        ));
+    }
+
+    #[test]
+    fn test_debug_hides_the_key() {
+        let signer = signer("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+        let debug = format!("{:?}", signer);
+        assert_eq!(
+            debug,
+            "LocalSigner { address: Address(0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266), .. }"
+        );
+    }
+}
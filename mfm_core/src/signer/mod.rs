@@ -0,0 +1,313 @@
+use std::fmt;
+
+use anyhow::anyhow;
+use mfm_machine::state::{StateError, StateErrorRecoverability};
+use serde_json::{json, Value};
+
+use crate::config::{
+    address::{keccak256, Address},
+    authentication::wallet::WalletError,
+    network::Network,
+};
+use crate::rpc::{to_hex, to_quantity, types::TxHash, RpcError};
+
+pub mod local;
+pub mod remote;
+pub mod rlp;
+
+#[derive(Debug)]
+pub enum SignerError {
+    Wallet(WalletError),
+    /// The auth method can't sign, e.g. it's handled by a browser extension.
+    Unsupported(String),
+    InvalidTransaction(String),
+    /// A remote signer denied the request, or signed another transaction than the one requested.
+    Rejected(String),
+    Remote(RpcError),
+}
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wallet(e) => write!(f, "{}", e),
+            Self::Unsupported(e) => write!(f, "unsupported signer: {}", e),
+            Self::InvalidTransaction(e) => write!(f, "invalid transaction: {}", e),
+            Self::Rejected(e) => write!(f, "the signer rejected the request: {}", e),
+            Self::Remote(e) => write!(f, "remote signer: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SignerError {}
+
+impl From<WalletError> for SignerError {
+    fn from(e: WalletError) -> Self {
+        Self::Wallet(e)
+    }
+}
+
+impl From<SignerError> for StateError {
+    fn from(e: SignerError) -> Self {
+        match e {
+            // a denial is a decision, retrying would only ask again
+            SignerError::Rejected(_) => {
+                StateError::OffChainError(StateErrorRecoverability::Unrecoverable, anyhow!(e))
+            }
+            SignerError::Remote(e) => e.into(),
+            // a wrong password or an unsupported method needs the config fixed
+            _ => StateError::ParsingInput(StateErrorRecoverability::Unrecoverable, anyhow!(e)),
+        }
+    }
+}
+
+/// How a transaction pays for its gas, in wei.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fees {
+    Legacy {
+        gas_price: u128,
+    },
+    Eip1559 {
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    },
+}
+
+impl Default for Fees {
+    fn default() -> Self {
+        Self::Eip1559 {
+            max_fee_per_gas: 0,
+            max_priority_fee_per_gas: 0,
+        }
+    }
+}
+
+/// An unsigned transaction; legacy ones are signed with the EIP-155 replay
+/// protection, and EIP-1559 ones are sent with an empty access list.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub fees: Fees,
+    pub gas: u64,
+    /// `None` creates a contract.
+    pub to: Option<Address>,
+    /// In wei.
+    pub value: u128,
+    pub data: Vec<u8>,
+}
+
+impl Transaction {
+    pub fn for_network(network: &Network, fees: Fees) -> Self {
+        Self {
+            chain_id: network.chain_id.into(),
+            fees,
+            ..Default::default()
+        }
+    }
+
+    fn to_field(&self) -> Vec<u8> {
+        rlp::encode_bytes(self.to.as_ref().map_or(&[][..], |to| &to.as_bytes()[..]))
+    }
+
+    fn fields(&self) -> Vec<Vec<u8>> {
+        match self.fees {
+            Fees::Legacy { gas_price } => vec![
+                rlp::encode_uint(self.nonce.into()),
+                rlp::encode_uint(gas_price),
+                rlp::encode_uint(self.gas.into()),
+                self.to_field(),
+                rlp::encode_uint(self.value),
+                rlp::encode_bytes(&self.data),
+            ],
+            Fees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => vec![
+                rlp::encode_uint(self.chain_id.into()),
+                rlp::encode_uint(self.nonce.into()),
+                rlp::encode_uint(max_priority_fee_per_gas),
+                rlp::encode_uint(max_fee_per_gas),
+                rlp::encode_uint(self.gas.into()),
+                self.to_field(),
+                rlp::encode_uint(self.value),
+                rlp::encode_bytes(&self.data),
+                rlp::encode_list(&[]),
+            ],
+        }
+    }
+
+    fn envelope(&self, list: Vec<u8>) -> Vec<u8> {
+        match self.fees {
+            Fees::Legacy { .. } => list,
+            Fees::Eip1559 { .. } => [vec![0x02], list].concat(),
+        }
+    }
+
+    /// The hash to sign.
+    pub fn signing_hash(&self) -> [u8; 32] {
+        let mut fields = self.fields();
+        if let Fees::Legacy { .. } = self.fees {
+            fields.extend([
+                rlp::encode_uint(self.chain_id.into()),
+                rlp::encode_uint(0),
+                rlp::encode_uint(0),
+            ]);
+        }
+
+        keccak256(&self.envelope(rlp::encode_list(&fields)))
+    }
+
+    /// The raw transaction, as sent by `eth_sendRawTransaction`.
+    pub fn encode_signed(&self, signature: &Signature) -> Vec<u8> {
+        let v = match self.fees {
+            Fees::Legacy { .. } => self.chain_id as u128 * 2 + 35 + signature.y_parity as u128,
+            Fees::Eip1559 { .. } => signature.y_parity as u128,
+        };
+
+        let mut fields = self.fields();
+        fields.extend([
+            rlp::encode_uint(v),
+            encode_word(&signature.r),
+            encode_word(&signature.s),
+        ]);
+
+        self.envelope(rlp::encode_list(&fields))
+    }
+
+    /// The transaction of a raw one built by [`Transaction::encode_signed`], `None`
+    /// when it isn't one.
+    pub fn decode_signed(raw: &[u8]) -> Option<Self> {
+        let (typed, list) = match raw.split_first()? {
+            (0x02, list) => (true, list),
+            _ => (false, raw),
+        };
+        let fields = match rlp::decode(list)? {
+            rlp::Item::List(fields) => fields,
+            rlp::Item::Bytes(_) => return None,
+        };
+
+        let uint = |i: usize| -> Option<u128> { fields.get(i)?.as_uint() };
+        let uint_u64 = |i: usize| -> Option<u64> { uint(i)?.try_into().ok() };
+        let to = |i: usize| -> Option<Option<Address>> {
+            match fields.get(i)?.as_bytes()? {
+                [] => Some(None),
+                to => Some(Some(Address::from(<[u8; 20]>::try_from(to).ok()?))),
+            }
+        };
+        let data = |i: usize| -> Option<Vec<u8>> { fields.get(i)?.as_bytes().map(<[u8]>::to_vec) };
+
+        match (typed, fields.len()) {
+            (true, 12) => Some(Self {
+                chain_id: uint_u64(0)?,
+                nonce: uint_u64(1)?,
+                fees: Fees::Eip1559 {
+                    max_priority_fee_per_gas: uint(2)?,
+                    max_fee_per_gas: uint(3)?,
+                },
+                gas: uint_u64(4)?,
+                to: to(5)?,
+                value: uint(6)?,
+                data: data(7)?,
+            }),
+            // with the EIP-155 `v`
+            (false, 9) => Some(Self {
+                chain_id: (uint(6)?.checked_sub(35)? / 2).try_into().ok()?,
+                nonce: uint_u64(0)?,
+                fees: Fees::Legacy {
+                    gas_price: uint(1)?,
+                },
+                gas: uint_u64(2)?,
+                to: to(3)?,
+                value: uint(4)?,
+                data: data(5)?,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The transaction as a JSON-RPC object, e.g. for `eth_signTransaction`.
+    pub fn to_value(&self, from: &Address) -> Value {
+        let mut tx = json!({
+            "from": from.to_string(),
+            "chainId": to_quantity(self.chain_id.into()),
+            "nonce": to_quantity(self.nonce.into()),
+            "gas": to_quantity(self.gas.into()),
+            "value": to_quantity(self.value),
+            "data": to_hex(&self.data),
+        });
+        if let Some(to) = self.to {
+            tx["to"] = json!(to.to_string());
+        }
+
+        match self.fees {
+            Fees::Legacy { gas_price } => tx["gasPrice"] = json!(to_quantity(gas_price)),
+            Fees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                tx["maxFeePerGas"] = json!(to_quantity(max_fee_per_gas));
+                tx["maxPriorityFeePerGas"] = json!(to_quantity(max_priority_fee_per_gas));
+            }
+        }
+        tx
+    }
+}
+
+// a 256 bits integer, without its leading zeros
+fn encode_word(word: &[u8; 32]) -> Vec<u8> {
+    let start = word.iter().position(|byte| *byte != 0).unwrap_or(32);
+    rlp::encode_bytes(&word[start..])
+}
+
+/// A secp256k1 signature, with the parity of the y coordinate of `r` to recover
+/// the signer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    pub y_parity: bool,
+}
+
+impl Signature {
+    /// The 65 bytes `r || s || v` form, with `v` being 27 or 28.
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut bytes = [0u8; 65];
+        bytes[..32].copy_from_slice(&self.r);
+        bytes[32..64].copy_from_slice(&self.s);
+        bytes[64] = 27 + self.y_parity as u8;
+        bytes
+    }
+}
+
+/// A signed transaction, ready to be sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedTransaction {
+    pub raw: Vec<u8>,
+    pub hash: TxHash,
+}
+
+impl SignedTransaction {
+    pub fn new(raw: Vec<u8>) -> Self {
+        Self {
+            hash: TxHash(keccak256(&raw)),
+            raw,
+        }
+    }
+}
+
+/// The EIP-191 hash of a `personal_sign` message.
+pub fn message_hash(message: &[u8]) -> [u8; 32] {
+    let prefix = format!("\x19Ethereum Signed Message:\n{}", message.len());
+    keccak256(&[prefix.as_bytes(), message].concat())
+}
+
+/// Signs for an account; authentication methods resolve into one signer per
+/// account they hold.
+pub trait Signer: fmt::Debug + Send + Sync {
+    fn address(&self) -> Address;
+
+    fn sign_transaction(&self, tx: &Transaction) -> Result<SignedTransaction, SignerError>;
+
+    /// Signs a message the `personal_sign` way, prefixing it as in EIP-191.
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, SignerError>;
+}
@@ -0,0 +1,345 @@
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::{Signature, SignedTransaction, Signer, SignerError, Transaction};
+use crate::{
+    config::{address::Address, authentication::remote::Remote},
+    rpc::{from_hex, parse_quantity, to_hex, transport::HttpTransport, RpcError},
+};
+
+/// Delegates the signatures to an external signer speaking the clef API, where
+/// each request may wait on a manual approval.
+#[derive(Debug)]
+pub struct RemoteSigner {
+    transport: HttpTransport,
+    address: Address,
+}
+
+impl RemoteSigner {
+    pub fn new(remote: &Remote) -> Self {
+        Self {
+            transport: HttpTransport::new(&remote.url, Duration::from_secs(remote.timeout_secs)),
+            address: remote.address,
+        }
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, SignerError> {
+        self.transport.request(method, params).map_err(|e| match e {
+            // clef answers a denial with the generic -32000 code
+            RpcError::Rpc(_, message) if message.to_lowercase().contains("request denied") => {
+                SignerError::Rejected(message)
+            }
+            e => SignerError::Remote(e),
+        })
+    }
+}
+
+fn invalid_response(e: impl ToString) -> SignerError {
+    SignerError::Remote(RpcError::InvalidResponse(e.to_string()))
+}
+
+// the approver may edit the transaction before clef signs it, so both the returned
+// `tx` object and the raw transaction must be the one requested
+fn check_signed(tx: &Transaction, signed: &Value, raw: &[u8]) -> Result<(), SignerError> {
+    let quantity = |field: &str| parse_quantity(&signed[field]).ok();
+    let to = match &signed["to"] {
+        Value::Null => Some(None),
+        to => to
+            .as_str()
+            .and_then(|to| to.parse::<Address>().ok())
+            .map(Some),
+    };
+    let data = signed["input"]
+        .as_str()
+        .or_else(|| signed["data"].as_str())
+        .and_then(|data| from_hex(data).ok());
+
+    let mismatches: Vec<&str> = [
+        ("nonce", quantity("nonce") == Some(tx.nonce.into())),
+        ("to", to == Some(tx.to)),
+        ("value", quantity("value") == Some(tx.value)),
+        ("data", data.as_ref() == Some(&tx.data)),
+        ("chain id", quantity("chainId") == Some(tx.chain_id.into())),
+        (
+            "raw transaction",
+            Transaction::decode_signed(raw).as_ref() == Some(tx),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(field, matches)| (!matches).then_some(field))
+    .collect();
+
+    match mismatches.is_empty() {
+        true => Ok(()),
+        false => Err(SignerError::Rejected(format!(
+            "the signed transaction differs from the requested one in its {}",
+            mismatches.join(", ")
+        ))),
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn sign_transaction(&self, tx: &Transaction) -> Result<SignedTransaction, SignerError> {
+        let result = self.request(
+            "account_signTransaction",
+            json!([tx.to_value(&self.address)]),
+        )?;
+
+        let raw = result["raw"].as_str().ok_or_else(|| {
+            invalid_response(format!("expected a raw transaction, found {}", result))
+        })?;
+        let raw = from_hex(raw).map_err(SignerError::Remote)?;
+
+        check_signed(tx, &result["tx"], &raw)?;
+        Ok(SignedTransaction::new(raw))
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let result = self.request(
+            "account_signData",
+            json!(["text/plain", self.address.to_string(), to_hex(message)]),
+        )?;
+
+        let bytes = result
+            .as_str()
+            .map(from_hex)
+            .transpose()
+            .map_err(SignerError::Remote)?
+            .filter(|bytes| bytes.len() == 65)
+            .ok_or_else(|| {
+                invalid_response(format!("expected a 65 bytes signature, found {}", result))
+            })?;
+
+        Ok(Signature {
+            r: bytes[..32].try_into().expect("checked length"),
+            s: bytes[32..64].try_into().expect("checked length"),
+            // clef answers with v as 27 or 28
+            y_parity: bytes[64] % 27 == 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mfm_machine::state::StateError;
+
+    use crate::{
+        config::authentication::wallet::PrivateKey,
+        rpc::{mock::MockNode, to_quantity},
+        signer::{local::LocalSigner, Fees},
+    };
+
+    use super::*;
+
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    fn local_signer() -> LocalSigner {
+        LocalSigner::new(PrivateKey::from_hex(KEY.as_bytes()).unwrap())
+    }
+
+    fn remote_signer(url: String) -> RemoteSigner {
+        RemoteSigner::new(&Remote {
+            url,
+            address: local_signer().address(),
+            timeout_secs: 5,
+        })
+    }
+
+    fn quantity(tx: &Value, field: &str) -> u128 {
+        parse_quantity(&tx[field]).unwrap()
+    }
+
+    // the `tx` object clef answers with, in the geth format
+    fn tx_object(tx: &Transaction) -> Value {
+        json!({
+            "chainId": to_quantity(tx.chain_id.into()),
+            "nonce": to_quantity(tx.nonce.into()),
+            "to": tx.to.map(|to| to.to_string()),
+            "value": to_quantity(tx.value),
+            "input": to_hex(&tx.data),
+        })
+    }
+
+    // a clef stand-in, signing with the same key as `local_signer` the transaction
+    // requested once edited by `edit`
+    fn stand_in_editing(edit: fn(&mut Transaction, &mut Transaction)) -> MockNode {
+        let node = MockNode::start(56);
+
+        node.handle("account_signTransaction", move |params| {
+            let tx = &params[0];
+            let fees = match tx.get("gasPrice") {
+                Some(_) => Fees::Legacy {
+                    gas_price: quantity(tx, "gasPrice"),
+                },
+                None => Fees::Eip1559 {
+                    max_fee_per_gas: quantity(tx, "maxFeePerGas"),
+                    max_priority_fee_per_gas: quantity(tx, "maxPriorityFeePerGas"),
+                },
+            };
+            let tx = Transaction {
+                chain_id: quantity(tx, "chainId") as u64,
+                nonce: quantity(tx, "nonce") as u64,
+                fees,
+                gas: quantity(tx, "gas") as u64,
+                to: tx["to"].as_str().map(|to| to.parse().unwrap()),
+                value: quantity(tx, "value"),
+                data: from_hex(tx["data"].as_str().unwrap()).unwrap(),
+            };
+
+            // the transaction signed, and the one described in the answer
+            let (mut signed, mut described) = (tx.clone(), tx);
+            edit(&mut signed, &mut described);
+
+            let signed = local_signer().sign_transaction(&signed).unwrap();
+            Ok(json!({"raw": to_hex(&signed.raw), "tx": tx_object(&described)}))
+        });
+
+        node.handle("account_signData", |params| {
+            assert_eq!(params[0], json!("text/plain"));
+            let message = from_hex(params[2].as_str().unwrap()).unwrap();
+            Ok(json!(to_hex(
+                &local_signer().sign_message(&message).unwrap().to_bytes()
+            )))
+        });
+
+        node
+    }
+
+    fn stand_in() -> MockNode {
+        stand_in_editing(|_, _| {})
+    }
+
+    #[test]
+    fn test_sign_as_the_local_signer() {
+        let node = stand_in();
+        let signer = remote_signer(node.url());
+
+        for fees in [
+            Fees::Legacy {
+                gas_price: 5_000_000_000,
+            },
+            Fees::Eip1559 {
+                max_fee_per_gas: 5_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+            },
+        ] {
+            let tx = Transaction {
+                chain_id: 56,
+                nonce: 3,
+                fees,
+                gas: 21_000,
+                to: Some(
+                    "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+                        .parse()
+                        .unwrap(),
+                ),
+                value: 1_000,
+                data: vec![1, 2, 3],
+            };
+            assert_eq!(
+                signer.sign_transaction(&tx).unwrap(),
+                local_signer().sign_transaction(&tx).unwrap()
+            );
+        }
+
+        assert_eq!(
+            signer.sign_message(b"hello world").unwrap(),
+            local_signer().sign_message(b"hello world").unwrap()
+        );
+        assert_eq!(node.calls_to("account_signTransaction"), 2);
+    }
+
+    #[test]
+    fn test_edited_transactions() {
+        let tx = Transaction {
+            chain_id: 56,
+            nonce: 3,
+            fees: Fees::Legacy {
+                gas_price: 5_000_000_000,
+            },
+            gas: 21_000,
+            to: Some(
+                "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+                    .parse()
+                    .unwrap(),
+            ),
+            value: 1_000,
+            data: vec![1, 2, 3],
+        };
+        let rejected =
+            |edit| match remote_signer(stand_in_editing(edit).url()).sign_transaction(&tx) {
+                Err(SignerError::Rejected(e)) => e,
+                result => panic!("expected a rejection, got {:?}", result),
+            };
+
+        // edited in both the raw transaction and its description
+        let e = rejected(|signed, described| {
+            signed.to = None;
+            described.to = None;
+        });
+        assert_eq!(
+            e,
+            "the signed transaction differs from the requested one in its to, raw transaction"
+        );
+
+        let e = rejected(|signed, described| {
+            signed.value = 0;
+            signed.nonce = 4;
+            described.value = 0;
+            described.nonce = 4;
+        });
+        assert!(e.ends_with("in its nonce, value, raw transaction"), "{}", e);
+
+        // described as requested, but signed otherwise
+        let e = rejected(|signed, _| signed.fees = Fees::Legacy { gas_price: 1 });
+        assert!(e.ends_with("in its raw transaction"), "{}", e);
+        let e = rejected(|_, described| described.data = vec![]);
+        assert!(e.ends_with("in its data"), "{}", e);
+    }
+
+    #[test]
+    fn test_approval_errors() {
+        let node = MockNode::start(56);
+        node.handle("account_signTransaction", |_| {
+            Err((-32000, "Request denied".to_string()))
+        });
+        node.handle("account_signData", |_| Ok(json!("0x1234")));
+
+        let signer = remote_signer(node.url());
+        let tx = Transaction {
+            chain_id: 56,
+            ..Default::default()
+        };
+
+        let denied = signer.sign_transaction(&tx).unwrap_err();
+        assert!(matches!(denied, SignerError::Rejected(_)));
+        match StateError::from(denied) {
+            e @ StateError::OffChainError(_, _) => assert!(!e.is_recoverable()),
+            e => panic!("expected an off chain error, got {:?}", e),
+        }
+
+        let invalid = signer.sign_message(b"hello world").unwrap_err();
+        assert!(matches!(
+            invalid,
+            SignerError::Remote(RpcError::InvalidResponse(_))
+        ));
+
+        // the signer being down or the approval timing out is worth a retry
+        let unreachable = remote_signer(MockNode::unreachable_url())
+            .sign_transaction(&tx)
+            .unwrap_err();
+        match StateError::from(unreachable) {
+            e @ StateError::RpcConnection(_, _) => assert!(e.is_recoverable()),
+            e => panic!("expected a connection error, got {:?}", e),
+        }
+    }
+}
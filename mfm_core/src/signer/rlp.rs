@@ -0,0 +1,189 @@
+//! The subset of the RLP encoding transactions need: byte strings, unsigned
+//! integers, and lists of already encoded items, and their decoding.
+
+/// A decoded item: a byte string, or a list of items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Bytes(Vec<u8>),
+    List(Vec<Item>),
+}
+
+impl Item {
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Bytes(bytes) => Some(bytes),
+            Self::List(_) => None,
+        }
+    }
+
+    /// An integer, as encoded by [`encode_uint`].
+    pub fn as_uint(&self) -> Option<u128> {
+        match self.as_bytes()? {
+            bytes if bytes.len() > 16 => None,
+            bytes => Some(
+                bytes
+                    .iter()
+                    .fold(0, |value, byte| value << 8 | *byte as u128),
+            ),
+        }
+    }
+}
+
+fn length_prefix(len: usize, offset: u8) -> Vec<u8> {
+    if len < 56 {
+        return vec![offset + len as u8];
+    }
+
+    let len_bytes: Vec<u8> = len
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|byte| *byte == 0)
+        .collect();
+    let mut prefix = vec![offset + 55 + len_bytes.len() as u8];
+    prefix.extend(len_bytes);
+    prefix
+}
+
+pub fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    if let [byte] = bytes {
+        if *byte < 0x80 {
+            return vec![*byte];
+        }
+    }
+
+    let mut encoded = length_prefix(bytes.len(), 0x80);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+/// Encodes an integer as its big endian bytes without leading zeros, so zero is
+/// the empty string.
+pub fn encode_uint(value: u128) -> Vec<u8> {
+    let bytes: Vec<u8> = value
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|byte| *byte == 0)
+        .collect();
+    encode_bytes(&bytes)
+}
+
+pub fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload = items.concat();
+    let mut encoded = length_prefix(payload.len(), 0xc0);
+    encoded.extend(payload);
+    encoded
+}
+
+// the length after a long prefix, and what follows it
+fn long_length(data: &[u8], len_bytes: u8) -> Option<(usize, &[u8])> {
+    let len_bytes = len_bytes as usize;
+    if data.len() < len_bytes || len_bytes > std::mem::size_of::<usize>() {
+        return None;
+    }
+
+    let (len, rest) = data.split_at(len_bytes);
+    Some((
+        len.iter().fold(0, |len, byte| len << 8 | *byte as usize),
+        rest,
+    ))
+}
+
+fn decode_item(data: &[u8]) -> Option<(Item, &[u8])> {
+    let (&prefix, rest) = data.split_first()?;
+    let (list, len, rest) = match prefix {
+        0x00..=0x7f => return Some((Item::Bytes(vec![prefix]), rest)),
+        0x80..=0xb7 => (false, (prefix - 0x80) as usize, rest),
+        0xb8..=0xbf => {
+            let (len, rest) = long_length(rest, prefix - 0xb7)?;
+            (false, len, rest)
+        }
+        0xc0..=0xf7 => (true, (prefix - 0xc0) as usize, rest),
+        0xf8..=0xff => {
+            let (len, rest) = long_length(rest, prefix - 0xf7)?;
+            (true, len, rest)
+        }
+    };
+    if rest.len() < len {
+        return None;
+    }
+
+    let (mut payload, rest) = rest.split_at(len);
+    if !list {
+        return Some((Item::Bytes(payload.to_vec()), rest));
+    }
+    let mut items = Vec::new();
+    while !payload.is_empty() {
+        let (item, remaining) = decode_item(payload)?;
+        items.push(item);
+        payload = remaining;
+    }
+    Some((Item::List(items), rest))
+}
+
+/// Decodes a single item, `None` when the data isn't exactly one.
+pub fn decode(data: &[u8]) -> Option<Item> {
+    match decode_item(data)? {
+        (item, []) => Some(item),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // from the examples of the ethereum wiki
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode_bytes(b"dog"), vec![0x83, b'd', b'o', b'g']);
+        assert_eq!(encode_bytes(b""), vec![0x80]);
+        assert_eq!(encode_bytes(&[0x0f]), vec![0x0f]);
+        assert_eq!(encode_bytes(&[0x80]), vec![0x81, 0x80]);
+        assert_eq!(encode_uint(0), vec![0x80]);
+        assert_eq!(encode_uint(15), vec![0x0f]);
+        assert_eq!(encode_uint(1024), vec![0x82, 0x04, 0x00]);
+        assert_eq!(
+            encode_list(&[encode_bytes(b"cat"), encode_bytes(b"dog")]),
+            vec![0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']
+        );
+        assert_eq!(encode_list(&[]), vec![0xc0]);
+
+        let lorem = b"Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        let encoded = encode_bytes(lorem);
+        assert_eq!(&encoded[..2], &[0xb8, 0x38]);
+        assert_eq!(&encoded[2..], lorem);
+
+        // [ [], [[]], [ [], [[]] ] ]
+        let empty = encode_list(&[]);
+        let nested = encode_list(std::slice::from_ref(&empty));
+        assert_eq!(
+            encode_list(&[empty.clone(), nested.clone(), encode_list(&[empty, nested])]),
+            vec![0xc7, 0xc0, 0xc1, 0xc0, 0xc3, 0xc0, 0xc1, 0xc0]
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        let bytes = |bytes: &[u8]| Item::Bytes(bytes.to_vec());
+        assert_eq!(decode(&encode_bytes(b"dog")), Some(bytes(b"dog")));
+        assert_eq!(decode(&[0x0f]), Some(bytes(&[0x0f])));
+        assert_eq!(decode(&encode_uint(1024)).unwrap().as_uint(), Some(1024));
+        assert_eq!(decode(&encode_uint(0)).unwrap().as_uint(), Some(0));
+
+        let lorem = b"Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        let list = encode_list(&[encode_bytes(lorem), encode_list(&[encode_bytes(b"cat")])]);
+        assert_eq!(
+            decode(&list),
+            Some(Item::List(vec![
+                bytes(lorem),
+                Item::List(vec![bytes(b"cat")])
+            ]))
+        );
+
+        // truncated, or followed by more data
+        assert_eq!(decode(&list[..list.len() - 1]), None);
+        assert_eq!(decode(&[0x83, b'd', b'o']), None);
+        assert_eq!(decode(&[0x0f, 0x0f]), None);
+        assert_eq!(decode(&[]), None);
+    }
+}
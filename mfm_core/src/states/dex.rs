@@ -0,0 +1,800 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+use mfm_machine::state::{
+    context::ContextWrapper, StateError, StateErrorRecoverability, StateHandler, StateResult,
+};
+use mfm_machine::StateMetadataReqs;
+
+use super::signers::account_signer;
+use super::transactions::{gas_limit, revert_error, sign_call, wait_mined, TxOptions};
+use crate::config::{
+    address::Address,
+    amount::BaseUnits,
+    dexes::{Dex, Kind},
+    network::Network,
+    token::TokenNetwork,
+    Config,
+};
+use crate::contexts::{
+    Quote, SentTransaction, Swap, SwapSide, ACCOUNT, QUOTES, READ_CONFIG, SWAPS, SWAP_ORDERS,
+};
+use crate::dex::{
+    max_amount, min_amount,
+    uniswap_v2::{Router, SwapAmounts, SwapCall},
+};
+use crate::rpc::{client::EvmClient, pool::PoolOptions, types::CallRequest, RpcError};
+use crate::signer::Signer;
+
+fn parsing_error(e: anyhow::Error) -> StateError {
+    StateError::ParsingInput(StateErrorRecoverability::Unrecoverable, e)
+}
+
+pub fn configured_dex<'a>(config: &'a Config, id: &str) -> Result<&'a Dex, StateError> {
+    config
+        .dexes
+        .get(id)
+        .ok_or_else(|| parsing_error(anyhow!("dex '{}' is not defined in the config", id)))
+}
+
+pub fn router(dex: &Dex) -> Router {
+    match dex.kind {
+        Kind::UniswapV2 => Router::for_dex(dex),
+    }
+}
+
+pub fn dex_network<'a>(config: &'a Config, dex: &Dex) -> Result<&'a Network, StateError> {
+    config.networks.get(&dex.network_id).ok_or_else(|| {
+        parsing_error(anyhow!(
+            "network '{}' is not defined in the config",
+            dex.network_id
+        ))
+    })
+}
+
+/// Whether `token` is the native coin of the network of a dex, by its `symbol`.
+pub fn is_native(config: &Config, dex: &Dex, token: &str) -> bool {
+    config
+        .networks
+        .get(&dex.network_id)
+        .is_some_and(|network| network.symbol == token)
+}
+
+/// A token on the network of a dex; the native coin stands for its `wrapped_asset`.
+pub fn token_network<'a>(
+    config: &'a Config,
+    dex: &Dex,
+    token: &str,
+) -> Result<&'a TokenNetwork, StateError> {
+    let token_networks = match config.networks.get(&dex.network_id) {
+        Some(network) if network.symbol == token => {
+            network.wrapped_asset.as_ref().map(|t| &t.networks)
+        }
+        _ => config.tokens.get(token).map(|t| &t.networks),
+    };
+
+    token_networks
+        .and_then(|networks| networks.get(&dex.network_id))
+        .ok_or_else(|| {
+            parsing_error(anyhow!(
+                "token '{}' is not defined on network '{}'",
+                token,
+                dex.network_id
+            ))
+        })
+}
+
+/// The tokens a swap goes through, by id: the token sold, its `path_asset` unless
+/// it's one of the ends, then the token bought.
+pub fn swap_path(
+    config: &Config,
+    dex: &Dex,
+    token_in: &str,
+    token_out: &str,
+) -> Result<Vec<(String, Address)>, StateError> {
+    let sold = token_network(config, dex, token_in)?;
+    let bought = token_network(config, dex, token_out)?;
+    // e.g. the native coin for its wrapped token
+    if sold.address == bought.address {
+        return Err(parsing_error(anyhow!(
+            "token '{}' can't be swapped for '{}', they're the same",
+            token_in,
+            token_out
+        )));
+    }
+
+    let mut path = vec![(token_in.to_string(), sold.address)];
+    let path_asset = token_network(config, dex, &sold.path_asset)?;
+    if path_asset.address != sold.address && path_asset.address != bought.address {
+        path.push((sold.path_asset.clone(), path_asset.address));
+    }
+    path.push((token_out.to_string(), bought.address));
+
+    Ok(path)
+}
+
+/// Quotes the `swap_orders` with the routers of their dex.
+#[derive(Debug, Clone, Default, PartialEq, StateMetadataReqs)]
+#[state(
+    label = "quote_swaps",
+    tags("quoted"),
+    depends_on("chain_verified"),
+    strategy = "latest"
+)]
+pub struct QuoteSwaps {
+    options: PoolOptions,
+}
+
+impl QuoteSwaps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: PoolOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl StateHandler for QuoteSwaps {
+    fn handler(&self, context: ContextWrapper) -> StateResult {
+        let config = READ_CONFIG.read(&context)?.config;
+        let mut clients: BTreeMap<String, EvmClient> = BTreeMap::new();
+        let mut quotes = vec![];
+
+        for order in SWAP_ORDERS.read(&context)? {
+            let dex = configured_dex(&config, &order.dex)?;
+            let path = swap_path(&config, dex, &order.token_in, &order.token_out)?;
+            let addresses: Vec<Address> = path.iter().map(|(_, address)| *address).collect();
+
+            let network = dex_network(&config, dex)?;
+            let client = clients
+                .entry(dex.network_id.clone())
+                .or_insert_with(|| EvmClient::for_network(network, self.options));
+
+            let router = router(dex);
+            let amounts = match order.side {
+                SwapSide::ExactIn => router.get_amounts_out(client, order.amount.0, &addresses),
+                SwapSide::ExactOut => router.get_amounts_in(client, order.amount.0, &addresses),
+            }
+            .map_err(|e| revert_error(&format!("the router of dex '{}'", order.dex), e))?;
+
+            quotes.push(Quote {
+                order,
+                path: path.into_iter().map(|(id, _)| id).collect(),
+                amounts: amounts.into_iter().map(BaseUnits).collect(),
+            });
+        }
+
+        QUOTES.write(&context, &quotes)
+    }
+}
+
+/// The gas a swap is assumed to use per hop of its path when it can't be estimated
+/// yet, e.g. before the router is approved; more than a UniswapV2 router uses.
+pub const SWAP_GAS_PER_HOP: u64 = 150_000;
+
+/// The gas limits of the swaps of the `quotes` left to send from the `account`,
+/// summed by network.
+pub fn estimate_swaps(
+    context: &ContextWrapper,
+    config: &Config,
+    options: PoolOptions,
+) -> Result<BTreeMap<String, u64>, StateError> {
+    let account = ACCOUNT.read(context)?;
+    let sent = SWAPS.read_optional(context)?.unwrap_or_default().len();
+    let deadline = (unix_now() + DEFAULT_DEADLINE).as_secs();
+
+    let mut clients: BTreeMap<String, EvmClient> = BTreeMap::new();
+    let mut estimates: BTreeMap<String, u64> = BTreeMap::new();
+    for quote in QUOTES.read(context)?.iter().skip(sent) {
+        let dex = configured_dex(config, &quote.order.dex)?;
+        let network = dex_network(config, dex)?;
+        let client = clients
+            .entry(dex.network_id.clone())
+            .or_insert_with(|| EvmClient::for_network(network, options));
+
+        let (mut call, _) = swap_call(config, quote, account, deadline)?;
+        call.from = Some(account);
+        let gas = match client.estimate_gas(&call) {
+            Ok(estimate) => gas_limit(estimate),
+            // e.g. short of an allowance the swap is approved with later
+            Err(RpcError::Rpc(_, message)) if message.contains("revert") => {
+                SWAP_GAS_PER_HOP.saturating_mul(quote.path.len().saturating_sub(1) as u64)
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let total = estimates.entry(dex.network_id.clone()).or_default();
+        *total = total.saturating_add(gas);
+    }
+
+    Ok(estimates)
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// How long after it's signed the router accepts a swap, by default.
+pub const DEFAULT_DEADLINE: Duration = Duration::from_secs(20 * 60);
+
+/// Sends the swaps of the `quotes` from the `account`, each one once the one
+/// before it is mined.
+///
+/// A swap accepts the `slippage` of the token sold off its quote, and expires
+/// after the deadline. It's written to `swaps` as soon as it's signed, then once
+/// mined: after a recovery the same transaction is broadcast again rather than
+/// another one signed, unless its deadline passed, as the router would only revert it.
+///
+/// It runs after `ApproveTokens`, so the routers are allowed the tokens sold; a
+/// recovery quotes again, and approves what the new quotes need.
+#[derive(Debug, Clone, PartialEq, StateMetadataReqs)]
+#[state(
+    label = "execute_swaps",
+    tags("swapped"),
+    depends_on("quoted", "approved"),
+    strategy = "latest"
+)]
+pub struct ExecuteSwaps {
+    deadline: Duration,
+    options: PoolOptions,
+    tx_options: TxOptions,
+}
+
+impl Default for ExecuteSwaps {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEADLINE)
+    }
+}
+
+impl ExecuteSwaps {
+    pub fn new(deadline: Duration) -> Self {
+        Self::with_options(deadline, PoolOptions::default(), TxOptions::default())
+    }
+
+    pub fn with_options(deadline: Duration, options: PoolOptions, tx_options: TxOptions) -> Self {
+        Self {
+            deadline,
+            options,
+            tx_options,
+        }
+    }
+
+    fn sign(
+        &self,
+        config: &Config,
+        client: &EvmClient,
+        signer: &dyn Signer,
+        quote: &Quote,
+    ) -> Result<Swap, StateError> {
+        let order = &quote.order;
+        let dex = configured_dex(config, &order.dex)?;
+
+        let deadline = (unix_now() + self.deadline).as_secs();
+        let (call, limit) = swap_call(config, quote, signer.address(), deadline)?;
+
+        let what = format!("the swap of '{}' for '{}'", order.token_in, order.token_out);
+        let transaction = sign_call(client, dex_network(config, dex)?, signer, call, &what)?;
+
+        Ok(Swap {
+            quote: quote.clone(),
+            limit: BaseUnits(limit),
+            deadline,
+            transaction,
+        })
+    }
+}
+
+/// The call of the router swapping a quote for `recipient`, and its limit: the
+/// least bought, or the most sold, given the `slippage` of the token sold.
+pub fn swap_call(
+    config: &Config,
+    quote: &Quote,
+    recipient: Address,
+    deadline: u64,
+) -> Result<(CallRequest, u128), StateError> {
+    let order = &quote.order;
+    let dex = configured_dex(config, &order.dex)?;
+    let path = swap_path(config, dex, &order.token_in, &order.token_out)?;
+
+    let slippage = token_network(config, dex, &order.token_in)?.slippage;
+    let limit = match order.side {
+        SwapSide::ExactIn => min_amount(quote.amount_out().0, slippage),
+        SwapSide::ExactOut => max_amount(quote.amount_in().0, slippage),
+    }
+    .ok_or_else(|| {
+        parsing_error(anyhow!(
+            "the slippage {} of token '{}' is out of range",
+            slippage,
+            order.token_in
+        ))
+    })?;
+    let amounts = match order.side {
+        SwapSide::ExactIn => SwapAmounts::ExactIn {
+            amount_in: quote.amount_in().0,
+            amount_out_min: limit,
+        },
+        SwapSide::ExactOut => SwapAmounts::ExactOut {
+            amount_out: quote.amount_out().0,
+            amount_in_max: limit,
+        },
+    };
+
+    let (data, value) = SwapCall {
+        amounts,
+        path: path.iter().map(|(_, address)| *address).collect(),
+        recipient,
+        deadline,
+        native_in: is_native(config, dex, &order.token_in),
+        native_out: is_native(config, dex, &order.token_out),
+    }
+    .encode();
+
+    let mut call = CallRequest::new(router(dex).address(), data);
+    if value > 0 {
+        call.value = Some(value);
+    }
+    Ok((call, limit))
+}
+
+impl StateHandler for ExecuteSwaps {
+    fn handler(&self, context: ContextWrapper) -> StateResult {
+        let config = READ_CONFIG.read(&context)?.config;
+        let account = ACCOUNT.read(&context)?;
+        let mut swaps = SWAPS.read_optional(&context)?.unwrap_or_default();
+        let mut clients: BTreeMap<String, EvmClient> = BTreeMap::new();
+        let mut signers: BTreeMap<String, Arc<dyn Signer>> = BTreeMap::new();
+
+        for (i, quote) in QUOTES.read(&context)?.iter().enumerate() {
+            let order = &quote.order;
+            let dex = configured_dex(&config, &order.dex)?;
+            let network = dex_network(&config, dex)?;
+            let client = clients
+                .entry(dex.network_id.clone())
+                .or_insert_with(|| EvmClient::for_network(network, self.options));
+
+            match swaps.get(i) {
+                Some(swap) if swap.quote.order != *order => {
+                    return Err(parsing_error(anyhow!(
+                        "swap {} was sent for another order than '{}' for '{}'",
+                        i,
+                        order.token_in,
+                        order.token_out
+                    )));
+                }
+                Some(swap) => check_deadline(client, swap)?,
+                None => {
+                    let signer = match signers.entry(dex.network_id.clone()) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            entry.insert(account_signer(&context, &config, &dex.network_id)?)
+                        }
+                    };
+                    swaps.push(self.sign(&config, client, signer.as_ref(), quote)?);
+                    SWAPS.write(&context, &swaps)?;
+                }
+            }
+
+            let receipt = wait_mined(client, &account, &swaps[i].transaction, self.tx_options)?;
+            if let SentTransaction::Signed { .. } = swaps[i].transaction {
+                swaps[i].transaction = SentTransaction::Mined(receipt.clone());
+                SWAPS.write(&context, &swaps)?;
+            }
+
+            if !receipt.status {
+                return Err(StateError::OnChainError(
+                    StateErrorRecoverability::Unrecoverable,
+                    anyhow!(
+                        "the swap of '{}' for '{}' reverted in transaction {}",
+                        order.token_in,
+                        order.token_out,
+                        receipt.transaction_hash
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// a swap signed before a recovery isn't broadcast again past its deadline, unless
+// it was mined already
+fn check_deadline(client: &EvmClient, swap: &Swap) -> Result<(), StateError> {
+    let hash = match &swap.transaction {
+        SentTransaction::Signed { hash, .. } if unix_now().as_secs() > swap.deadline => hash,
+        _ => return Ok(()),
+    };
+    if client.get_transaction_receipt(hash)?.is_some() {
+        return Ok(());
+    }
+
+    Err(StateError::OnChainError(
+        StateErrorRecoverability::Unrecoverable,
+        anyhow!(
+            "the swap of '{}' for '{}' wasn't mined before its deadline; transaction {} would \
+             revert now",
+            swap.quote.order.token_in,
+            swap.quote.order.token_out,
+            hash
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::rpc::abi::{self, Token};
+    use crate::rpc::{mock::MockNode, to_hex, to_quantity};
+    use crate::signer::SignedTransaction;
+    use crate::states::fixtures::{
+        config_with_cake, config_with_nodes, context_with_config, estimated_calls, fast_tx_options,
+        quoted, start_router, swap_order as order, CAKE, ROUTER,
+    };
+
+    const WBNB: &str = "0xbb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c";
+    const BUSD: &str = "0xe9e7CEA3DedcA5984780Bafc599bD69ADd087D56";
+    const ACCOUNT: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    #[test]
+    fn test_swap_path() {
+        let node = MockNode::start(56);
+        let config = config_with_cake(&node);
+        let dex = configured_dex(&config, "pancake_swap_v2").unwrap();
+        let ids = |token_in, token_out| {
+            swap_path(&config, dex, token_in, token_out)
+                .unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(ids("cake", "busd"), vec!["cake", "wbnb", "busd"]);
+        assert_eq!(ids("busd", "wbnb"), vec!["busd", "wbnb"]);
+        assert_eq!(ids("wbnb", "cake"), vec!["wbnb", "cake"]);
+        assert_eq!(
+            swap_path(&config, dex, "cake", "wbnb").unwrap()[0],
+            ("cake".to_string(), CAKE.parse().unwrap())
+        );
+
+        assert!(swap_path(&config, dex, "busd", "busd").is_err());
+        assert!(swap_path(&config, dex, "bnb", "wbnb").is_err());
+        // the native coin goes through its wrapped token
+        assert_eq!(ids("bnb", "cake"), vec!["bnb", "cake"]);
+        assert_eq!(
+            swap_path(&config, dex, "cake", "bnb").unwrap(),
+            swap_path(&config, dex, "cake", "wbnb").unwrap()[..1]
+                .iter()
+                .cloned()
+                .chain([("bnb".to_string(), WBNB.parse().unwrap())])
+                .collect::<Vec<_>>()
+        );
+        assert!(is_native(&config, dex, "bnb"));
+        assert!(!is_native(&config, dex, "wbnb"));
+        match swap_path(&config, dex, "busd", "usdt") {
+            Err(e @ StateError::ParsingInput(_, _)) => {
+                assert!(e
+                    .to_string()
+                    .contains("token 'usdt' is not defined on network 'bsc'"));
+            }
+            result => panic!("expected a parsing error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_quote_swaps() {
+        let node = start_router();
+        let context = context_with_config(config_with_cake(&node));
+        let orders = vec![
+            order("busd", "wbnb", SwapSide::ExactIn, 4 * 10u128.pow(21)),
+            order("cake", "busd", SwapSide::ExactOut, 1_000),
+        ];
+        SWAP_ORDERS.write(&context, &orders).unwrap();
+
+        assert!(QuoteSwaps::new().handler(context.clone()).is_ok());
+        let quotes = QUOTES.read(&context).unwrap();
+        assert_eq!(
+            quotes,
+            vec![
+                Quote {
+                    order: orders[0].clone(),
+                    path: vec!["busd".to_string(), "wbnb".to_string()],
+                    amounts: vec![BaseUnits(4 * 10u128.pow(21)), BaseUnits(2 * 10u128.pow(21))],
+                },
+                Quote {
+                    order: orders[1].clone(),
+                    path: vec!["cake".to_string(), "wbnb".to_string(), "busd".to_string()],
+                    amounts: vec![BaseUnits(4_000), BaseUnits(2_000), BaseUnits(1_000)],
+                },
+            ]
+        );
+        assert_eq!(quotes[1].amount_in(), BaseUnits(4_000));
+        assert_eq!(quotes[1].amount_out(), BaseUnits(1_000));
+
+        // the exact calldata of the getAmountsIn call
+        let calls = node.calls();
+        let call = &calls
+            .iter()
+            .rfind(|(method, _)| method == "eth_call")
+            .unwrap()
+            .1;
+        let expected = abi::encode_call(
+            "getAmountsIn(uint256,address[])",
+            &[
+                Token::Uint(1_000),
+                Token::Addresses(vec![
+                    CAKE.parse().unwrap(),
+                    WBNB.parse().unwrap(),
+                    BUSD.parse().unwrap(),
+                ]),
+            ],
+        );
+        assert_eq!(
+            call[0]["to"].as_str().unwrap().parse::<Address>().unwrap(),
+            ROUTER.parse().unwrap()
+        );
+        assert_eq!(call[0]["data"], json!(crate::rpc::to_hex(&expected)));
+        assert_eq!(node.calls_to("eth_call"), 2);
+    }
+
+    #[test]
+    fn test_quote_swaps_errors() {
+        let node = start_router();
+        let context = context_with_config(config_with_nodes(&[&node]));
+        let mut unknown = order("busd", "wbnb", SwapSide::ExactIn, 1_000);
+        unknown.dex = "uniswap_v2".to_string();
+        SWAP_ORDERS.write(&context, &vec![unknown]).unwrap();
+
+        match QuoteSwaps::new().handler(context) {
+            Err(e @ StateError::ParsingInput(_, _)) => {
+                assert!(e
+                    .to_string()
+                    .contains("dex 'uniswap_v2' is not defined in the config"));
+            }
+            result => panic!("expected a parsing error, got {:?}", result),
+        }
+
+        // a pair without liquidity
+        node.set_contract(ROUTER.parse().unwrap(), |_| {
+            Err((
+                3,
+                "execution reverted: PancakeLibrary: INSUFFICIENT_LIQUIDITY".to_string(),
+            ))
+        });
+        let context = context_with_config(config_with_nodes(&[&node]));
+        SWAP_ORDERS
+            .write(
+                &context,
+                &vec![order("busd", "wbnb", SwapSide::ExactIn, 1_000)],
+            )
+            .unwrap();
+
+        match QuoteSwaps::new().handler(context.clone()) {
+            Err(e @ StateError::OnChainError(_, _)) => {
+                assert!(!e.is_recoverable());
+                assert!(e.to_string().contains("INSUFFICIENT_LIQUIDITY"));
+            }
+            result => panic!("expected an on chain error, got {:?}", result),
+        }
+        assert!(QUOTES.read_optional(&context).unwrap().is_none());
+
+        // a router answering less amounts than the path has is a bad node
+        node.set_contract(ROUTER.parse().unwrap(), |_| {
+            Ok(abi::encode(&[
+                Token::Uint(32),
+                Token::Uint(1),
+                Token::Uint(5),
+            ]))
+        });
+        match QuoteSwaps::new().handler(context) {
+            Err(e @ StateError::RpcConnection(_, _)) => {
+                assert!(e.is_recoverable());
+                assert!(e.to_string().contains("expected 2 amounts"));
+            }
+            result => panic!("expected a connection error, got {:?}", result),
+        }
+    }
+
+    fn execute_swaps() -> ExecuteSwaps {
+        ExecuteSwaps::with_options(DEFAULT_DEADLINE, PoolOptions::default(), fast_tx_options())
+    }
+
+    #[test]
+    fn test_execute_swaps() {
+        let node = start_router();
+        let orders = [
+            order("busd", "wbnb", SwapSide::ExactIn, 4 * 10u128.pow(21)),
+            order("cake", "busd", SwapSide::ExactOut, 1_000),
+        ];
+        let context = quoted(&node, &orders);
+
+        assert!(execute_swaps().handler(context.clone()).is_ok());
+        let swaps = SWAPS.read(&context).unwrap();
+        assert_eq!(swaps.len(), 2);
+        // 0.5% less than quoted for busd, 1% more for cake
+        assert_eq!(swaps[0].limit, BaseUnits(1_990 * 10u128.pow(18)));
+        assert_eq!(swaps[1].limit, BaseUnits(4_040));
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let deadline = (now + DEFAULT_DEADLINE).as_secs();
+        assert!((deadline - 5..=deadline).contains(&swaps[0].deadline));
+
+        let sent: Vec<_> = node
+            .transactions()
+            .into_iter()
+            .map(|raw| SignedTransaction::new(raw).hash)
+            .collect();
+        for (swap, hash) in swaps.iter().zip(&sent) {
+            match &swap.transaction {
+                SentTransaction::Mined(receipt) => {
+                    assert!(receipt.status);
+                    assert_eq!(receipt.transaction_hash, *hash);
+                }
+                transaction => panic!("expected a mined swap, got {:?}", transaction),
+            }
+        }
+        assert_eq!(sent.len(), 2);
+
+        let (data, _) = SwapCall {
+            amounts: SwapAmounts::ExactOut {
+                amount_out: 1_000,
+                amount_in_max: 4_040,
+            },
+            path: vec![
+                CAKE.parse().unwrap(),
+                WBNB.parse().unwrap(),
+                BUSD.parse().unwrap(),
+            ],
+            recipient: ACCOUNT.parse().unwrap(),
+            deadline: swaps[1].deadline,
+            native_in: false,
+            native_out: false,
+        }
+        .encode();
+        let calls = estimated_calls(&node);
+        assert_eq!(calls[1]["data"], json!(to_hex(&data)));
+        assert_eq!(
+            calls[1]["from"]
+                .as_str()
+                .unwrap()
+                .parse::<Address>()
+                .unwrap(),
+            ACCOUNT.parse().unwrap()
+        );
+        assert!(calls[1].get("value").is_none());
+
+        // all mined, nothing is sent again
+        assert!(execute_swaps().handler(context).is_ok());
+        assert_eq!(node.transactions().len(), 2);
+    }
+
+    #[test]
+    fn test_execute_native_swap() {
+        let node = start_router();
+        let context = quoted(
+            &node,
+            &[order("bnb", "busd", SwapSide::ExactIn, 10u128.pow(18))],
+        );
+        assert_eq!(
+            QUOTES.read(&context).unwrap()[0].path,
+            vec!["bnb".to_string(), "busd".to_string()]
+        );
+
+        assert!(execute_swaps().handler(context).is_ok());
+        let call = &estimated_calls(&node)[0];
+        let data = call["data"].as_str().unwrap();
+        // swapExactETHForTokens, paying the amount sold
+        assert!(data.starts_with("0x7ff36ab5"));
+        assert!(data.contains(&WBNB[2..].to_lowercase()));
+        assert_eq!(call["value"], json!(to_quantity(10u128.pow(18))));
+    }
+
+    #[test]
+    fn test_execute_swaps_recovery() {
+        let node = start_router();
+        node.set_auto_mine(false);
+        let orders = [order("busd", "wbnb", SwapSide::ExactIn, 1_000)];
+        let context = quoted(&node, &orders);
+
+        match execute_swaps().handler(context.clone()) {
+            Err(e @ StateError::OnChainError(_, _)) => {
+                assert!(e.is_recoverable());
+                assert!(e.to_string().contains("wasn't mined"));
+            }
+            result => panic!("expected an on chain error, got {:?}", result),
+        }
+        let signed = SWAPS.read(&context).unwrap()[0].transaction.clone();
+        assert!(matches!(signed, SentTransaction::Signed { .. }));
+
+        // the recovery quotes again, and broadcasts the signed swap again
+        QuoteSwaps::new().handler(context.clone()).unwrap();
+        node.mine();
+        assert!(execute_swaps().handler(context.clone()).is_ok());
+
+        let mut sent = node.transactions();
+        sent.dedup();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(SignedTransaction::new(sent.remove(0)).hash, signed.hash());
+        assert!(matches!(
+            SWAPS.read(&context).unwrap()[0].transaction,
+            SentTransaction::Mined(_)
+        ));
+
+        // the orders changed since
+        SWAP_ORDERS
+            .write(
+                &context,
+                &vec![order("wbnb", "busd", SwapSide::ExactIn, 1_000)],
+            )
+            .unwrap();
+        QuoteSwaps::new().handler(context.clone()).unwrap();
+        match execute_swaps().handler(context) {
+            Err(e @ StateError::ParsingInput(_, _)) => {
+                assert!(e.to_string().contains("swap 0 was sent for another order"));
+            }
+            result => panic!("expected a parsing error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_execute_swaps_expired() {
+        let node = start_router();
+        node.set_auto_mine(false);
+        let context = quoted(&node, &[order("busd", "wbnb", SwapSide::ExactIn, 1_000)]);
+        assert!(execute_swaps().handler(context.clone()).is_err());
+
+        let mut swaps = SWAPS.read(&context).unwrap();
+        swaps[0].deadline = unix_now().as_secs() - 1;
+        SWAPS.write(&context, &swaps).unwrap();
+        match execute_swaps().handler(context.clone()) {
+            Err(e @ StateError::OnChainError(_, _)) => {
+                assert!(!e.is_recoverable());
+                assert!(e
+                    .to_string()
+                    .contains("the swap of 'busd' for 'wbnb' wasn't mined before its deadline"));
+            }
+            result => panic!("expected an on chain error, got {:?}", result),
+        }
+        assert_eq!(node.transactions().len(), 1);
+
+        // mined in time after all
+        node.mine();
+        assert!(execute_swaps().handler(context).is_ok());
+        let mut sent = node.transactions();
+        sent.dedup();
+        assert_eq!(sent.len(), 1);
+    }
+
+    #[test]
+    fn test_execute_swaps_revert() {
+        let node = start_router();
+        node.set_revert_transactions(true);
+        let context = quoted(&node, &[order("busd", "wbnb", SwapSide::ExactIn, 1_000)]);
+
+        for _ in 0..2 {
+            match execute_swaps().handler(context.clone()) {
+                Err(e @ StateError::OnChainError(_, _)) => {
+                    assert!(!e.is_recoverable());
+                    assert!(e
+                        .to_string()
+                        .contains("the swap of 'busd' for 'wbnb' reverted in transaction"));
+                }
+                result => panic!("expected an on chain error, got {:?}", result),
+            }
+        }
+
+        assert!(matches!(
+            &SWAPS.read(&context).unwrap()[0].transaction,
+            SentTransaction::Mined(receipt) if !receipt.status
+        ));
+        assert_eq!(node.transactions().len(), 1);
+    }
+}
@@ -0,0 +1,681 @@
+use std::collections::{btree_map::Entry, BTreeMap};
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use mfm_machine::state::{
+    context::ContextWrapper, StateError, StateErrorRecoverability, StateHandler, StateResult,
+};
+use mfm_machine::StateMetadataReqs;
+
+use super::dex::{configured_dex, dex_network, is_native, router, token_network};
+use super::signers::account_signer;
+use super::transactions::{gas_limit, revert_error, sign_call, wait_mined, TxOptions};
+use crate::config::{address::Address, amount::BaseUnits, Config};
+use crate::contexts::{
+    Approval, SentTransaction, SwapSide, TokenBalance, ACCOUNT, APPROVALS, QUOTES, READ_CONFIG,
+    SWAPS, SWAP_ORDERS, TOKENS,
+};
+use crate::dex::max_amount;
+use crate::erc20::{Allowance, Erc20};
+use crate::rpc::{client::EvmClient, pool::PoolOptions, types::CallRequest};
+use crate::signer::Signer;
+
+/// Reads the symbol, the decimals and the balance of the `account` of the ERC-20
+/// tokens the `swap_orders` sell and buy; the native coins are left out.
+#[derive(Debug, Clone, Default, PartialEq, StateMetadataReqs)]
+#[state(
+    label = "read_tokens",
+    tags("tokens_read"),
+    depends_on("chain_verified"),
+    strategy = "latest"
+)]
+pub struct ReadTokens {
+    options: PoolOptions,
+}
+
+impl ReadTokens {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: PoolOptions) -> Self {
+        Self { options }
+    }
+
+    fn read(
+        &self,
+        client: &EvmClient,
+        token: &Erc20,
+        id: &str,
+        account: &Address,
+    ) -> Result<TokenBalance, StateError> {
+        let what = format!("token '{}'", id);
+        let symbol = token.symbol(client).map_err(|e| revert_error(&what, e))?;
+        let decimals = token.decimals(client).map_err(|e| revert_error(&what, e))?;
+        let balance = token
+            .balance_of(client, account)
+            .map_err(|e| revert_error(&what, e))?;
+
+        Ok(TokenBalance {
+            address: token.address(),
+            symbol,
+            decimals,
+            balance: BaseUnits(balance),
+        })
+    }
+}
+
+impl StateHandler for ReadTokens {
+    fn handler(&self, context: ContextWrapper) -> StateResult {
+        let config = READ_CONFIG.read(&context)?.config;
+        let account = ACCOUNT.read(&context)?;
+        let mut clients: BTreeMap<String, EvmClient> = BTreeMap::new();
+        let mut tokens: BTreeMap<String, BTreeMap<String, TokenBalance>> = BTreeMap::new();
+
+        for order in SWAP_ORDERS.read(&context)? {
+            let dex = configured_dex(&config, &order.dex)?;
+            let network = dex_network(&config, dex)?;
+            let client = clients
+                .entry(dex.network_id.clone())
+                .or_insert_with(|| EvmClient::for_network(network, self.options));
+
+            for id in [&order.token_in, &order.token_out] {
+                let read = tokens.entry(dex.network_id.clone()).or_default();
+                if is_native(&config, dex, id) || read.contains_key(id) {
+                    continue;
+                }
+                let token = Erc20::new(token_network(&config, dex, id)?.address);
+                read.insert(id.clone(), self.read(client, &token, id, &account)?);
+            }
+        }
+
+        TOKENS.write(&context, &tokens)
+    }
+}
+
+/// How much `ApproveTokens` allows a router when its allowance falls short.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApprovalPolicy {
+    /// What the swaps left to send need, so a router never holds more.
+    #[default]
+    Exact,
+    /// The largest `uint256`, so the next swaps need no approval.
+    Unlimited,
+}
+
+/// Approves the routers of the `quotes` to transfer the tokens their swaps sell,
+/// when the allowance of the `account` falls short.
+///
+/// A swap already in `swaps` needs no allowance anymore, and neither does one
+/// selling a native coin. A non-zero allowance is approved to zero first, as some
+/// tokens refuse to change it otherwise. Approvals are written to `approvals` as
+/// soon as they're signed, then once mined, as the swaps are. It runs after
+/// `CheckBalances`, so nothing is sent without the gas to send it all.
+#[derive(Debug, Clone, Default, PartialEq, StateMetadataReqs)]
+#[state(
+    label = "approve_tokens",
+    tags("approved"),
+    depends_on("quoted", "balance_checked"),
+    strategy = "latest"
+)]
+pub struct ApproveTokens {
+    policy: ApprovalPolicy,
+    options: PoolOptions,
+    tx_options: TxOptions,
+}
+
+impl ApproveTokens {
+    pub fn new(policy: ApprovalPolicy) -> Self {
+        Self::with_options(policy, PoolOptions::default(), TxOptions::default())
+    }
+
+    pub fn with_options(
+        policy: ApprovalPolicy,
+        options: PoolOptions,
+        tx_options: TxOptions,
+    ) -> Self {
+        Self {
+            policy,
+            options,
+            tx_options,
+        }
+    }
+}
+
+// the network, the token and the router of an allowance
+type AllowanceKey = (String, String, Address);
+
+// what the swaps left to send sell of each token, and the token
+fn required_allowances(
+    context: &ContextWrapper,
+    config: &Config,
+) -> Result<BTreeMap<AllowanceKey, (Erc20, u128)>, StateError> {
+    let sent = SWAPS.read_optional(context)?.unwrap_or_default().len();
+    let mut required: BTreeMap<AllowanceKey, (Erc20, u128)> = BTreeMap::new();
+
+    for quote in QUOTES.read(context)?.into_iter().skip(sent) {
+        let order = &quote.order;
+        let dex = configured_dex(config, &order.dex)?;
+        if is_native(config, dex, &order.token_in) {
+            continue;
+        }
+
+        let amount = match order.side {
+            SwapSide::ExactIn => Some(quote.amount_in().0),
+            SwapSide::ExactOut => {
+                let slippage = token_network(config, dex, &order.token_in)?.slippage;
+                max_amount(quote.amount_in().0, slippage)
+            }
+        }
+        .ok_or_else(|| {
+            StateError::ParsingInput(
+                StateErrorRecoverability::Unrecoverable,
+                anyhow!("the slippage of token '{}' is out of range", order.token_in),
+            )
+        })?;
+
+        let key = (
+            dex.network_id.clone(),
+            order.token_in.clone(),
+            router(dex).address(),
+        );
+        let token = Erc20::new(token_network(config, dex, &order.token_in)?.address);
+        let (_, total) = required.entry(key).or_insert((token, 0));
+        *total = total.saturating_add(amount);
+    }
+
+    Ok(required)
+}
+
+/// The gas limits of the approvals the swaps of the `quotes` left to send are
+/// short of, summed by network.
+pub fn estimate_approvals(
+    context: &ContextWrapper,
+    config: &Config,
+    options: PoolOptions,
+) -> Result<BTreeMap<String, u64>, StateError> {
+    let account = ACCOUNT.read(context)?;
+    let mut clients: BTreeMap<String, EvmClient> = BTreeMap::new();
+    let mut estimates: BTreeMap<String, u64> = BTreeMap::new();
+
+    for ((network_id, token_id, spender), (token, required)) in
+        required_allowances(context, config)?
+    {
+        let network = config.networks.get(&network_id).ok_or_else(|| {
+            StateError::ParsingInput(
+                StateErrorRecoverability::Unrecoverable,
+                anyhow!("network '{}' is not defined in the config", network_id),
+            )
+        })?;
+        let client = clients
+            .entry(network_id.clone())
+            .or_insert_with(|| EvmClient::for_network(network, options));
+
+        let what = format!("token '{}'", token_id);
+        let allowance = token
+            .allowance(client, &account, &spender)
+            .map_err(|e| revert_error(&what, e))?;
+        if allowance >= required {
+            continue;
+        }
+
+        // a non-zero allowance is approved to zero first; the approval after it can't be
+        // estimated before, as tokens like USDT revert it until then, and costs as much
+        let (allowance, steps) = match allowance {
+            0 => (Allowance::Exact(required), 1),
+            _ => (Allowance::Exact(0), 2),
+        };
+        let data = Erc20::approve_data(&spender, allowance);
+        let mut call = CallRequest::new(token.address(), data);
+        call.from = Some(account);
+        let estimate = client
+            .estimate_gas(&call)
+            .map_err(|e| revert_error(&format!("the approval of {}", what), e))?;
+
+        let total = estimates.entry(network_id).or_default();
+        *total = total.saturating_add(gas_limit(estimate).saturating_mul(steps));
+    }
+
+    Ok(estimates)
+}
+
+impl ApproveTokens {
+    // waits for an approval, and writes it once mined
+    fn wait(
+        &self,
+        context: &ContextWrapper,
+        client: &EvmClient,
+        account: &Address,
+        approvals: &mut Vec<Approval>,
+        i: usize,
+    ) -> StateResult {
+        let receipt = wait_mined(client, account, &approvals[i].transaction, self.tx_options)?;
+        if let SentTransaction::Signed { .. } = approvals[i].transaction {
+            approvals[i].transaction = SentTransaction::Mined(receipt.clone());
+            APPROVALS.write(context, approvals)?;
+        }
+
+        match receipt.status {
+            true => Ok(()),
+            false => Err(StateError::OnChainError(
+                StateErrorRecoverability::Unrecoverable,
+                anyhow!(
+                    "the approval of token '{}' reverted in transaction {}",
+                    approvals[i].token,
+                    receipt.transaction_hash
+                ),
+            )),
+        }
+    }
+}
+
+impl StateHandler for ApproveTokens {
+    fn handler(&self, context: ContextWrapper) -> StateResult {
+        let config = READ_CONFIG.read(&context)?.config;
+        let account = ACCOUNT.read(&context)?;
+        let mut approvals = APPROVALS.read_optional(&context)?.unwrap_or_default();
+        let mut clients: BTreeMap<String, EvmClient> = BTreeMap::new();
+        let mut signers: BTreeMap<String, Arc<dyn Signer>> = BTreeMap::new();
+
+        for ((network_id, token_id, spender), (token, required)) in
+            required_allowances(&context, &config)?
+        {
+            let network = config.networks.get(&network_id).ok_or_else(|| {
+                StateError::ParsingInput(
+                    StateErrorRecoverability::Unrecoverable,
+                    anyhow!("network '{}' is not defined in the config", network_id),
+                )
+            })?;
+            let client = clients
+                .entry(network_id.clone())
+                .or_insert_with(|| EvmClient::for_network(network, self.options));
+
+            // an approval sent before a recovery is mined first, not sent again
+            let tracked = approvals.iter().rposition(|approval| {
+                approval.network_id == network_id
+                    && approval.token == token_id
+                    && approval.spender == spender
+            });
+            if let Some(i) = tracked {
+                self.wait(&context, client, &account, &mut approvals, i)?;
+            }
+
+            let what = format!("token '{}'", token_id);
+            let allowance = token
+                .allowance(client, &account, &spender)
+                .map_err(|e| revert_error(&what, e))?;
+            if allowance >= required {
+                continue;
+            }
+
+            let approved = match self.policy {
+                ApprovalPolicy::Exact => (Allowance::Exact(required), Some(BaseUnits(required))),
+                ApprovalPolicy::Unlimited => (Allowance::Unlimited, None),
+            };
+            // tokens like USDT only change an allowance from or to zero
+            let mut steps = vec![approved];
+            if allowance > 0 {
+                steps.insert(0, (Allowance::Exact(0), Some(BaseUnits(0))));
+            }
+
+            let signer = match signers.entry(network_id.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    entry.insert(account_signer(&context, &config, &network_id)?)
+                }
+            };
+            for (allowance, amount) in steps {
+                let call =
+                    CallRequest::new(token.address(), Erc20::approve_data(&spender, allowance));
+                let transaction = sign_call(
+                    client,
+                    network,
+                    signer.as_ref(),
+                    call,
+                    &format!("the approval of {}", what),
+                )?;
+
+                approvals.push(Approval {
+                    network_id: network_id.clone(),
+                    token: token_id.clone(),
+                    spender,
+                    amount,
+                    transaction,
+                });
+                APPROVALS.write(&context, &approvals)?;
+                let i = approvals.len() - 1;
+                self.wait(&context, client, &account, &mut approvals, i)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mfm_machine::pipeline;
+    use mfm_machine::pipeline::PipelineError;
+    use serde_json::json;
+
+    use super::*;
+    use crate::contexts::CHECK_BALANCES;
+    use crate::rpc::{abi::MAX_UINT, to_hex};
+    use crate::states::dex::{ExecuteSwaps, QuoteSwaps, DEFAULT_DEADLINE};
+    use crate::states::fixtures::{
+        config_with_cake, context_with_config, estimated_calls, fast_tx_options, quoted,
+        start_router, swap_order as order, token_contract, CAKE, ROUTER,
+    };
+    use crate::states::network::{BalancePolicy, CheckBalances, VerifyChainIds};
+    use crate::states::signers::ResolveSigners;
+
+    const BUSD: &str = "0xe9e7CEA3DedcA5984780Bafc599bD69ADd087D56";
+    const OWNER: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    fn approve_tokens(policy: ApprovalPolicy) -> ApproveTokens {
+        ApproveTokens::with_options(policy, PoolOptions::default(), fast_tx_options())
+    }
+
+    fn approve_data(allowance: Allowance) -> serde_json::Value {
+        json!(to_hex(&Erc20::approve_data(
+            &ROUTER.parse().unwrap(),
+            allowance
+        )))
+    }
+
+    #[test]
+    fn test_read_tokens() {
+        let node = start_router();
+        token_contract(&node, BUSD, "BUSD");
+        token_contract(&node, CAKE, "Cake");
+        let context = quoted(
+            &node,
+            &[
+                order("busd", "bnb", SwapSide::ExactIn, 1_000),
+                order("cake", "busd", SwapSide::ExactOut, 1_000),
+            ],
+        );
+
+        assert!(ReadTokens::new().handler(context.clone()).is_ok());
+        let tokens = TOKENS.read(&context).unwrap();
+        let bsc = tokens.get("bsc").unwrap();
+        assert_eq!(
+            bsc.keys().collect::<Vec<_>>(),
+            vec![&"busd".to_string(), &"cake".to_string()]
+        );
+        assert_eq!(
+            bsc.get("busd").unwrap(),
+            &TokenBalance {
+                address: BUSD.parse().unwrap(),
+                symbol: "BUSD".to_string(),
+                decimals: 18,
+                balance: BaseUnits(5 * 10u128.pow(21)),
+            }
+        );
+        assert_eq!(bsc.get("busd").unwrap().to_string(), "5000 BUSD");
+        assert_eq!(bsc.get("cake").unwrap().symbol, "Cake");
+
+        // no token at the address of wbnb
+        SWAP_ORDERS
+            .write(
+                &context,
+                &vec![order("wbnb", "busd", SwapSide::ExactIn, 1_000)],
+            )
+            .unwrap();
+        assert!(ReadTokens::new().handler(context).is_err());
+    }
+
+    #[test]
+    fn test_approve_tokens() {
+        let node = start_router();
+        let busd = token_contract(&node, BUSD, "BUSD");
+        let cake = token_contract(&node, CAKE, "Cake");
+        *cake.lock().unwrap() = 10_000;
+        let context = quoted(
+            &node,
+            &[
+                order("busd", "wbnb", SwapSide::ExactIn, 1_000),
+                order("busd", "cake", SwapSide::ExactOut, 1_000),
+                order("cake", "busd", SwapSide::ExactIn, 1_000),
+                order("bnb", "busd", SwapSide::ExactIn, 1_000),
+            ],
+        );
+
+        assert!(approve_tokens(ApprovalPolicy::Exact)
+            .handler(context.clone())
+            .is_ok());
+        // the busd the two swaps sell, at most 4000 plus the 0.5% slippage for the
+        // second one; cake is allowed enough already, and bnb needs no approval
+        let approvals = APPROVALS.read(&context).unwrap();
+        assert_eq!(approvals.len(), 1);
+        assert_eq!(approvals[0].token, "busd");
+        assert_eq!(approvals[0].spender, ROUTER.parse().unwrap());
+        assert_eq!(approvals[0].amount, Some(BaseUnits(5_020)));
+        assert!(matches!(
+            approvals[0].transaction,
+            SentTransaction::Mined(_)
+        ));
+        let calls = estimated_calls(&node);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0]["data"], approve_data(Allowance::Exact(5_020)));
+        assert_eq!(
+            calls[0]["to"].as_str().unwrap().parse::<Address>().unwrap(),
+            BUSD.parse().unwrap()
+        );
+        assert_eq!(node.transactions().len(), 1);
+
+        // allowed since, nothing is sent again
+        *busd.lock().unwrap() = 5_020;
+        assert!(approve_tokens(ApprovalPolicy::Exact)
+            .handler(context.clone())
+            .is_ok());
+        assert_eq!(APPROVALS.read(&context).unwrap().len(), 1);
+        assert_eq!(node.transactions().len(), 1);
+
+        *busd.lock().unwrap() = 0;
+        assert!(approve_tokens(ApprovalPolicy::Unlimited)
+            .handler(context.clone())
+            .is_ok());
+        let approvals = APPROVALS.read(&context).unwrap();
+        assert_eq!(approvals.len(), 2);
+        assert_eq!(approvals[1].amount, None);
+        assert_eq!(
+            estimated_calls(&node)[1]["data"],
+            approve_data(Allowance::Unlimited)
+        );
+        assert!(estimated_calls(&node)[1]["data"]
+            .as_str()
+            .unwrap()
+            .ends_with(&to_hex(&MAX_UINT)[2..]));
+    }
+
+    #[test]
+    fn test_approve_tokens_from_non_zero() {
+        let node = start_router();
+        let busd = token_contract(&node, BUSD, "BUSD");
+        *busd.lock().unwrap() = 400;
+        let context = quoted(&node, &[order("busd", "wbnb", SwapSide::ExactIn, 1_000)]);
+
+        assert!(approve_tokens(ApprovalPolicy::Exact)
+            .handler(context.clone())
+            .is_ok());
+        let approvals = APPROVALS.read(&context).unwrap();
+        assert_eq!(
+            approvals.iter().map(|a| a.amount).collect::<Vec<_>>(),
+            vec![Some(BaseUnits(0)), Some(BaseUnits(1_000))]
+        );
+        let calls = estimated_calls(&node);
+        assert_eq!(calls[0]["data"], approve_data(Allowance::Exact(0)));
+        assert_eq!(calls[1]["data"], approve_data(Allowance::Exact(1_000)));
+        assert_eq!(node.transactions().len(), 2);
+    }
+
+    // stands for ReadConfig, the config being in the context already
+    #[derive(Debug, Clone, Default, PartialEq, StateMetadataReqs)]
+    #[state(
+        label = "config_read",
+        tags("setup"),
+        depends_on("setup"),
+        strategy = "latest"
+    )]
+    struct ConfigRead;
+
+    impl StateHandler for ConfigRead {
+        fn handler(&self, _context: ContextWrapper) -> StateResult {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_approve_before_swaps() {
+        let node = start_router();
+        token_contract(&node, BUSD, "BUSD");
+        node.set_balance(OWNER.parse().unwrap(), 10u128.pow(18));
+        let context = context_with_config(config_with_cake(&node));
+        SWAP_ORDERS
+            .write(
+                &context,
+                &vec![order("busd", "bnb", SwapSide::ExactIn, 1_000)],
+            )
+            .unwrap();
+
+        let pipeline = pipeline!("swap" => [
+            ConfigRead,
+            VerifyChainIds::new(),
+            ResolveSigners::new(),
+            QuoteSwaps::new(),
+            ReadTokens::new(),
+            CheckBalances::new(BalancePolicy::Block),
+            approve_tokens(ApprovalPolicy::Exact),
+            ExecuteSwaps::with_options(DEFAULT_DEADLINE, PoolOptions::default(), fast_tx_options()),
+        ])
+        .build()
+        .unwrap();
+        pipeline.state_machine().execute(context.clone()).unwrap();
+
+        assert!(TOKENS.read(&context).unwrap()["bsc"].contains_key("busd"));
+        assert!(CHECK_BALANCES.read(&context).unwrap()["bsc"].sufficient);
+        // estimated by CheckBalances, then the approval is mined before the swap is
+        // signed
+        let calls = estimated_calls(&node);
+        assert_eq!(calls.len(), 4);
+        assert_eq!(calls[2]["data"], approve_data(Allowance::Exact(1_000)));
+        assert_eq!(
+            calls[3]["to"].as_str().unwrap().parse::<Address>().unwrap(),
+            ROUTER.parse().unwrap()
+        );
+        assert!(matches!(
+            SWAPS.read(&context).unwrap()[0].transaction,
+            SentTransaction::Mined(_)
+        ));
+
+        // the swaps don't run without the approvals
+        let unapproved = pipeline!("swap" => [
+            ConfigRead,
+            VerifyChainIds::new(),
+            QuoteSwaps::new(),
+            ExecuteSwaps::new(DEFAULT_DEADLINE),
+        ])
+        .build();
+        match unapproved {
+            Err(PipelineError::UnsatisfiedDependency(label, _)) => {
+                assert_eq!(label.as_str(), "execute_swaps");
+            }
+            result => panic!("expected an unsatisfied dependency, got {:?}", result.err()),
+        }
+
+        // nor the approvals without the balances checked
+        let unchecked = pipeline!("swap" => [
+            ConfigRead,
+            VerifyChainIds::new(),
+            QuoteSwaps::new(),
+            approve_tokens(ApprovalPolicy::Exact),
+        ])
+        .build();
+        match unchecked {
+            Err(PipelineError::UnsatisfiedDependency(label, _)) => {
+                assert_eq!(label.as_str(), "approve_tokens");
+            }
+            result => panic!("expected an unsatisfied dependency, got {:?}", result.err()),
+        }
+    }
+
+    #[test]
+    fn test_approve_tokens_recovery() {
+        let node = start_router();
+        let busd = token_contract(&node, BUSD, "BUSD");
+        node.set_auto_mine(false);
+        let context = quoted(
+            &node,
+            &[
+                order("busd", "wbnb", SwapSide::ExactIn, 1_000),
+                order("busd", "cake", SwapSide::ExactIn, 2_000),
+            ],
+        );
+
+        match approve_tokens(ApprovalPolicy::Exact).handler(context.clone()) {
+            Err(e @ StateError::OnChainError(_, _)) => {
+                assert!(e.is_recoverable());
+                assert!(e.to_string().contains("wasn't mined"));
+            }
+            result => panic!("expected an on chain error, got {:?}", result),
+        }
+        let signed = APPROVALS.read(&context).unwrap()[0].transaction.clone();
+        assert!(matches!(signed, SentTransaction::Signed { .. }));
+
+        // mined while recovering, the signed approval is broadcast again
+        node.mine();
+        *busd.lock().unwrap() = 3_000;
+        assert!(approve_tokens(ApprovalPolicy::Exact)
+            .handler(context.clone())
+            .is_ok());
+        let approvals = APPROVALS.read(&context).unwrap();
+        assert_eq!(approvals.len(), 1);
+        assert_eq!(approvals[0].transaction.hash(), signed.hash());
+        assert!(matches!(
+            approvals[0].transaction,
+            SentTransaction::Mined(_)
+        ));
+        let mut sent = node.transactions();
+        sent.dedup();
+        assert_eq!(sent.len(), 1);
+
+        // the first swap spent its share, the second one is still allowed
+        node.set_auto_mine(true);
+        ExecuteSwaps::with_options(DEFAULT_DEADLINE, PoolOptions::default(), fast_tx_options())
+            .handler(context.clone())
+            .unwrap();
+        *busd.lock().unwrap() = 0;
+        SWAPS
+            .write(&context, &SWAPS.read(&context).unwrap()[..1].to_vec())
+            .unwrap();
+        assert!(approve_tokens(ApprovalPolicy::Exact)
+            .handler(context.clone())
+            .is_ok());
+        let approvals = APPROVALS.read(&context).unwrap();
+        assert_eq!(approvals[1].amount, Some(BaseUnits(2_000)));
+    }
+
+    #[test]
+    fn test_approve_tokens_revert() {
+        let node = start_router();
+        token_contract(&node, BUSD, "BUSD");
+        node.set_revert_transactions(true);
+        let context = quoted(&node, &[order("busd", "wbnb", SwapSide::ExactIn, 1_000)]);
+
+        match approve_tokens(ApprovalPolicy::Exact).handler(context.clone()) {
+            Err(e @ StateError::OnChainError(_, _)) => {
+                assert!(!e.is_recoverable());
+                assert!(e
+                    .to_string()
+                    .contains("the approval of token 'busd' reverted"));
+            }
+            result => panic!("expected an on chain error, got {:?}", result),
+        }
+        // the reverted approval fails a retry too, rather than being sent again
+        assert!(approve_tokens(ApprovalPolicy::Exact)
+            .handler(context)
+            .is_err());
+        assert_eq!(node.transactions().len(), 1);
+    }
+}
@@ -0,0 +1,162 @@
+// helpers shared by the tests of the states
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use mfm_machine::state::{
+    context::{wrap_context, ContextWrapper, Local},
+    StateHandler,
+};
+use serde_json::json;
+
+use super::{dex::QuoteSwaps, signers::ResolveSigners, transactions::TxOptions};
+use crate::config::{amount::BaseUnits, loader::ConfigLoader, Config};
+use crate::contexts::{self, ConfigSource, SwapOrder, SwapSide, READ_CONFIG, SWAP_ORDERS};
+use crate::rpc::abi::{self, Token};
+use crate::rpc::mock::MockNode;
+
+pub const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/config.toml");
+/// The router of the fixture dex.
+pub const ROUTER: &str = "0x10ED43C718714eb63d5aA57B78B54704E256024E";
+pub const CAKE: &str = "0x0E09FaBB73Bd3Ade0a17ECC321fD13a19e81cE82";
+
+/// The fixture config, with the bsc nodes replaced by the mock ones.
+pub fn config_with_nodes(nodes: &[&MockNode]) -> Config {
+    let urls: Vec<String> = nodes.iter().map(|node| node.url()).collect();
+
+    ConfigLoader::new()
+        .env(vec![
+            ("MFM_NETWORKS__BSC__NODE_URL".to_string(), urls[0].clone()),
+            (
+                "MFM_NETWORKS__BSC__NODE_URL_FAILOVER".to_string(),
+                json!(urls[1..]).to_string(),
+            ),
+        ])
+        .load(&ConfigSource::TomlFile(FIXTURE.to_string()))
+        .expect("valid fixture")
+}
+
+pub fn context_with_config(config: Config) -> ContextWrapper {
+    let context = wrap_context(Local::new(HashMap::new()));
+    READ_CONFIG
+        .write(
+            &context,
+            &contexts::ReadConfig {
+                config_source: ConfigSource::TomlFile(FIXTURE.to_string()),
+                config,
+            },
+        )
+        .expect("writable context");
+    context
+}
+
+/// A router where each token is worth twice the one before it in the path.
+pub fn start_router() -> MockNode {
+    let node = MockNode::start(56);
+    node.set_contract(ROUTER.parse().unwrap(), |data| {
+        let amount = abi::decode_uint(&data[4..], 0).unwrap();
+        let hops = abi::decode_uint(&data[4..], 2).unwrap() as u32;
+        let amounts: Vec<u128> = match data[..4] {
+            [0xd0, 0x6c, 0xa6, 0x1f] => (0..hops).map(|i| amount / 2u128.pow(i)).collect(),
+            [0x1f, 0x00, 0xca, 0x74] => (0..hops).rev().map(|i| amount * 2u128.pow(i)).collect(),
+            _ => return Err((-32000, "execution reverted".to_string())),
+        };
+
+        let mut words = vec![Token::Uint(32), Token::Uint(amounts.len() as u128)];
+        words.extend(amounts.into_iter().map(Token::Uint));
+        Ok(abi::encode(&words))
+    });
+    node
+}
+
+/// The fixture config, with a token routed through wbnb.
+pub fn config_with_cake(node: &MockNode) -> Config {
+    let mut config = config_with_nodes(&[node]);
+    let mut tokens = serde_json::to_value(&config.tokens).unwrap();
+    tokens["cake"] = json!({
+        "kind": "ERC20",
+        "networks": {"bsc": {
+            "name": "cake",
+            "network_id": "bsc",
+            "address": CAKE,
+            "slippage": 1,
+            "path_asset": "wbnb",
+        }},
+    });
+    config.tokens = serde_json::from_value(tokens).unwrap();
+    config
+}
+
+pub fn swap_order(token_in: &str, token_out: &str, side: SwapSide, amount: u128) -> SwapOrder {
+    SwapOrder {
+        dex: "pancake_swap_v2".to_string(),
+        token_in: token_in.to_string(),
+        token_out: token_out.to_string(),
+        side,
+        amount: BaseUnits(amount),
+    }
+}
+
+/// The orders quoted, with the signers resolved.
+pub fn quoted(node: &MockNode, orders: &[SwapOrder]) -> ContextWrapper {
+    let context = context_with_config(config_with_cake(node));
+    ResolveSigners::new().handler(context.clone()).unwrap();
+    SWAP_ORDERS.write(&context, &orders.to_vec()).unwrap();
+    QuoteSwaps::new().handler(context.clone()).unwrap();
+    context
+}
+
+/// The calls of the transactions, as estimated before signing them.
+pub fn estimated_calls(node: &MockNode) -> Vec<serde_json::Value> {
+    node.calls()
+        .into_iter()
+        .filter(|(method, _)| method == "eth_estimateGas")
+        .map(|(_, params)| params[0].clone())
+        .collect()
+}
+
+/// Waits for the receipts a short while only.
+pub fn fast_tx_options() -> TxOptions {
+    TxOptions {
+        receipt_timeout: Duration::from_millis(200),
+        poll_interval: Duration::from_millis(10),
+    }
+}
+
+/// An ERC-20 token of 18 decimals holding 5000 of any account, and allowing any
+/// spender the amount held by the returned mutex.
+///
+/// The approvals mined set the allowance, and as USDT's they revert when changing
+/// a non-zero allowance to another one than zero.
+pub fn token_contract(node: &MockNode, address: &str, symbol: &'static str) -> Arc<Mutex<u128>> {
+    let allowance = Arc::new(Mutex::new(0));
+    let approved = allowance.clone();
+    node.set_contract_transactions(address.parse().unwrap(), move |data, mined| {
+        if data[..4] != abi::selector("approve(address,uint256)") {
+            return Ok(());
+        }
+        let amount = abi::decode_uint_saturating(&data[4..], 1).unwrap();
+        let mut allowance = approved.lock().unwrap();
+        if *allowance > 0 && amount > 0 {
+            return Err((3, "execution reverted: non-zero allowance".to_string()));
+        }
+        if mined {
+            *allowance = amount;
+        }
+        Ok(())
+    });
+
+    let allowed = allowance.clone();
+    node.set_contract(address.parse().unwrap(), move |data| {
+        Ok(match data[..4] {
+            [0x70, 0xa0, 0x82, 0x31] => abi::encode(&[Token::Uint(5 * 10u128.pow(21))]),
+            [0xdd, 0x62, 0xed, 0x3e] => abi::encode(&[Token::Uint(*allowed.lock().unwrap())]),
+            [0x31, 0x3c, 0xe5, 0x67] => abi::encode(&[Token::Uint(18)]),
+            [0x95, 0xd8, 0x9b, 0x41] => format!("{:\0<32}", symbol).into_bytes(),
+            _ => return Err((-32000, "execution reverted".to_string())),
+        })
+    });
+    allowance
+}
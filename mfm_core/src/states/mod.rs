@@ -1,47 +1,65 @@
-use mfm_machine_derive::StateMetadataReqs;
+use mfm_machine::StateMetadataReqs;
 
+pub mod dex;
+pub mod erc20;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod network;
+pub mod signers;
+pub mod transactions;
+
+use anyhow::anyhow;
 use mfm_machine::state::{
-    context::ContextWrapper, DependencyStrategy, Label, StateHandler, StateMetadata, StateResult,
-    Tag,
+    context::ContextWrapper, StateError, StateErrorRecoverability, StateHandler, StateResult,
 };
 
-use crate::contexts::{self, READ_CONFIG};
+use crate::config::loader::{self, ConfigError};
+use crate::contexts::{self, CONFIG_SOURCE, READ_CONFIG};
 
-#[derive(Debug, Clone, PartialEq, StateMetadataReqs)]
-pub struct ReadConfig {
-    label: Label,
-    tags: Vec<Tag>,
-    depends_on: Vec<Tag>,
-    depends_on_strategy: DependencyStrategy,
-}
+#[derive(Debug, Clone, Default, PartialEq, StateMetadataReqs)]
+#[state(
+    label = "read_config",
+    tags("setup"),
+    depends_on("config"),
+    strategy = "latest"
+)]
+pub struct ReadConfig;
 
 impl ReadConfig {
     pub fn new() -> Self {
-        Self {
-            label: Label::new("read_config").unwrap(),
-            tags: vec![Tag::new("setup").unwrap()],
-            depends_on: vec![Tag::new("config").unwrap()],
-            depends_on_strategy: DependencyStrategy::Latest,
-        }
-    }
-}
-impl Default for ReadConfig {
-    fn default() -> Self {
-        Self::new()
+        Self
     }
 }
 
 impl StateHandler for ReadConfig {
     fn handler(&self, context: ContextWrapper) -> StateResult {
-        let value = context
-            .lock()
-            .unwrap()
-            .read(READ_CONFIG.to_string())
-            .unwrap();
-
-        let data: contexts::ConfigSource = serde_json::from_value(value).unwrap();
-        println!("data: {:?}", data);
-        Ok(())
+        let config_source = CONFIG_SOURCE.read(&context)?;
+
+        // retrying won't fix a missing file or an invalid config
+        let config = loader::load(&config_source).map_err(|e| match e {
+            ConfigError::Io(_, _) => {
+                StateError::StorageAccess(StateErrorRecoverability::Unrecoverable, anyhow!(e))
+            }
+            ConfigError::Parse(_) => {
+                StateError::ParsingInput(StateErrorRecoverability::Unrecoverable, anyhow!(e))
+            }
+        })?;
+
+        config.validate().map_err(|errors| {
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            StateError::ParsingInput(
+                StateErrorRecoverability::Unrecoverable,
+                anyhow!("invalid config:\n{}", errors.join("\n")),
+            )
+        })?;
+
+        READ_CONFIG.write(
+            &context,
+            &contexts::ReadConfig {
+                config_source,
+                config,
+            },
+        )
     }
 }
 
@@ -51,23 +69,78 @@ mod test {
 
     use mfm_machine::state::{
         context::{wrap_context, Local},
-        StateHandler,
+        StateError, StateHandler,
     };
     use serde_json::json;
 
-    use crate::contexts::{ConfigSource, READ_CONFIG};
+    use crate::contexts::{ConfigFormat, ConfigSource, CONFIG_SOURCE, READ_CONFIG};
 
     use super::ReadConfig;
 
+    const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/config.toml");
+
+    fn context_with_source(source: ConfigSource) -> mfm_machine::state::context::ContextWrapper {
+        wrap_context(Local::new(HashMap::from([(
+            CONFIG_SOURCE.name().to_string(),
+            json!(source),
+        )])))
+    }
+
     #[test]
     fn test_readconfig_from_source_file() {
         let state = ReadConfig::new();
-        let ctx_input = wrap_context(Local::new(HashMap::from([(
-            READ_CONFIG.to_string(),
-            json!(ConfigSource::TomlFile("test_config.toml".to_string())),
-        )])));
-        let result = state.handler(ctx_input);
-        assert!(result.is_ok())
+        let source = ConfigSource::TomlFile(FIXTURE.to_string());
+        let ctx_input = context_with_source(source.clone());
+
+        let result = state.handler(ctx_input.clone());
+        assert!(result.is_ok());
+
+        let read_config = READ_CONFIG.read(&ctx_input).unwrap();
+        assert_eq!(read_config.config_source, source);
+        assert_eq!(read_config.config.networks.get("bsc").unwrap().chain_id, 56);
+    }
+
+    #[test]
+    fn test_readconfig_errors() {
+        let state = ReadConfig::new();
+
+        let missing_source = state.handler(wrap_context(Local::default()));
+        assert!(matches!(
+            missing_source,
+            Err(StateError::StorageAccess(_, _))
+        ));
+
+        let missing_file = state.handler(context_with_source(ConfigSource::TomlFile(
+            "not_found.toml".to_string(),
+        )));
+        assert!(matches!(missing_file, Err(StateError::StorageAccess(_, _))));
+
+        let invalid_file = state.handler(context_with_source(ConfigSource::TomlFile(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml").to_string(),
+        )));
+        match invalid_file {
+            Err(e @ StateError::ParsingInput(_, _)) => {
+                assert!(!e.is_recoverable());
+                assert!(e.to_string().contains("Cargo.toml:1:1"));
+            }
+            result => panic!("expected a parsing error, got {:?}", result),
+        }
+
+        let invalid_config = state.handler(context_with_source(ConfigSource::Inline(
+            ConfigFormat::Toml,
+            std::fs::read_to_string(FIXTURE).unwrap().replace(
+                "network_id = \"bsc\"\n\n[tokens",
+                "network_id = \"eth\"\n\n[tokens",
+            ),
+        )));
+        match invalid_config {
+            Err(e @ StateError::ParsingInput(_, _)) => {
+                assert!(e
+                    .to_string()
+                    .contains("dexes.pancake_swap_v2.network_id: network 'eth' is not defined"));
+            }
+            result => panic!("expected a parsing error, got {:?}", result),
+        }
     }
 
     // TODO: add a test transitioning between states and contexts.
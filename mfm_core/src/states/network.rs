@@ -0,0 +1,492 @@
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use mfm_machine::state::{
+    context::ContextWrapper, StateError, StateErrorRecoverability, StateHandler, StateResult,
+};
+use mfm_machine::StateMetadataReqs;
+
+use super::dex::estimate_swaps;
+use super::erc20::estimate_approvals;
+use crate::config::{address::Address, amount::Amount, network::Network, Config};
+use crate::contexts::{
+    NativeBalance, ACCOUNT, CHECK_BALANCES, GAS_ESTIMATES, NETWORKS, READ_CONFIG, VERIFY_CHAIN_IDS,
+};
+use crate::rpc::client::EvmClient;
+use crate::rpc::pool::{EndpointHealth, EndpointPool, PoolOptions};
+use crate::rpc::types::BlockId;
+
+/// The decimals of a native coin when the network doesn't set them.
+pub const DEFAULT_DECIMALS: u8 = 18;
+
+/// The networks used by the pipeline, sorted by id.
+pub fn active_networks(
+    context: &ContextWrapper,
+    config: &Config,
+) -> Result<Vec<(String, Network)>, StateError> {
+    let mut ids = match NETWORKS.read_optional(context)? {
+        Some(ids) => ids,
+        None => config.networks.hashmap().keys().cloned().collect(),
+    };
+    ids.sort();
+
+    ids.into_iter()
+        .map(|id| match config.networks.get(&id) {
+            Some(network) => Ok((id, network.clone())),
+            None => Err(StateError::ParsingInput(
+                StateErrorRecoverability::Unrecoverable,
+                anyhow!("network '{}' is not defined in the config", id),
+            )),
+        })
+        .collect()
+}
+
+/// Checks that every node of the networks used answers with the configured chain id.
+///
+/// A node on another chain fails unrecoverably, as sending transactions through it
+/// could lose funds; networks with no reachable node are retried. A node unreachable
+/// here has its chain id checked by the pools of the next states before they use it.
+#[derive(Debug, Clone, Default, PartialEq, StateMetadataReqs)]
+#[state(
+    label = "verify_chain_ids",
+    tags("chain_verified"),
+    depends_on("setup"),
+    strategy = "latest"
+)]
+pub struct VerifyChainIds {
+    options: PoolOptions,
+}
+
+impl VerifyChainIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: PoolOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl StateHandler for VerifyChainIds {
+    fn handler(&self, context: ContextWrapper) -> StateResult {
+        let config = READ_CONFIG.read(&context)?.config;
+        let mut checked = BTreeMap::new();
+
+        for (id, network) in active_networks(&context, &config)? {
+            let pool = EndpointPool::for_network(&network, self.options);
+            let statuses = pool.check_health();
+
+            let wrong_chains: Vec<String> = statuses
+                .iter()
+                .filter_map(|status| match status.health {
+                    EndpointHealth::WrongChain { chain_id } => {
+                        Some(format!("{} is on chain {}", status.url, chain_id))
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            if !wrong_chains.is_empty() {
+                return Err(StateError::RpcConnection(
+                    StateErrorRecoverability::Unrecoverable,
+                    anyhow!(
+                        "network '{}' expects chain id {}, but {}",
+                        id,
+                        network.chain_id,
+                        wrong_chains.join(", ")
+                    ),
+                ));
+            }
+
+            let unreachable =
+                |health: &EndpointHealth| matches!(health, EndpointHealth::Unreachable { .. });
+            if statuses.iter().all(|status| unreachable(&status.health)) {
+                return Err(StateError::RpcConnection(
+                    StateErrorRecoverability::Recoverable,
+                    anyhow!(
+                        "no node of network '{}' could be reached:\n{}",
+                        id,
+                        pool.report()
+                    ),
+                ));
+            }
+
+            checked.insert(id, statuses);
+        }
+
+        VERIFY_CHAIN_IDS.write(&context, &checked)
+    }
+}
+
+/// What `CheckBalances` does when a balance is too low.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BalancePolicy {
+    /// Fails, so no transaction is sent.
+    #[default]
+    Block,
+    /// Logs a warning and goes on; the balance is still reported as insufficient.
+    Alert,
+}
+
+/// Fetches the native balance of the account on each network used, and checks it
+/// covers `min_balance_coin` plus the fees of the pending transactions.
+///
+/// The pending transactions are the approvals and swaps of the `quotes` left to
+/// send, whose gas is estimated and written to `gas_estimates`. It runs after
+/// `QuoteSwaps`, and the states sending transactions after it.
+#[derive(Debug, Clone, Default, PartialEq, StateMetadataReqs)]
+#[state(
+    label = "check_balances",
+    tags("balance_checked"),
+    depends_on("quoted"),
+    strategy = "latest"
+)]
+pub struct CheckBalances {
+    policy: BalancePolicy,
+    options: PoolOptions,
+}
+
+impl CheckBalances {
+    pub fn new(policy: BalancePolicy) -> Self {
+        Self {
+            policy,
+            options: PoolOptions::default(),
+        }
+    }
+
+    pub fn with_options(policy: BalancePolicy, options: PoolOptions) -> Self {
+        Self { policy, options }
+    }
+
+    fn check(
+        &self,
+        client: &EvmClient,
+        network: &Network,
+        gas: u64,
+        address: Address,
+    ) -> Result<NativeBalance, StateError> {
+        let decimals = network.decimals.unwrap_or(DEFAULT_DECIMALS);
+        let min_balance = network
+            .min_balance_coin
+            .to_base_units(decimals)
+            .ok_or_else(|| {
+                StateError::ParsingInput(
+                    StateErrorRecoverability::Unrecoverable,
+                    anyhow!(
+                        "min_balance_coin {} doesn't fit {} decimals",
+                        network.min_balance_coin,
+                        decimals
+                    ),
+                )
+            })?;
+
+        let fees = match gas {
+            0 => 0,
+            gas => client.gas_price()?.saturating_mul(gas.into()),
+        };
+        let required = min_balance.saturating_add(fees);
+        let balance = client.get_balance(&address, BlockId::Latest)?;
+
+        let amount = |wei| {
+            Amount::from_base_units(wei, decimals).ok_or_else(|| {
+                StateError::OnChainError(
+                    StateErrorRecoverability::Unrecoverable,
+                    anyhow!("{} wei is out of the supported amounts", wei),
+                )
+            })
+        };
+
+        Ok(NativeBalance {
+            address,
+            balance: amount(balance)?,
+            required: amount(required)?,
+            sufficient: balance >= required,
+        })
+    }
+}
+
+impl StateHandler for CheckBalances {
+    fn handler(&self, context: ContextWrapper) -> StateResult {
+        let config = READ_CONFIG.read(&context)?.config;
+        let address = ACCOUNT.read(&context)?;
+        let mut gas_estimates = estimate_approvals(&context, &config, self.options)?;
+        for (id, gas) in estimate_swaps(&context, &config, self.options)? {
+            let total = gas_estimates.entry(id).or_default();
+            *total = total.saturating_add(gas);
+        }
+        GAS_ESTIMATES.write(&context, &gas_estimates)?;
+
+        let mut balances = BTreeMap::new();
+        let mut insufficient = Vec::new();
+
+        for (id, network) in active_networks(&context, &config)? {
+            let client = EvmClient::for_network(&network, self.options);
+            let gas = gas_estimates.get(&id).copied().unwrap_or_default();
+            let balance = self.check(&client, &network, gas, address)?;
+
+            if !balance.sufficient {
+                insufficient.push(format!(
+                    "{} {} on network '{}' is below the required {} {}",
+                    balance.balance, network.symbol, id, balance.required, network.symbol
+                ));
+            }
+            balances.insert(id, balance);
+        }
+
+        CHECK_BALANCES.write(&context, &balances)?;
+
+        match (insufficient.is_empty(), self.policy) {
+            (true, _) => Ok(()),
+            (false, BalancePolicy::Alert) => {
+                for message in insufficient {
+                    tracing::warn!(account = %address, "{}", message);
+                }
+                Ok(())
+            }
+            // topping up the account takes longer than a retry
+            (false, BalancePolicy::Block) => Err(StateError::OnChainError(
+                StateErrorRecoverability::Unrecoverable,
+                anyhow!(
+                    "insufficient balance of {}: {}",
+                    address,
+                    insufficient.join("; ")
+                ),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::contexts::{SwapSide, QUOTES};
+    use crate::rpc::mock::MockNode;
+    use crate::states::dex::SWAP_GAS_PER_HOP;
+    use crate::states::fixtures::{
+        config_with_nodes, context_with_config, estimated_calls, quoted, start_router, swap_order,
+        token_contract,
+    };
+    use serde_json::json;
+
+    const BUSD: &str = "0xe9e7CEA3DedcA5984780Bafc599bD69ADd087D56";
+    use mfm_machine::state::{StateError, StateHandler};
+
+    #[test]
+    fn test_verify_chain_ids() {
+        let primary = MockNode::start(56);
+        let failover = MockNode::start(56);
+        let context = context_with_config(config_with_nodes(&[&primary, &failover]));
+
+        assert!(VerifyChainIds::new().handler(context.clone()).is_ok());
+
+        let checked = VERIFY_CHAIN_IDS.read(&context).unwrap();
+        let bsc = checked.get("bsc").unwrap();
+        assert_eq!(bsc.len(), 2);
+        assert!(bsc
+            .iter()
+            .all(|status| status.health == EndpointHealth::Healthy));
+    }
+
+    #[test]
+    fn test_verify_chain_ids_mismatch() {
+        let primary = MockNode::start(56);
+        let failover = MockNode::start(97);
+        let context = context_with_config(config_with_nodes(&[&primary, &failover]));
+
+        match VerifyChainIds::new().handler(context.clone()) {
+            Err(e @ StateError::RpcConnection(_, _)) => {
+                assert!(!e.is_recoverable());
+                assert!(e.to_string().contains(&format!(
+                    "network 'bsc' expects chain id 56, but {} is on chain 97",
+                    failover.url()
+                )));
+            }
+            result => panic!("expected a chain id mismatch, got {:?}", result),
+        }
+        assert!(VERIFY_CHAIN_IDS.read_optional(&context).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_verify_chain_ids_unreachable() {
+        let unreachable = MockNode::start(56);
+        let config = config_with_nodes(&[&unreachable]);
+        drop(unreachable);
+        let context = context_with_config(config);
+
+        let result = VerifyChainIds::new().handler(context);
+        assert!(matches!(result, Err(StateError::RpcConnection(_, _))));
+        assert!(result.unwrap_err().is_recoverable());
+    }
+
+    const ACCOUNT_ADDRESS: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    fn context_with_account(node: &MockNode) -> ContextWrapper {
+        let context = context_with_config(config_with_nodes(&[node]));
+        ACCOUNT
+            .write(&context, &ACCOUNT_ADDRESS.parse().unwrap())
+            .unwrap();
+        QUOTES.write(&context, &vec![]).unwrap();
+        context
+    }
+
+    #[test]
+    fn test_check_balances() {
+        let node = MockNode::start(56);
+        node.set_gas_price(5_000_000_000);
+        // min_balance_coin is 0.2 bnb in the fixture
+        node.set_balance(ACCOUNT_ADDRESS.parse().unwrap(), 250_000_000_000_000_000);
+        let context = context_with_account(&node);
+
+        assert!(CheckBalances::new(BalancePolicy::Block)
+            .handler(context.clone())
+            .is_ok());
+        let bsc = CHECK_BALANCES.read(&context).unwrap()["bsc"].clone();
+        assert_eq!(bsc.balance.to_string(), "0.25");
+        assert_eq!(bsc.required.to_string(), "0.2");
+        assert!(bsc.sufficient);
+        // without pending gas, no gas price is needed
+        assert_eq!(node.calls_to("eth_gasPrice"), 0);
+        assert!(GAS_ESTIMATES.read(&context).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_balances_pending_gas() {
+        let node = start_router();
+        let busd = token_contract(&node, BUSD, "BUSD");
+        node.set_gas_price(5_000_000_000);
+        node.set_estimate_gas(100_000);
+        node.set_balance(ACCOUNT_ADDRESS.parse().unwrap(), 250_000_000_000_000_000);
+        let context = quoted(
+            &node,
+            &[
+                swap_order("busd", "wbnb", SwapSide::ExactIn, 1_000),
+                swap_order("bnb", "busd", SwapSide::ExactIn, 1_000),
+            ],
+        );
+
+        // the approval of busd and the two swaps, each estimated plus a fifth
+        assert!(CheckBalances::new(BalancePolicy::Block)
+            .handler(context.clone())
+            .is_ok());
+        assert_eq!(
+            GAS_ESTIMATES.read(&context).unwrap(),
+            BTreeMap::from([("bsc".to_string(), 360_000)])
+        );
+        let bsc = CHECK_BALANCES.read(&context).unwrap()["bsc"].clone();
+        assert_eq!(bsc.required.to_string(), "0.2018");
+
+        // 0.2 bnb plus 3 times 12M gas at 5 gwei
+        node.set_estimate_gas(10_000_000);
+        match CheckBalances::new(BalancePolicy::Block).handler(context.clone()) {
+            Err(e @ StateError::OnChainError(_, _)) => {
+                assert!(!e.is_recoverable());
+                assert!(e
+                    .to_string()
+                    .contains("0.25 bnb on network 'bsc' is below the required 0.38 bnb"));
+            }
+            result => panic!("expected an insufficient balance, got {:?}", result),
+        }
+
+        // approved already, and swaps reverting until they are
+        *busd.lock().unwrap() = 1_000;
+        node.handle("eth_estimateGas", |params| {
+            match params[0]["data"]
+                .as_str()
+                .unwrap()
+                .starts_with("0x095ea7b3")
+            {
+                true => Ok(json!("0x186a0")),
+                false => Err((3, "execution reverted: TRANSFER_FROM_FAILED".to_string())),
+            }
+        });
+        assert!(CheckBalances::new(BalancePolicy::Block)
+            .handler(context.clone())
+            .is_ok());
+        assert_eq!(
+            GAS_ESTIMATES.read(&context).unwrap(),
+            BTreeMap::from([("bsc".to_string(), 2 * SWAP_GAS_PER_HOP)])
+        );
+    }
+
+    #[test]
+    fn test_check_balances_non_zero_allowance() {
+        let node = start_router();
+        let busd = token_contract(&node, BUSD, "BUSD");
+        *busd.lock().unwrap() = 400;
+        node.set_gas_price(5_000_000_000);
+        node.set_estimate_gas(100_000);
+        node.set_balance(ACCOUNT_ADDRESS.parse().unwrap(), 250_000_000_000_000_000);
+        let context = quoted(
+            &node,
+            &[swap_order("busd", "wbnb", SwapSide::ExactIn, 1_000)],
+        );
+
+        // the approval of zero twice, as the one of 1000 reverts until it's mined,
+        // and the swap
+        assert!(CheckBalances::new(BalancePolicy::Block)
+            .handler(context.clone())
+            .is_ok());
+        assert_eq!(
+            GAS_ESTIMATES.read(&context).unwrap(),
+            BTreeMap::from([("bsc".to_string(), 360_000)])
+        );
+        let approvals: Vec<_> = estimated_calls(&node)
+            .into_iter()
+            .filter(|call| call["to"].as_str().unwrap().eq_ignore_ascii_case(BUSD))
+            .collect();
+        assert_eq!(approvals.len(), 1);
+        assert!(approvals[0]["data"]
+            .as_str()
+            .unwrap()
+            .ends_with(&"0".repeat(64)));
+    }
+
+    #[test]
+    fn test_check_balances_alert() {
+        let node = MockNode::start(56);
+        let context = context_with_account(&node);
+
+        assert!(CheckBalances::new(BalancePolicy::Alert)
+            .handler(context.clone())
+            .is_ok());
+        let bsc = CHECK_BALANCES.read(&context).unwrap()["bsc"].clone();
+        assert_eq!(bsc.balance, Amount::ZERO);
+        assert!(!bsc.sufficient);
+    }
+
+    #[test]
+    fn test_check_balances_errors() {
+        let node = MockNode::start(56);
+        let context = context_with_config(config_with_nodes(&[&node]));
+        assert!(matches!(
+            CheckBalances::new(BalancePolicy::Block).handler(context.clone()),
+            Err(StateError::StorageAccess(_, _))
+        ));
+
+        let context = context_with_account(&node);
+        drop(node);
+        let result = CheckBalances::new(BalancePolicy::Block).handler(context);
+        assert!(matches!(result, Err(StateError::RpcConnection(_, _))));
+        assert!(result.unwrap_err().is_recoverable());
+    }
+
+    #[test]
+    fn test_active_networks() {
+        let node = MockNode::start(56);
+        let context = context_with_config(config_with_nodes(&[&node]));
+        let config = READ_CONFIG.read(&context).unwrap().config;
+
+        let ids: Vec<_> = active_networks(&context, &config)
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, ["bsc"]);
+
+        NETWORKS.write(&context, &vec!["eth".to_string()]).unwrap();
+        assert!(matches!(
+            active_networks(&context, &config),
+            Err(StateError::ParsingInput(_, _))
+        ));
+    }
+}
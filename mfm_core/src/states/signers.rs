@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+use anyhow::anyhow;
+use mfm_machine::state::{
+    context::ContextWrapper, StateError, StateErrorRecoverability, StateHandler, StateResult,
+};
+use mfm_machine::StateMetadataReqs;
+
+use super::network::active_networks;
+use crate::config::{authentication::AuthMethod, Config};
+use crate::contexts::{Account, ACCOUNT, AUTH_METHODS, READ_CONFIG, SIGNERS};
+use crate::signer::Signer;
+
+/// The auth methods used by the pipeline, in the config order.
+pub fn active_auth_methods(
+    context: &ContextWrapper,
+    config: &Config,
+) -> Result<Vec<AuthMethod>, StateError> {
+    let names = match AUTH_METHODS.read_optional(context)? {
+        Some(names) => names,
+        None => return Ok(config.auth_methods.list().to_vec()),
+    };
+
+    if let Some(missing) = names
+        .iter()
+        .find(|name| config.auth_methods.get(name).is_none())
+    {
+        return Err(StateError::ParsingInput(
+            StateErrorRecoverability::Unrecoverable,
+            anyhow!("auth method '{}' is not defined in the config", missing),
+        ));
+    }
+
+    Ok(config
+        .auth_methods
+        .list()
+        .iter()
+        .filter(|auth_method| names.contains(&auth_method.name))
+        .cloned()
+        .collect())
+}
+
+// the signers unlocked during the run, by the auth method they come from
+type Unlocked = Vec<(AuthMethod, Vec<Arc<dyn Signer>>)>;
+
+fn unlocked() -> &'static Mutex<Unlocked> {
+    static UNLOCKED: OnceLock<Mutex<Unlocked>> = OnceLock::new();
+    UNLOCKED.get_or_init(Default::default)
+}
+
+/// Unlocks the signers of an auth method once for the run, so the key derivation
+/// and the password prompts don't run again for each transaction.
+pub fn unlock(auth_method: &AuthMethod) -> Result<Vec<Arc<dyn Signer>>, StateError> {
+    // held while unlocking, so concurrent states don't prompt twice
+    let mut unlocked = unlocked().lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((_, signers)) = unlocked.iter().find(|(method, _)| method == auth_method) {
+        return Ok(signers.clone());
+    }
+
+    let signers: Vec<Arc<dyn Signer>> = auth_method
+        .method
+        .signers()?
+        .into_iter()
+        .map(Arc::from)
+        .collect();
+    unlocked.push((auth_method.clone(), signers.clone()));
+    Ok(signers)
+}
+
+/// The signer of a resolved account, for the states sending transactions.
+pub fn signer_for(config: &Config, account: &Account) -> Result<Arc<dyn Signer>, StateError> {
+    let auth_method = config
+        .auth_methods
+        .get(&account.auth_method)
+        .ok_or_else(|| {
+            StateError::ParsingInput(
+                StateErrorRecoverability::Unrecoverable,
+                anyhow!(
+                    "auth method '{}' is not defined in the config",
+                    account.auth_method
+                ),
+            )
+        })?;
+
+    unlock(auth_method)?
+        .into_iter()
+        .find(|signer| signer.address() == account.address)
+        .ok_or_else(|| {
+            StateError::ParsingInput(
+                StateErrorRecoverability::Unrecoverable,
+                anyhow!(
+                    "auth method '{}' has no account {}",
+                    account.auth_method,
+                    account.address
+                ),
+            )
+        })
+}
+
+/// The signer of the `account` on a network, among the resolved ones.
+pub fn account_signer(
+    context: &ContextWrapper,
+    config: &Config,
+    network_id: &str,
+) -> Result<Arc<dyn Signer>, StateError> {
+    let address = ACCOUNT.read(context)?;
+    let account = SIGNERS
+        .read(context)?
+        .remove(network_id)
+        .and_then(|accounts| accounts.into_iter().find(|a| a.address == address))
+        .ok_or_else(|| {
+            StateError::ParsingInput(
+                StateErrorRecoverability::Unrecoverable,
+                anyhow!("account {} can't sign on network '{}'", address, network_id),
+            )
+        })?;
+
+    signer_for(config, &account)
+}
+
+/// Unlocks the auth methods used by the pipeline, and writes the accounts each
+/// network can sign with; `account` defaults to the first of them.
+///
+/// Every network used needs at least one account, so a pipeline doesn't find out
+/// midway that it can't sign.
+#[derive(Debug, Clone, Default, PartialEq, StateMetadataReqs)]
+#[state(
+    label = "resolve_signers",
+    tags("signers_resolved"),
+    depends_on("setup"),
+    strategy = "latest"
+)]
+pub struct ResolveSigners;
+
+impl ResolveSigners {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl StateHandler for ResolveSigners {
+    fn handler(&self, context: ContextWrapper) -> StateResult {
+        let config = READ_CONFIG.read(&context)?.config;
+        let networks = active_networks(&context, &config)?;
+
+        // each method is unlocked once, and only when a network uses it
+        let mut accounts: BTreeMap<String, Vec<Account>> = BTreeMap::new();
+        for auth_method in active_auth_methods(&context, &config)? {
+            let used_by: Vec<&String> = networks
+                .iter()
+                .map(|(id, _)| id)
+                .filter(|id| auth_method.allows(id))
+                .collect();
+            if used_by.is_empty() {
+                continue;
+            }
+
+            let signers = unlock(&auth_method)?;
+            for id in used_by {
+                accounts
+                    .entry(id.clone())
+                    .or_default()
+                    .extend(signers.iter().map(|signer| Account {
+                        auth_method: auth_method.name.clone(),
+                        address: signer.address(),
+                    }));
+            }
+        }
+
+        if let Some((id, _)) = networks.iter().find(|(id, _)| !accounts.contains_key(id)) {
+            return Err(StateError::ParsingInput(
+                StateErrorRecoverability::Unrecoverable,
+                anyhow!("no auth method signs on network '{}'", id),
+            ));
+        }
+
+        SIGNERS.write(&context, &accounts)?;
+
+        match (ACCOUNT.read_optional(&context)?, accounts.values().next()) {
+            (None, Some(network_accounts)) => ACCOUNT.write(&context, &network_accounts[0].address),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::contexts::NETWORKS;
+    use crate::rpc::mock::MockNode;
+    use crate::states::fixtures::{config_with_nodes, context_with_config};
+
+    const FIRST: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const SECOND: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+    fn account(auth_method: &str, address: &str) -> Account {
+        Account {
+            auth_method: auth_method.to_string(),
+            address: address.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_signers() {
+        let node = MockNode::start(56);
+        let context = context_with_config(config_with_nodes(&[&node]));
+
+        assert!(ResolveSigners::new().handler(context.clone()).is_ok());
+        assert_eq!(
+            SIGNERS.read(&context).unwrap(),
+            BTreeMap::from([(
+                "bsc".to_string(),
+                vec![
+                    account("deployer", FIRST),
+                    account("hardhat", FIRST),
+                    account("hardhat", SECOND)
+                ]
+            )])
+        );
+        assert_eq!(ACCOUNT.read(&context).unwrap(), FIRST.parse().unwrap());
+
+        // the selected methods only, leaving the account already set
+        let context = context_with_config(config_with_nodes(&[&node]));
+        AUTH_METHODS
+            .write(&context, &vec!["hardhat".to_string()])
+            .unwrap();
+        ACCOUNT.write(&context, &SECOND.parse().unwrap()).unwrap();
+
+        assert!(ResolveSigners::new().handler(context.clone()).is_ok());
+        assert_eq!(SIGNERS.read(&context).unwrap()["bsc"].len(), 2);
+        assert_eq!(ACCOUNT.read(&context).unwrap(), SECOND.parse().unwrap());
+
+        let config = READ_CONFIG.read(&context).unwrap().config;
+        let signer = signer_for(&config, &account("hardhat", SECOND)).unwrap();
+        assert_eq!(signer.address(), SECOND.parse().unwrap());
+        assert!(signer_for(&config, &account("deployer", SECOND)).is_err());
+
+        let signer = account_signer(&context, &config, "bsc").unwrap();
+        assert_eq!(signer.address(), SECOND.parse().unwrap());
+        assert!(account_signer(&context, &config, "eth").is_err());
+
+        // unlocked once for the run
+        assert!(Arc::ptr_eq(
+            &signer,
+            &signer_for(&config, &account("hardhat", SECOND)).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_resolve_signers_errors() {
+        let node = MockNode::start(56);
+        let context = context_with_config(config_with_nodes(&[&node]));
+        AUTH_METHODS
+            .write(&context, &vec!["ledger".to_string()])
+            .unwrap();
+        match ResolveSigners::new().handler(context) {
+            Err(e @ StateError::ParsingInput(_, _)) => {
+                assert!(e
+                    .to_string()
+                    .contains("auth method 'ledger' is not defined in the config"));
+            }
+            result => panic!("expected a parsing error, got {:?}", result),
+        }
+
+        // the only method selected is restricted to another network
+        let mut config = config_with_nodes(&[&node]);
+        config.auth_methods = serde_json::from_value(json!([{
+            "name": "hardhat",
+            "networks": ["eth"],
+            "type": "wallet",
+            "private_key": "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            "not_encrypted": true,
+        }]))
+        .unwrap();
+        let context = context_with_config(config);
+        NETWORKS.write(&context, &vec!["bsc".to_string()]).unwrap();
+
+        match ResolveSigners::new().handler(context.clone()) {
+            Err(e @ StateError::ParsingInput(_, _)) => {
+                assert!(!e.is_recoverable());
+                assert!(e
+                    .to_string()
+                    .contains("no auth method signs on network 'bsc'"));
+            }
+            result => panic!("expected a parsing error, got {:?}", result),
+        }
+        assert!(SIGNERS.read_optional(&context).unwrap().is_none());
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use mfm_machine::state::{StateError, StateErrorRecoverability};
+
+use crate::config::{address::Address, network::Network};
+use crate::contexts::SentTransaction;
+use crate::rpc::{
+    client::EvmClient,
+    from_hex, to_hex,
+    types::{BlockId, CallRequest, TransactionReceipt},
+    RpcError,
+};
+use crate::signer::{Fees, Signer, Transaction};
+
+/// How the states sending transactions wait for them to be mined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxOptions {
+    /// How long to wait for a receipt before failing, recoverably, to wait again
+    /// after the recovery.
+    pub receipt_timeout: Duration,
+    pub poll_interval: Duration,
+}
+
+impl Default for TxOptions {
+    fn default() -> Self {
+        Self {
+            receipt_timeout: Duration::from_secs(180),
+            poll_interval: Duration::from_secs(3),
+        }
+    }
+}
+
+// a revert is the same on every node
+pub(crate) fn revert_error(what: &str, e: RpcError) -> StateError {
+    match e {
+        RpcError::Rpc(_, message) if message.contains("revert") => StateError::OnChainError(
+            StateErrorRecoverability::Unrecoverable,
+            anyhow!("{} reverted: {}", what, message),
+        ),
+        e => e.into(),
+    }
+}
+
+/// The gas limit of a transaction: its estimate plus a fifth.
+pub fn gas_limit(estimate: u128) -> u64 {
+    u64::try_from(estimate.saturating_mul(6) / 5).unwrap_or(u64::MAX)
+}
+
+/// Signs `call` as a transaction of `signer`, paying the gas price of the node
+/// for the gas estimate plus a fifth.
+///
+/// `what` names the call in the errors, e.g. when the estimate reverts.
+pub fn sign_call(
+    client: &EvmClient,
+    network: &Network,
+    signer: &dyn Signer,
+    mut call: CallRequest,
+    what: &str,
+) -> Result<SentTransaction, StateError> {
+    call.from = Some(signer.address());
+    let gas = client
+        .estimate_gas(&call)
+        .map_err(|e| revert_error(what, e))?;
+    let nonce = client.get_transaction_count(&signer.address(), BlockId::Pending)?;
+    let gas_price = client.gas_price()?;
+
+    let tx = Transaction {
+        nonce,
+        gas: gas_limit(gas),
+        to: Some(call.to),
+        value: call.value.unwrap_or_default(),
+        data: call.data,
+        ..Transaction::for_network(network, Fees::Legacy { gas_price })
+    };
+    let signed = signer.sign_transaction(&tx)?;
+
+    Ok(SentTransaction::Signed {
+        hash: signed.hash,
+        nonce,
+        raw: to_hex(&signed.raw),
+    })
+}
+
+// a node refusing a transaction it has, or one it may have mined
+fn is_broadcast(message: &str) -> bool {
+    [
+        "already known",
+        "nonce too low",
+        "replacement transaction underpriced",
+    ]
+    .iter()
+    .any(|refusal| message.contains(refusal))
+}
+
+/// Broadcasts a signed transaction of `from`, again if it already was, and waits
+/// for its receipt; a mined one answers its receipt right away.
+///
+/// One not mined in time fails recoverably, to be waited for again after a
+/// recovery, unless another transaction took its nonce: it can't be mined anymore.
+pub fn wait_mined(
+    client: &EvmClient,
+    from: &Address,
+    transaction: &SentTransaction,
+    options: TxOptions,
+) -> Result<TransactionReceipt, StateError> {
+    let (hash, nonce, raw) = match transaction {
+        SentTransaction::Mined(receipt) => return Ok(receipt.clone()),
+        SentTransaction::Signed { hash, nonce, raw } => (hash, *nonce, raw),
+    };
+    let raw = from_hex(raw).map_err(|e| {
+        StateError::ParsingInput(StateErrorRecoverability::Unrecoverable, anyhow!(e))
+    })?;
+
+    // once broadcast, the nodes refuse it as known, or as reusing a nonce once mined;
+    // after a failover the next node may see it mined before it has the receipt, so
+    // it's polled for rather than failing
+    if let Err(e) = client.send_raw_transaction(&raw) {
+        if let Some(receipt) = client.get_transaction_receipt(hash)? {
+            return Ok(receipt);
+        }
+        match e {
+            RpcError::Rpc(_, message) if is_broadcast(&message) => {}
+            e => return Err(e.into()),
+        }
+    }
+
+    let started = Instant::now();
+    loop {
+        if let Some(receipt) = client.get_transaction_receipt(hash)? {
+            return Ok(receipt);
+        }
+        if started.elapsed() >= options.receipt_timeout {
+            // the nonce is used, but not by this transaction as it has no receipt
+            if client.get_transaction_count(from, BlockId::Latest)? > nonce {
+                return Err(StateError::OnChainError(
+                    StateErrorRecoverability::Unrecoverable,
+                    anyhow!(
+                        "another transaction took the nonce {} of {}, so transaction {} \
+                         can't be mined",
+                        nonce,
+                        from,
+                        hash
+                    ),
+                ));
+            }
+            return Err(StateError::OnChainError(
+                StateErrorRecoverability::Recoverable,
+                anyhow!(
+                    "transaction {} wasn't mined within {:?}",
+                    hash,
+                    options.receipt_timeout
+                ),
+            ));
+        }
+        thread::sleep(options.poll_interval);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::authentication::wallet::PrivateKey;
+    use crate::rpc::{mock::MockNode, pool::PoolOptions};
+    use crate::signer::local::LocalSigner;
+    use crate::states::fixtures::{config_with_nodes, fast_tx_options};
+
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ROUTER: &str = "0x10ED43C718714eb63d5aA57B78B54704E256024E";
+
+    fn from() -> Address {
+        "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+            .parse()
+            .unwrap()
+    }
+
+    fn signed(node: &MockNode) -> (EvmClient, SentTransaction) {
+        let network = config_with_nodes(&[node])
+            .networks
+            .get("bsc")
+            .unwrap()
+            .clone();
+        let client = EvmClient::for_network(&network, PoolOptions::default());
+        let signer = LocalSigner::new(PrivateKey::from_hex(KEY.as_bytes()).unwrap());
+        let call = CallRequest::new(ROUTER.parse().unwrap(), vec![1, 2, 3]);
+
+        let transaction = sign_call(&client, &network, &signer, call, "the call").unwrap();
+        (client, transaction)
+    }
+
+    #[test]
+    fn test_sign_and_wait() {
+        let node = MockNode::start(56);
+        node.set_estimate_gas(100_000);
+        node.set_gas_price(5_000_000_000);
+        node.set_nonce(from(), 7);
+
+        let (client, transaction) = signed(&node);
+        assert!(matches!(
+            transaction,
+            SentTransaction::Signed { nonce: 7, .. }
+        ));
+        assert!(node.transactions().is_empty());
+
+        let receipt = wait_mined(&client, &from(), &transaction, fast_tx_options()).unwrap();
+        assert_eq!(receipt.transaction_hash, transaction.hash());
+        assert!(receipt.status);
+        assert_eq!(node.transactions().len(), 1);
+
+        // the estimate plus a fifth, at the gas price of the node
+        let expected = LocalSigner::new(PrivateKey::from_hex(KEY.as_bytes()).unwrap())
+            .sign_transaction(&Transaction {
+                chain_id: 56,
+                nonce: 7,
+                fees: Fees::Legacy {
+                    gas_price: 5_000_000_000,
+                },
+                gas: 120_000,
+                to: Some(ROUTER.parse().unwrap()),
+                value: 0,
+                data: vec![1, 2, 3],
+            })
+            .unwrap();
+        assert_eq!(node.transactions(), vec![expected.raw.clone()]);
+        assert_eq!(
+            transaction,
+            SentTransaction::Signed {
+                hash: expected.hash,
+                nonce: 7,
+                raw: to_hex(&expected.raw),
+            }
+        );
+
+        // mined, nothing is broadcast again
+        let mined = SentTransaction::Mined(receipt.clone());
+        assert_eq!(
+            wait_mined(&client, &from(), &mined, fast_tx_options()).unwrap(),
+            receipt
+        );
+        // a node refusing it as mined already answers its receipt
+        node.handle("eth_sendRawTransaction", |_| {
+            Err((-32000, "nonce too low".to_string()))
+        });
+        assert_eq!(
+            wait_mined(&client, &from(), &transaction, fast_tx_options()).unwrap(),
+            receipt
+        );
+        assert_eq!(node.transactions().len(), 1);
+    }
+
+    #[test]
+    fn test_wait_errors() {
+        let node = MockNode::start(56);
+        node.set_auto_mine(false);
+        let (client, transaction) = signed(&node);
+
+        match wait_mined(&client, &from(), &transaction, fast_tx_options()) {
+            Err(e @ StateError::OnChainError(_, _)) => {
+                assert!(e.is_recoverable());
+                assert!(e.to_string().contains("wasn't mined within 200ms"));
+            }
+            result => panic!("expected an on chain error, got {:?}", result),
+        }
+
+        // broadcast again after the recovery, the node knows it
+        node.handle("eth_sendRawTransaction", |_| {
+            Err((-32000, "already known".to_string()))
+        });
+        node.mine();
+        assert!(
+            wait_mined(&client, &from(), &transaction, fast_tx_options())
+                .unwrap()
+                .status
+        );
+
+        // refused as mined by a node without the receipt yet, polled for again
+        node.set_auto_mine(false);
+        node.set_nonce(from(), 5);
+        let (_, pending) = signed(&node);
+        for refusal in ["nonce too low", "replacement transaction underpriced"] {
+            node.handle("eth_sendRawTransaction", move |_| {
+                Err((-32000, refusal.to_string()))
+            });
+            match wait_mined(&client, &from(), &pending, fast_tx_options()) {
+                Err(e @ StateError::OnChainError(_, _)) => {
+                    assert!(e.is_recoverable());
+                    assert!(e.to_string().contains("wasn't mined within"));
+                }
+                result => panic!("expected an on chain error, got {:?}", result),
+            }
+        }
+
+        // another transaction mined with its nonce, it never will be
+        node.set_nonce(from(), 6);
+        match wait_mined(&client, &from(), &pending, fast_tx_options()) {
+            Err(e @ StateError::OnChainError(_, _)) => {
+                assert!(!e.is_recoverable());
+                assert!(e
+                    .to_string()
+                    .contains("another transaction took the nonce 5 of 0xf39F"));
+            }
+            result => panic!("expected an on chain error, got {:?}", result),
+        }
+        node.set_auto_mine(true);
+
+        // refused for good
+        node.handle("eth_sendRawTransaction", |_| {
+            Err((
+                -32000,
+                "insufficient funds for gas * price + value".to_string(),
+            ))
+        });
+        node.set_nonce(from(), 1);
+        let (client, transaction) = signed(&node);
+        match wait_mined(&client, &from(), &transaction, fast_tx_options()) {
+            Err(e @ StateError::RpcConnection(_, _)) => {
+                assert!(!e.is_recoverable());
+                assert!(e.to_string().contains("insufficient funds"));
+            }
+            result => panic!("expected a connection error, got {:?}", result),
+        }
+
+        node.handle("eth_estimateGas", |_| {
+            Err((3, "execution reverted: TRANSFER_FROM_FAILED".to_string()))
+        });
+        let network = config_with_nodes(&[&node])
+            .networks
+            .get("bsc")
+            .unwrap()
+            .clone();
+        let client = EvmClient::for_network(&network, PoolOptions::default());
+        let signer = LocalSigner::new(PrivateKey::from_hex(KEY.as_bytes()).unwrap());
+        let call = CallRequest::new(signer.address(), vec![]);
+        match sign_call(&client, &network, &signer, call, "the swap") {
+            Err(e @ StateError::OnChainError(_, _)) => {
+                assert!(!e.is_recoverable());
+                assert!(e
+                    .to_string()
+                    .contains("the swap reverted: execution reverted: TRANSFER_FROM_FAILED"));
+            }
+            result => panic!("expected an on chain error, got {:?}", result),
+        }
+    }
+}
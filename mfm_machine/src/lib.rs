@@ -1,6 +1,12 @@
+pub mod pipeline;
 pub mod state;
 pub mod state_machine;
-extern crate mfm_machine_derive;
+
+#[cfg(feature = "derive")]
+pub use mfm_machine_derive::{state_handler, StateMetadataReqs};
+
+// lets the derive macros use `::mfm_machine::...` paths inside this crate too
+extern crate self as mfm_machine;
 
 //FIXME: reorganize library to be more ergonomic to use
 // see example in tests/public_api_test.rs how bad its.
@@ -0,0 +1,160 @@
+use std::fmt::Write;
+
+use super::{Layout, Pipeline};
+
+enum Node {
+    State(usize),
+    Decision(usize),
+}
+
+enum Item {
+    Node(Node),
+    Group(&'static str, Vec<usize>),
+}
+
+// the nodes and edges of a pipeline, rendered as Mermaid or Graphviz
+pub(super) struct Diagram<'a> {
+    pipeline: &'a Pipeline,
+    items: Vec<Item>,
+    edges: Vec<(usize, usize, Option<&'static str>)>,
+}
+
+impl<'a> Diagram<'a> {
+    pub(super) fn new(pipeline: &'a Pipeline) -> Self {
+        let mut items = Vec::new();
+        let mut edges = Vec::new();
+        // the nodes (and the edge label) that lead to the next node of the pipeline
+        let mut tails: Vec<(usize, Option<&'static str>)> = Vec::new();
+
+        let mut chain = |tails: &mut Vec<(usize, Option<&'static str>)>, index: usize| {
+            tails
+                .drain(..)
+                .for_each(|(from, label)| edges.push((from, index, label)));
+            tails.push((index, None));
+        };
+
+        for layout in pipeline.layout.iter() {
+            match layout {
+                Layout::State(index) => {
+                    items.push(Item::Node(Node::State(*index)));
+                    chain(&mut tails, *index);
+                }
+                Layout::Group(name, indexes) => {
+                    items.push(Item::Group(name, indexes.clone()));
+                    indexes.iter().for_each(|index| chain(&mut tails, *index));
+                }
+                Layout::Branch {
+                    decision,
+                    then,
+                    otherwise,
+                } => {
+                    items.push(Item::Node(Node::Decision(*decision)));
+                    chain(&mut tails, *decision);
+                    tails.clear();
+
+                    for (arm, label) in [(then, "true"), (otherwise, "false")] {
+                        let mut arm_tails = vec![(*decision, Some(label))];
+                        for index in arm.iter() {
+                            items.push(Item::Node(Node::State(*index)));
+                            chain(&mut arm_tails, *index);
+                        }
+                        tails.extend(arm_tails);
+                    }
+                }
+            }
+        }
+
+        Self {
+            pipeline,
+            items,
+            edges,
+        }
+    }
+
+    fn label(&self, index: usize) -> &'static str {
+        self.pipeline.states[index].label().as_str()
+    }
+
+    pub(super) fn mermaid(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "flowchart TD");
+        let _ = writeln!(out, "    %% pipeline: {}", self.pipeline.name);
+
+        let node = |out: &mut String, node: &Node, indent: &str| {
+            let _ = match node {
+                Node::State(i) => writeln!(out, "{}s{}[\"{}\"]", indent, i, self.label(*i)),
+                Node::Decision(i) => writeln!(out, "{}s{}{{\"{}\"}}", indent, i, self.label(*i)),
+            };
+        };
+
+        for item in self.items.iter() {
+            match item {
+                Item::Node(n) => node(&mut out, n, "    "),
+                Item::Group(name, indexes) => {
+                    let _ = writeln!(out, "    subgraph {}", name);
+                    indexes
+                        .iter()
+                        .for_each(|i| node(&mut out, &Node::State(*i), "        "));
+                    let _ = writeln!(out, "    end");
+                }
+            }
+        }
+
+        for (from, to, label) in self.edges.iter() {
+            let _ = match label {
+                Some(label) => writeln!(out, "    s{} -- \"{}\" --> s{}", from, label, to),
+                None => writeln!(out, "    s{} --> s{}", from, to),
+            };
+        }
+
+        out
+    }
+
+    pub(super) fn graphviz(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph {} {{", self.pipeline.name);
+
+        let node = |out: &mut String, node: &Node, indent: &str| {
+            let _ = match node {
+                Node::State(i) => writeln!(
+                    out,
+                    "{}s{} [label=\"{}\", shape=box];",
+                    indent,
+                    i,
+                    self.label(*i)
+                ),
+                Node::Decision(i) => writeln!(
+                    out,
+                    "{}s{} [label=\"{}\", shape=diamond];",
+                    indent,
+                    i,
+                    self.label(*i)
+                ),
+            };
+        };
+
+        for item in self.items.iter() {
+            match item {
+                Item::Node(n) => node(&mut out, n, "    "),
+                Item::Group(name, indexes) => {
+                    let _ = writeln!(out, "    subgraph cluster_{} {{", name);
+                    let _ = writeln!(out, "        label=\"{}\";", name);
+                    indexes
+                        .iter()
+                        .for_each(|i| node(&mut out, &Node::State(*i), "        "));
+                    let _ = writeln!(out, "    }}");
+                }
+            }
+        }
+
+        for (from, to, label) in self.edges.iter() {
+            let _ = match label {
+                Some(label) => writeln!(out, "    s{} -> s{} [label=\"{}\"];", from, to, label),
+                None => writeln!(out, "    s{} -> s{};", from, to),
+            };
+        }
+
+        let _ = writeln!(out, "}}");
+        out
+    }
+}
@@ -0,0 +1,580 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::anyhow;
+use serde_json::json;
+
+use crate::state::{
+    context::{read_value, write_value, Context, ContextWrapper},
+    ensure_nonempty_ascii_lowercase_underscore, DependencyStrategy, Label, StateError,
+    StateErrorRecoverability, StateHandler, StateMetadata, StateResult, States, Tag,
+};
+use crate::state_machine::{StateMachine, StateMachineBuilder};
+
+mod diagram;
+
+pub const BRANCH_KEY_PREFIX: &str = "pipeline_branch";
+
+// context key where the decision of a branch is stored, so the states of its arms
+// (and a recovery from the tracker) see the same decision
+pub fn branch_key(name: &str) -> String {
+    format!("{}.{}", BRANCH_KEY_PREFIX, name)
+}
+
+pub type Condition = Arc<dyn Fn(&dyn Context) -> bool + Send + Sync>;
+
+/// Declares a [`Pipeline`] with its states and groups of states, e.g.:
+///
+/// ```ignore
+/// let pipeline = pipeline!("price_report" => [
+///     Setup::new(),
+///     group("pricing") [ComputePrice::new(), Report::new()],
+/// ])
+/// .build()?;
+/// ```
+///
+/// It expands to a [`PipelineBuilder`], so branches can still be added with
+/// [`PipelineBuilder::branch`] before building it.
+#[macro_export]
+macro_rules! pipeline {
+    ($name:expr => [$($items:tt)*]) => {
+        $crate::__pipeline_items!($crate::pipeline::PipelineBuilder::new($name); $($items)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __pipeline_items {
+    ($builder:expr;) => {
+        $builder
+    };
+    ($builder:expr; group($name:expr) [$($inner:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::__pipeline_items!(
+            $builder.group($name, |group| $crate::__pipeline_items!(group; $($inner)*));
+            $($($rest)*)?
+        )
+    };
+    ($builder:expr; $state:expr $(, $($rest:tt)*)?) => {
+        $crate::__pipeline_items!(
+            $builder.state(::std::boxed::Box::new($state));
+            $($($rest)*)?
+        )
+    };
+}
+
+#[derive(Debug)]
+pub enum PipelineError {
+    EmptyPipeline(anyhow::Error),
+    InvalidName(&'static str, anyhow::Error),
+    DuplicatedName(&'static str, anyhow::Error),
+    UnsatisfiedDependency(Label, anyhow::Error),
+}
+
+/// A sequence of states used by a [`Group`] or by the arms of a [`Branch`].
+#[derive(Default)]
+pub struct Group {
+    states: Vec<Box<dyn StateHandler>>,
+}
+
+impl Group {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(mut self, state: Box<dyn StateHandler>) -> Self {
+        self.states.push(state);
+        self
+    }
+}
+
+/// Runs the `then` states when the condition holds for the context at the time the
+/// branch is reached, and the `otherwise` states when it doesn't.
+pub struct Branch {
+    name: &'static str,
+    condition: Condition,
+    then: Group,
+    otherwise: Group,
+}
+
+impl Branch {
+    pub fn new<F>(name: &'static str, condition: F) -> Self
+    where
+        F: Fn(&dyn Context) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            name,
+            condition: Arc::new(condition),
+            then: Group::new(),
+            otherwise: Group::new(),
+        }
+    }
+
+    pub fn then(mut self, state: Box<dyn StateHandler>) -> Self {
+        self.then = self.then.state(state);
+        self
+    }
+
+    pub fn otherwise(mut self, state: Box<dyn StateHandler>) -> Self {
+        self.otherwise = self.otherwise.state(state);
+        self
+    }
+}
+
+enum Step {
+    State(Box<dyn StateHandler>),
+    Group(&'static str, Group),
+    Branch(Branch),
+}
+
+// the shape of the pipeline over the flattened states indexes, used to describe it
+enum Layout {
+    State(usize),
+    Group(&'static str, Vec<usize>),
+    Branch {
+        decision: usize,
+        then: Vec<usize>,
+        otherwise: Vec<usize>,
+    },
+}
+
+pub struct PipelineBuilder {
+    name: &'static str,
+    steps: Vec<Step>,
+}
+
+impl PipelineBuilder {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn state(mut self, state: Box<dyn StateHandler>) -> Self {
+        self.steps.push(Step::State(state));
+        self
+    }
+
+    pub fn group<F>(mut self, name: &'static str, group: F) -> Self
+    where
+        F: FnOnce(Group) -> Group,
+    {
+        self.steps.push(Step::Group(name, group(Group::new())));
+        self
+    }
+
+    pub fn branch(mut self, branch: Branch) -> Self {
+        self.steps.push(Step::Branch(branch));
+        self
+    }
+
+    fn validate_names(&self) -> Result<(), PipelineError> {
+        let mut names = HashSet::new();
+        let step_names = self.steps.iter().filter_map(|step| match step {
+            Step::State(_) => None,
+            Step::Group(name, _) => Some(*name),
+            Step::Branch(branch) => Some(branch.name),
+        });
+
+        for name in std::iter::once(self.name).chain(step_names) {
+            ensure_nonempty_ascii_lowercase_underscore(name)
+                .map_err(|e| PipelineError::InvalidName(name, e))?;
+
+            if !names.insert(name) {
+                return Err(PipelineError::DuplicatedName(
+                    name,
+                    anyhow!("'{}' is used by more than one group or branch", name),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<Pipeline, PipelineError> {
+        self.validate_names()?;
+
+        let mut states: Vec<Box<dyn StateHandler>> = Vec::new();
+        let mut layout = Vec::new();
+
+        for step in self.steps {
+            match step {
+                Step::State(state) => layout.push(Layout::State(push(&mut states, state))),
+                Step::Group(name, group) => {
+                    let indexes = group
+                        .states
+                        .into_iter()
+                        .map(|state| push(&mut states, state))
+                        .collect();
+                    layout.push(Layout::Group(name, indexes));
+                }
+                Step::Branch(branch) => {
+                    let decision = push(
+                        &mut states,
+                        Box::new(BranchDecision {
+                            label: Label::new(branch.name)
+                                .map_err(|e| PipelineError::InvalidName(branch.name, e))?,
+                            condition: branch.condition,
+                        }),
+                    );
+
+                    let mut guard = |group: Group, arm: bool| -> Vec<usize> {
+                        group
+                            .states
+                            .into_iter()
+                            .map(|inner| {
+                                let guarded = Guarded {
+                                    inner,
+                                    branch: branch.name,
+                                    arm,
+                                };
+                                push(&mut states, Box::new(guarded))
+                            })
+                            .collect()
+                    };
+
+                    let then = guard(branch.then, true);
+                    let otherwise = guard(branch.otherwise, false);
+
+                    layout.push(Layout::Branch {
+                        decision,
+                        then,
+                        otherwise,
+                    });
+                }
+            }
+        }
+
+        if states.is_empty() {
+            return Err(PipelineError::EmptyPipeline(anyhow!(
+                "pipeline '{}' has no state to execute",
+                self.name
+            )));
+        }
+
+        validate_dependencies(&states)?;
+
+        Ok(Pipeline {
+            name: self.name,
+            states: states.into(),
+            layout,
+        })
+    }
+}
+
+fn push(states: &mut Vec<Box<dyn StateHandler>>, state: Box<dyn StateHandler>) -> usize {
+    states.push(state);
+    states.len() - 1
+}
+
+// on a recoverable error the state machine goes back to the last state tagged with
+// the first dependency of the failing state, so it must be provided by the state itself
+// or by one that runs before it
+fn validate_dependencies(states: &[Box<dyn StateHandler>]) -> Result<(), PipelineError> {
+    let mut provided = HashSet::new();
+
+    for state in states.iter() {
+        provided.extend(state.tags());
+
+        if let Some(missing) = state
+            .depends_on()
+            .into_iter()
+            .find(|tag| !provided.contains(tag))
+        {
+            return Err(PipelineError::UnsatisfiedDependency(
+                state.label(),
+                anyhow!(
+                    "state '{}' depends on '{}', but no state up to it has this tag",
+                    state.label().as_str(),
+                    missing.as_str()
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// A validated sequence of states, ready to be executed by a [`StateMachine`].
+pub struct Pipeline {
+    name: &'static str,
+    states: States,
+    layout: Vec<Layout>,
+}
+
+impl Pipeline {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn states(&self) -> States {
+        self.states.clone()
+    }
+
+    pub fn state_machine_builder(&self) -> StateMachineBuilder {
+        StateMachineBuilder::new(self.states())
+    }
+
+    pub fn state_machine(&self) -> StateMachine {
+        StateMachine::new(self.states())
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        diagram::Diagram::new(self).mermaid()
+    }
+
+    pub fn to_graphviz(&self) -> String {
+        diagram::Diagram::new(self).graphviz()
+    }
+}
+
+struct BranchDecision {
+    label: Label,
+    condition: Condition,
+}
+
+impl StateMetadata for BranchDecision {
+    fn label(&self) -> Label {
+        self.label
+    }
+
+    fn tags(&self) -> Vec<Tag> {
+        vec![]
+    }
+
+    fn depends_on(&self) -> Vec<Tag> {
+        vec![]
+    }
+
+    fn depends_on_strategy(&self) -> DependencyStrategy {
+        DependencyStrategy::Latest
+    }
+}
+
+impl StateHandler for BranchDecision {
+    fn handler(&self, context: ContextWrapper) -> StateResult {
+        let decision = {
+            let context = context.lock().map_err(|e| {
+                StateError::StorageAccess(
+                    StateErrorRecoverability::Unrecoverable,
+                    anyhow!("context lock poisoned: {}", e),
+                )
+            })?;
+            (self.condition)(context.as_ref())
+        };
+
+        write_value(&context, &branch_key(self.label.as_str()), &json!(decision))
+    }
+}
+
+// a state of a branch arm, only handled when the branch decision picked its arm
+struct Guarded {
+    inner: Box<dyn StateHandler>,
+    branch: &'static str,
+    arm: bool,
+}
+
+impl StateMetadata for Guarded {
+    fn label(&self) -> Label {
+        self.inner.label()
+    }
+
+    fn tags(&self) -> Vec<Tag> {
+        self.inner.tags()
+    }
+
+    fn depends_on(&self) -> Vec<Tag> {
+        self.inner.depends_on()
+    }
+
+    fn depends_on_strategy(&self) -> DependencyStrategy {
+        self.inner.depends_on_strategy()
+    }
+}
+
+impl StateHandler for Guarded {
+    fn handler(&self, context: ContextWrapper) -> StateResult {
+        let decision: bool = read_value(&context, &branch_key(self.branch))?;
+
+        if decision == self.arm {
+            self.inner.handler(context)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// the test states are built with the derive macros
+#[cfg(all(test, feature = "derive"))]
+mod test {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::state::context::{read_value, wrap_context, Local};
+    use crate::StateMetadataReqs;
+
+    #[derive(StateMetadataReqs)]
+    #[state(label = "setup", tags("setup"), depends_on("setup"))]
+    struct Setup;
+
+    impl StateHandler for Setup {
+        fn handler(&self, context: ContextWrapper) -> StateResult {
+            write_value(&context, "setup", &json!(true))
+        }
+    }
+
+    #[derive(StateMetadataReqs)]
+    #[state(label = "buy", tags("trade"), depends_on("setup"))]
+    struct Buy;
+
+    impl StateHandler for Buy {
+        fn handler(&self, context: ContextWrapper) -> StateResult {
+            write_value(&context, "trade", &json!("buy"))
+        }
+    }
+
+    #[derive(StateMetadataReqs)]
+    #[state(label = "sell", tags("trade"), depends_on("setup"))]
+    struct Sell;
+
+    impl StateHandler for Sell {
+        fn handler(&self, context: ContextWrapper) -> StateResult {
+            write_value(&context, "trade", &json!("sell"))
+        }
+    }
+
+    #[derive(StateMetadataReqs)]
+    #[state(label = "report", tags("report"), depends_on("trade"))]
+    struct Report;
+
+    impl StateHandler for Report {
+        fn handler(&self, _context: ContextWrapper) -> StateResult {
+            Ok(())
+        }
+    }
+
+    fn trade_pipeline() -> Pipeline {
+        pipeline!("trade" => [
+            Setup,
+            group("trading") [Buy, Sell],
+        ])
+        .branch(
+            Branch::new("should_report", |ctx| {
+                ctx.read("report".to_string()).is_ok()
+            })
+            .then(Box::new(Report)),
+        )
+        .build()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pipeline_build() {
+        let pipeline = trade_pipeline();
+
+        let labels: Vec<&str> = pipeline
+            .states()
+            .iter()
+            .map(|state| state.label().as_str())
+            .collect();
+
+        assert_eq!(pipeline.name(), "trade");
+        assert_eq!(
+            labels,
+            vec!["setup", "buy", "sell", "should_report", "report"]
+        );
+    }
+
+    #[test]
+    fn test_pipeline_branch_execution() {
+        let pipeline = trade_pipeline();
+
+        let context = wrap_context(Local::default());
+        pipeline.state_machine().execute(context.clone()).unwrap();
+        let decision: bool = read_value(&context, &branch_key("should_report")).unwrap();
+        assert!(!decision);
+
+        let context = wrap_context(Local::new(HashMap::from([(
+            "report".to_string(),
+            json!(true),
+        )])));
+        pipeline.state_machine().execute(context.clone()).unwrap();
+        let decision: bool = read_value(&context, &branch_key("should_report")).unwrap();
+        assert!(decision);
+    }
+
+    #[test]
+    fn test_pipeline_validation() {
+        let empty = PipelineBuilder::new("empty").build();
+        assert!(matches!(empty, Err(PipelineError::EmptyPipeline(_))));
+
+        let invalid_name = PipelineBuilder::new("Invalid Name")
+            .state(Box::new(Setup))
+            .build();
+        assert!(matches!(
+            invalid_name,
+            Err(PipelineError::InvalidName("Invalid Name", _))
+        ));
+
+        let duplicated = PipelineBuilder::new("duplicated")
+            .group("twice", |g| g.state(Box::new(Setup)))
+            .group("twice", |g| g.state(Box::new(Buy)))
+            .build();
+        assert!(matches!(
+            duplicated,
+            Err(PipelineError::DuplicatedName("twice", _))
+        ));
+
+        let unsatisfied = PipelineBuilder::new("unsatisfied")
+            .state(Box::new(Buy))
+            .state(Box::new(Setup))
+            .build();
+        assert!(matches!(
+            unsatisfied,
+            Err(PipelineError::UnsatisfiedDependency(label, _)) if label.as_str() == "buy"
+        ));
+    }
+
+    #[test]
+    fn test_pipeline_diagrams() {
+        let pipeline = trade_pipeline();
+
+        assert_eq!(
+            pipeline.to_mermaid(),
+            r#"flowchart TD
+    %% pipeline: trade
+    s0["setup"]
+    subgraph trading
+        s1["buy"]
+        s2["sell"]
+    end
+    s3{"should_report"}
+    s4["report"]
+    s0 --> s1
+    s1 --> s2
+    s2 --> s3
+    s3 -- "true" --> s4
+"#
+        );
+
+        assert_eq!(
+            pipeline.to_graphviz(),
+            r#"digraph trade {
+    s0 [label="setup", shape=box];
+    subgraph cluster_trading {
+        label="trading";
+        s1 [label="buy", shape=box];
+        s2 [label="sell", shape=box];
+    }
+    s3 [label="should_report", shape=diamond];
+    s4 [label="report", shape=box];
+    s0 -> s1;
+    s1 -> s2;
+    s2 -> s3;
+    s3 -> s4 [label="true"];
+}
+"#
+        );
+    }
+}
@@ -1,12 +1,17 @@
 use std::{
     collections::HashMap,
+    fmt,
+    marker::PhantomData,
     sync::{Arc, Mutex},
 };
 
 use anyhow::{anyhow, Error};
+use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use super::{StateError, StateErrorRecoverability, StateResult};
+
 pub type ContextWrapper = Arc<Mutex<Box<dyn Context>>>;
 
 // TODO: rethink this implementation of kv store context;
@@ -38,21 +43,176 @@ impl Context for Local {
     }
 
     fn dump(&self) -> Result<Value, Error> {
-        Ok(json!(self))
+        let mut dump = json!(self);
+        redact_secrets(&mut dump);
+        Ok(dump)
     }
 }
 
 pub trait Context {
     fn read(&self, key: String) -> Result<Value, Error>;
     fn write(&mut self, key: String, value: &Value) -> Result<(), Error>;
+    /// The whole context, as logged or tracked; values marked secret are redacted.
     fn dump(&self) -> Result<Value, Error>;
 }
 
+/// The key of the objects marking a value as secret, e.g. `{"$secret": ...}`.
+///
+/// States can read such values back from the context, but dumps, and so the tracker
+/// history and the logs, never hold them.
+pub const SECRET_MARKER: &str = "$secret";
+/// What a value marked secret is replaced with.
+pub const REDACTED: &str = "<redacted>";
+
+/// Replaces the content of every value marked secret with [`REDACTED`], returning
+/// how many were found.
+pub fn redact_secrets(value: &mut Value) -> usize {
+    match value {
+        // the whole object goes, so no field next to the marker leaks either
+        Value::Object(map) if map.contains_key(SECRET_MARKER) => {
+            *map = serde_json::Map::from_iter([(SECRET_MARKER.to_string(), json!(REDACTED))]);
+            1
+        }
+        Value::Object(map) => map.values_mut().map(redact_secrets).sum(),
+        Value::Array(items) => items.iter_mut().map(redact_secrets).sum(),
+        _ => 0,
+    }
+}
+
 pub fn wrap_context<C: Context + 'static>(context: C) -> ContextWrapper {
     #[allow(clippy::arc_with_non_send_sync)]
     Arc::new(Mutex::new(Box::new(context)))
 }
 
+// a poisoned lock means a state panicked while holding the context,
+// so its content can't be trusted anymore
+fn poisoned_context_error<T>(e: std::sync::PoisonError<T>) -> StateError {
+    StateError::StorageAccess(
+        StateErrorRecoverability::Unrecoverable,
+        anyhow!("context lock poisoned: {}", e),
+    )
+}
+
+/// Reads the value stored under `key` and deserializes it into `T`.
+///
+/// A missing key is a recoverable `StateError::StorageAccess`, since a dependency may
+/// write it when retried; a value that can't be deserialized is an unrecoverable
+/// `StateError::ParsingInput`.
+pub fn read_value<T: DeserializeOwned>(
+    context: &ContextWrapper,
+    key: &str,
+) -> Result<T, StateError> {
+    let value = context
+        .lock()
+        .map_err(poisoned_context_error)?
+        .read(key.to_string())
+        .map_err(|e| {
+            StateError::StorageAccess(
+                StateErrorRecoverability::Recoverable,
+                e.context(format!("reading context key '{}'", key)),
+            )
+        })?;
+
+    serde_json::from_value(value).map_err(|e| {
+        StateError::ParsingInput(
+            StateErrorRecoverability::Unrecoverable,
+            anyhow!(e).context(format!("deserializing context key '{}'", key)),
+        )
+    })
+}
+
+/// Same as `read_value`, but returns `None` when the key can't be read.
+pub fn read_optional_value<T: DeserializeOwned>(
+    context: &ContextWrapper,
+    key: &str,
+) -> Result<Option<T>, StateError> {
+    let value = match context
+        .lock()
+        .map_err(poisoned_context_error)?
+        .read(key.to_string())
+    {
+        Ok(value) => value,
+        Err(_) => return Ok(None),
+    };
+
+    serde_json::from_value(value).map_err(|e| {
+        StateError::ParsingInput(
+            StateErrorRecoverability::Unrecoverable,
+            anyhow!(e).context(format!("deserializing context key '{}'", key)),
+        )
+    })
+}
+
+/// Serializes `value` and writes it under `key`.
+pub fn write_value<T: serde::Serialize>(
+    context: &ContextWrapper,
+    key: &str,
+    value: &T,
+) -> StateResult {
+    let value = serde_json::to_value(value).map_err(|e| {
+        StateError::ParsingInput(
+            StateErrorRecoverability::Unrecoverable,
+            anyhow!(e).context(format!("serializing context key '{}'", key)),
+        )
+    })?;
+
+    context
+        .lock()
+        .map_err(poisoned_context_error)?
+        .write(key.to_string(), &value)
+        .map_err(|e| StateError::StorageAccess(StateErrorRecoverability::Recoverable, e))
+}
+
+/// A context key bound to the type of the value stored under it, so states
+/// sharing a key can't disagree on its type.
+pub struct ContextKey<T> {
+    name: &'static str,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> ContextKey<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _value: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T: DeserializeOwned> ContextKey<T> {
+    pub fn read(&self, context: &ContextWrapper) -> Result<T, StateError> {
+        read_value(context, self.name)
+    }
+
+    pub fn read_optional(&self, context: &ContextWrapper) -> Result<Option<T>, StateError> {
+        read_optional_value(context, self.name)
+    }
+}
+
+impl<T: serde::Serialize> ContextKey<T> {
+    pub fn write(&self, context: &ContextWrapper, value: &T) -> StateResult {
+        write_value(context, self.name, value)
+    }
+}
+
+impl<T> Clone for ContextKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ContextKey<T> {}
+
+impl<T> fmt::Debug for ContextKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ContextKey").field(&self.name).finish()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use serde_json::json;
@@ -70,4 +230,67 @@ mod test {
 
         assert_eq!(context_a.read(key).unwrap(), body);
     }
+
+    #[test]
+    fn test_read_write_value() {
+        let context = wrap_context(Local::default());
+
+        write_value(&context, "key1", &vec![1, 2, 3]).unwrap();
+
+        let value: Vec<u32> = read_value(&context, "key1").unwrap();
+        assert_eq!(value, vec![1, 2, 3]);
+
+        let missing = read_value::<Vec<u32>>(&context, "key2").unwrap_err();
+        assert!(matches!(missing, StateError::StorageAccess(_, _)));
+        assert!(missing.is_recoverable());
+
+        let mistyped = read_value::<String>(&context, "key1").unwrap_err();
+        assert!(matches!(mistyped, StateError::ParsingInput(_, _)));
+        assert!(!mistyped.is_recoverable());
+
+        let optional: Option<Vec<u32>> = read_optional_value(&context, "key2").unwrap();
+        assert!(optional.is_none());
+    }
+
+    #[test]
+    fn test_dump_redacts_secrets() {
+        let context_a: &mut dyn Context = &mut Local::default();
+        let config = json!({
+            "wallets": [
+                {"name": "a", "key": {"$secret": "0xdeadbeef"}},
+                {"name": "b", "key": {"$secret": {"nested": "0xfeedface"}, "hint": "cafe"}},
+            ],
+        });
+        context_a.write("config".to_string(), &config).unwrap();
+
+        // states still read the secrets back
+        assert_eq!(context_a.read("config".to_string()).unwrap(), config);
+
+        let dump = context_a.dump().unwrap().to_string();
+        for secret in ["deadbeef", "feedface", "cafe"] {
+            assert!(!dump.contains(secret));
+        }
+        assert_eq!(dump.matches(REDACTED).count(), 2);
+
+        let mut value = json!([{"$secret": 1}, {"other": {"$secret": null}}, 3]);
+        assert_eq!(redact_secrets(&mut value), 2);
+        assert_eq!(
+            value,
+            json!([{"$secret": REDACTED}, {"other": {"$secret": REDACTED}}, 3])
+        );
+    }
+
+    #[test]
+    fn test_context_key() {
+        const KEY: ContextKey<Vec<u32>> = ContextKey::new("key1");
+        let context = wrap_context(Local::default());
+
+        KEY.write(&context, &vec![1, 2, 3]).unwrap();
+
+        assert_eq!(KEY.read(&context).unwrap(), vec![1, 2, 3]);
+        assert_eq!(
+            read_value::<Vec<u32>>(&context, KEY.name()).unwrap(),
+            vec![1, 2, 3]
+        );
+    }
 }
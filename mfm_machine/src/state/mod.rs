@@ -11,7 +11,9 @@ pub struct Tag(&'static str);
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Label(&'static str);
 
-fn ensure_nonempty_ascii_lowercase_underscore(input: &'static str) -> Result<&'static str, Error> {
+pub(crate) fn ensure_nonempty_ascii_lowercase_underscore(
+    input: &'static str,
+) -> Result<&'static str, Error> {
     if input.is_empty() {
         return Err(anyhow!("empty string; this string should be non empty, lowercase and use underscore as separator"));
     }
@@ -30,6 +32,10 @@ impl Tag {
             Err(e) => Err(e),
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
 }
 
 impl Label {
@@ -39,6 +45,10 @@ impl Label {
             Err(e) => Err(e),
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
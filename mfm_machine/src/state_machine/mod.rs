@@ -1,5 +1,3 @@
-use std::usize;
-
 use anyhow::anyhow;
 
 use crate::state::{context::ContextWrapper, StateResult, States};
@@ -190,14 +188,15 @@ impl StateMachine {
     }
 }
 
-#[cfg(test)]
+// the test states are built with the derive macros
+#[cfg(all(test, feature = "derive"))]
 mod test {
     use std::sync::Arc;
 
     use crate::state::context::{wrap_context, Context, ContextWrapper, Local};
     use crate::state::{DependencyStrategy, Label, StateHandler, StateMetadata, Tag};
     use crate::state::{StateError, StateErrorRecoverability};
-    use mfm_machine_derive::StateMetadataReqs;
+    use crate::StateMetadataReqs;
     use serde_derive::{Deserialize, Serialize};
     use serde_json::json;
 
@@ -3,7 +3,10 @@ use std::{collections::HashMap, fmt::Debug};
 use anyhow::{anyhow, Error};
 use serde_json::Value;
 
-use crate::state::{context::ContextWrapper, Label, Tag};
+use crate::state::{
+    context::{redact_secrets, ContextWrapper},
+    Label, Tag,
+};
 
 pub trait TrackerMetadata {
     fn indexes(&self) -> Vec<Index>;
@@ -41,8 +44,10 @@ impl TrackerHistory {
     }
 
     pub fn push(&mut self, index: Index, context: ContextWrapper) {
-        self.0
-            .push((self.len(), index, context.lock().unwrap().dump().unwrap()))
+        let mut dump = context.lock().unwrap().dump().unwrap();
+        // whatever the context implementation, secrets are never kept in the history
+        redact_secrets(&mut dump);
+        self.0.push((self.len(), index, dump))
     }
 }
 
@@ -139,7 +144,7 @@ mod test {
     use serde_json::json;
 
     use crate::state::{
-        context::{wrap_context, ContextWrapper, Local},
+        context::{wrap_context, Context, ContextWrapper, Local},
         Label, Tag,
     };
 
@@ -154,7 +159,7 @@ mod test {
             wrap_context(Local::new(HashMap::from([("value".to_string(), json!(2))]))),
             wrap_context(Local::new(HashMap::from([("value".to_string(), json!(3))]))),
         ];
-        let indexes = vec![
+        let indexes = [
             Index::new(
                 1,
                 Label::new("value_one").unwrap(),
@@ -188,6 +193,57 @@ mod test {
         }
     }
 
+    // a context that dumps its values as they are
+    struct Raw(HashMap<String, serde_json::Value>);
+
+    impl Context for Raw {
+        fn read(&self, key: String) -> Result<serde_json::Value, anyhow::Error> {
+            Ok(self.0[&key].clone())
+        }
+
+        fn write(&mut self, key: String, value: &serde_json::Value) -> Result<(), anyhow::Error> {
+            self.0.insert(key, value.clone());
+            Ok(())
+        }
+
+        fn dump(&self) -> Result<serde_json::Value, anyhow::Error> {
+            Ok(json!(self.0))
+        }
+    }
+
+    #[test]
+    fn test_history_redacts_secrets() {
+        let tracker: &mut dyn Tracker = &mut HashMapTracker::new();
+        let secret = json!({"$secret": "0xdeadbeef"});
+        let index = Index::new(
+            1,
+            Label::new("value_one").unwrap(),
+            vec![Tag::new("tag_one").unwrap()],
+        );
+
+        let local = wrap_context(Local::new(HashMap::from([(
+            "key".to_string(),
+            secret.clone(),
+        )])));
+        let raw = wrap_context(Raw(HashMap::from([("key".to_string(), secret.clone())])));
+        tracker.track(index.clone(), local.clone()).unwrap();
+        tracker.track(index.clone(), raw).unwrap();
+
+        let history = tracker.history();
+        assert_eq!(history.len(), 2);
+        assert!(!format!("{:?}", history).contains("deadbeef"));
+        for (_, _, value) in history {
+            assert!(!value.to_string().contains("deadbeef"));
+        }
+
+        // the tracked context itself keeps the secret for the recovery
+        let recovered = tracker.recover(index).unwrap();
+        assert_eq!(
+            recovered.lock().unwrap().read("key".to_string()).unwrap(),
+            secret
+        );
+    }
+
     #[test]
     fn test_search_by_tag() {
         let tracker: &mut dyn Tracker = &mut HashMapTracker::new();
@@ -197,7 +253,7 @@ mod test {
             wrap_context(Local::new(HashMap::from([("value".to_string(), json!(2))]))),
             wrap_context(Local::new(HashMap::from([("value".to_string(), json!(3))]))),
         ];
-        let indexes = vec![
+        let indexes = [
             Index::new(
                 1,
                 Label::new("value_one").unwrap(),
@@ -239,7 +295,7 @@ mod test {
             wrap_context(Local::new(HashMap::from([("value".to_string(), json!(2))]))),
             wrap_context(Local::new(HashMap::from([("value".to_string(), json!(3))]))),
         ];
-        let indexes = vec![
+        let indexes = [
             Index::new(
                 1,
                 Label::new("value_one").unwrap(),
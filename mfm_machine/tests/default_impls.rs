@@ -1,3 +1,6 @@
+// shared between integration tests; each test binary uses only a subset of it
+#![allow(dead_code)]
+
 use anyhow::anyhow;
 use mfm_machine::state::context::ContextWrapper;
 use mfm_machine::state::DependencyStrategy;
@@ -5,10 +8,9 @@ use mfm_machine::state::Label;
 use mfm_machine::state::StateError;
 use mfm_machine::state::StateErrorRecoverability;
 use mfm_machine::state::StateHandler;
-use mfm_machine::state::StateMetadata;
 use mfm_machine::state::StateResult;
 use mfm_machine::state::Tag;
-use mfm_machine_derive::StateMetadataReqs;
+use mfm_machine::StateMetadataReqs;
 use rand::Rng;
 use serde_derive::{Deserialize, Serialize};
 use serde_json::json;
@@ -100,7 +102,7 @@ impl StateHandler for ComputePrice {
     fn handler(&self, context: ContextWrapper) -> StateResult {
         let value = context.lock().unwrap().read("setup".to_string()).unwrap();
         let _data: SetupCtx = serde_json::from_value(value).unwrap();
-        if _data.b % 2 == 0 {
+        if _data.b.is_multiple_of(2) {
             return Err(StateError::ParsingInput(
                 StateErrorRecoverability::Recoverable,
                 anyhow!("the input is even, should be odd"),
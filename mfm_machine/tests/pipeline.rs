@@ -0,0 +1,41 @@
+mod default_impls;
+
+use default_impls::{ComputePrice, Report, Setup};
+use mfm_machine::pipeline;
+use mfm_machine::state::context::{wrap_context, Local};
+use serde_json::json;
+use std::collections::HashMap;
+
+#[test]
+fn test_pipeline_state_machine_execute() {
+    let pipeline = pipeline!("price_report" => [
+        Setup::new(),
+        group("pricing") [ComputePrice::new(), Report::new()],
+    ])
+    .build()
+    .unwrap();
+
+    let context = wrap_context(Local::new(HashMap::from([(
+        "zero_ctx".to_string(),
+        json!(0),
+    )])));
+
+    let mut state_machine = pipeline.state_machine_builder().max_recoveries(100).build();
+    let result = state_machine.execute(context);
+
+    assert!(result.is_ok());
+    assert_eq!(pipeline.states().len(), 3);
+    assert_eq!(
+        pipeline.to_mermaid(),
+        r#"flowchart TD
+    %% pipeline: price_report
+    s0["setup_state"]
+    subgraph pricing
+        s1["compute_price"]
+        s2["report_state"]
+    end
+    s0 --> s1
+    s1 --> s2
+"#
+    );
+}
@@ -0,0 +1,50 @@
+use mfm_machine::state::context::{wrap_context, ContextWrapper, Local};
+use mfm_machine::state::{
+    DependencyStrategy, Label, StateHandler, StateMetadata, StateResult, Tag,
+};
+use mfm_machine::StateMetadataReqs;
+
+#[derive(Debug, Clone, Default, PartialEq, StateMetadataReqs)]
+#[state(
+    label = "read_config",
+    tags("setup", "config"),
+    depends_on("setup"),
+    strategy = "latest"
+)]
+pub struct ReadConfig;
+
+impl StateHandler for ReadConfig {
+    fn handler(&self, _context: ContextWrapper) -> StateResult {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, StateMetadataReqs)]
+#[state(label = "only_label")]
+pub struct OnlyLabel {
+    _value: u32,
+}
+
+#[test]
+fn test_state_metadata_from_attributes() {
+    let state = ReadConfig;
+
+    assert_eq!(state.label(), Label::new("read_config").unwrap());
+    assert_eq!(
+        state.tags(),
+        vec![Tag::new("setup").unwrap(), Tag::new("config").unwrap()]
+    );
+    assert_eq!(state.depends_on(), vec![Tag::new("setup").unwrap()]);
+    assert_eq!(state.depends_on_strategy(), DependencyStrategy::Latest);
+    assert!(state.handler(wrap_context(Local::default())).is_ok());
+}
+
+#[test]
+fn test_state_metadata_from_attributes_defaults() {
+    let state = OnlyLabel::default();
+
+    assert_eq!(state.label(), Label::new("only_label").unwrap());
+    assert!(state.tags().is_empty());
+    assert!(state.depends_on().is_empty());
+    assert_eq!(state.depends_on_strategy(), DependencyStrategy::Latest);
+}
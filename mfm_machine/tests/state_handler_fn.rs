@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use mfm_machine::state::context::{read_value, wrap_context, Local};
+use mfm_machine::state::{
+    DependencyStrategy, Label, StateError, StateErrorRecoverability, StateHandler, StateMetadata,
+    States, Tag,
+};
+use mfm_machine::state_handler;
+use mfm_machine::state_machine::StateMachine;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetupCtx {
+    a: String,
+    b: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ComputePriceCtx {
+    msg: String,
+    b: u32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReportCtx {
+    report_msg: String,
+    report_value: u32,
+}
+
+#[state_handler(tags("setup"), depends_on("setup"), output = "setup")]
+fn setup_state(zero_ctx: u32) -> SetupCtx {
+    SetupCtx {
+        a: "setup_b".to_string(),
+        b: zero_ctx + 3,
+    }
+}
+
+#[state_handler(
+    label = "compute_price",
+    tags("computation"),
+    depends_on("setup"),
+    strategy = "latest",
+    output = "compute"
+)]
+fn compute_price(setup: SetupCtx) -> Result<ComputePriceCtx, StateError> {
+    if setup.b.is_multiple_of(2) {
+        return Err(StateError::ParsingInput(
+            StateErrorRecoverability::Unrecoverable,
+            anyhow!("the input is even, should be odd"),
+        ));
+    }
+
+    Ok(ComputePriceCtx {
+        msg: "the input number is odd".to_string(),
+        b: setup.b,
+    })
+}
+
+#[state_handler(
+    name = "Report",
+    label = "report_state",
+    tags("report"),
+    depends_on("setup"),
+    output = "report"
+)]
+fn report(compute: Option<ComputePriceCtx>, setup: SetupCtx) -> ReportCtx {
+    match compute {
+        Some(compute) => ReportCtx {
+            report_msg: format!("some new data reported: {}", compute.msg),
+            report_value: compute.b,
+        },
+        None => ReportCtx {
+            report_msg: format!("some new data reported: {}", setup.a),
+            report_value: setup.b,
+        },
+    }
+}
+
+#[test]
+fn test_state_handler_metadata() {
+    let state = SetupState::new();
+    assert_eq!(state.label(), Label::new("setup_state").unwrap());
+    assert_eq!(state.tags(), vec![Tag::new("setup").unwrap()]);
+    assert_eq!(state.depends_on(), vec![Tag::new("setup").unwrap()]);
+    assert_eq!(state.depends_on_strategy(), DependencyStrategy::Latest);
+
+    assert_eq!(Report::new().label(), Label::new("report_state").unwrap());
+}
+
+#[test]
+fn test_state_handler_execute() {
+    let context = wrap_context(Local::new(HashMap::from([(
+        "zero_ctx".to_string(),
+        json!(0),
+    )])));
+
+    let states: States = Arc::new([
+        Box::new(SetupState::new()),
+        Box::new(ComputePrice::new()),
+        Box::new(Report::new()),
+    ]);
+
+    let mut state_machine = StateMachine::new(states);
+    let result = state_machine.execute(context.clone());
+    assert!(result.is_ok());
+
+    let report: ReportCtx = read_value(&context, "report").unwrap();
+    assert_eq!(
+        report,
+        ReportCtx {
+            report_msg: "some new data reported: the input number is odd".to_string(),
+            report_value: 3,
+        }
+    );
+}
+
+#[test]
+fn test_state_handler_errors() {
+    let missing_input = ComputePrice::new().handler(wrap_context(Local::default()));
+    assert!(matches!(
+        missing_input,
+        Err(StateError::StorageAccess(
+            StateErrorRecoverability::Recoverable,
+            _
+        ))
+    ));
+
+    let context = wrap_context(Local::new(HashMap::from([(
+        "setup".to_string(),
+        json!(SetupCtx {
+            a: "setup_b".to_string(),
+            b: 2,
+        }),
+    )])));
+    let even_input = ComputePrice::new().handler(context);
+    assert!(matches!(
+        even_input,
+        Err(StateError::ParsingInput(
+            StateErrorRecoverability::Unrecoverable,
+            _
+        ))
+    ));
+}
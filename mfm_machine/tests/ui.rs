@@ -0,0 +1,26 @@
+#[test]
+fn test_derive_diagnostics() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/no_imports.rs");
+    t.compile_fail("tests/ui/missing_field.rs");
+    t.compile_fail("tests/ui/mistyped_field.rs");
+    t.compile_fail("tests/ui/enum_without_attribute.rs");
+    t.compile_fail("tests/ui/union.rs");
+    t.compile_fail("tests/ui/invalid_label.rs");
+    t.compile_fail("tests/ui/invalid_tag.rs");
+    t.compile_fail("tests/ui/missing_label.rs");
+    t.compile_fail("tests/ui/unknown_strategy.rs");
+    t.compile_fail("tests/ui/unknown_attribute.rs");
+    t.compile_fail("tests/ui/duplicated_label.rs");
+}
+
+#[test]
+fn test_state_handler_diagnostics() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/state_handler_async.rs");
+    t.compile_fail("tests/ui/state_handler_generic.rs");
+    t.compile_fail("tests/ui/state_handler_invalid_fn_name.rs");
+    t.compile_fail("tests/ui/state_handler_output_without_value.rs");
+    t.compile_fail("tests/ui/state_handler_unknown_attribute.rs");
+    t.compile_fail("tests/ui/state_handler_duplicated_key.rs");
+}
@@ -0,0 +1,7 @@
+use mfm_machine::StateMetadataReqs;
+
+#[derive(StateMetadataReqs)]
+#[state(label = "read_config", label = "read_config_again")]
+struct DuplicatedLabel;
+
+fn main() {}
@@ -0,0 +1,9 @@
+use mfm_machine::StateMetadataReqs;
+
+#[derive(StateMetadataReqs)]
+enum State {
+    A,
+    B,
+}
+
+fn main() {}
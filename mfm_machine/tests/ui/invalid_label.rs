@@ -0,0 +1,7 @@
+use mfm_machine::StateMetadataReqs;
+
+#[derive(StateMetadataReqs)]
+#[state(label = "Read Config")]
+struct InvalidLabel;
+
+fn main() {}
@@ -0,0 +1,7 @@
+use mfm_machine::StateMetadataReqs;
+
+#[derive(StateMetadataReqs)]
+#[state(label = "read_config", tags("setup", ""))]
+struct InvalidTag;
+
+fn main() {}
@@ -0,0 +1,10 @@
+use mfm_machine::state::{Label, Tag};
+use mfm_machine::StateMetadataReqs;
+
+#[derive(StateMetadataReqs)]
+struct MissingFields {
+    label: Label,
+    tags: Vec<Tag>,
+}
+
+fn main() {}
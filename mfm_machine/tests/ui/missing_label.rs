@@ -0,0 +1,7 @@
+use mfm_machine::StateMetadataReqs;
+
+#[derive(StateMetadataReqs)]
+#[state(tags("setup"))]
+struct MissingLabel;
+
+fn main() {}
@@ -0,0 +1,14 @@
+use mfm_machine::state::{DependencyStrategy, Label, Tag};
+use mfm_machine::StateMetadataReqs;
+
+#[derive(StateMetadataReqs)]
+struct MistypedFields {
+    label: String,
+    tags: Vec<String>,
+    depends_on: Vec<Tag>,
+    depends_on_strategy: DependencyStrategy,
+}
+
+fn main() {
+    let _ = Label::new("unused");
+}
@@ -0,0 +1,19 @@
+// the generated impl must not rely on names imported by the user
+#[derive(mfm_machine::StateMetadataReqs)]
+#[state(label = "no_imports", tags("setup"))]
+struct WithAttribute;
+
+#[derive(mfm_machine::StateMetadataReqs)]
+struct WithFields {
+    label: mfm_machine::state::Label,
+    tags: Vec<mfm_machine::state::Tag>,
+    depends_on: Vec<mfm_machine::state::Tag>,
+    depends_on_strategy: mfm_machine::state::DependencyStrategy,
+}
+
+fn main() {
+    use mfm_machine::state::StateMetadata;
+
+    let _ = WithAttribute.label();
+    let _ = |s: &WithFields| s.label();
+}
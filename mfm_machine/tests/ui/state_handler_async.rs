@@ -0,0 +1,6 @@
+use mfm_machine::state_handler;
+
+#[state_handler(label = "async_state")]
+async fn async_state() {}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use mfm_machine::state_handler;
+
+#[state_handler(label = "read_config", output = "config", output = "settings")]
+fn read_config() -> u32 {
+    1
+}
+
+fn main() {}
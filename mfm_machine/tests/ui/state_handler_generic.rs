@@ -0,0 +1,8 @@
+use mfm_machine::state_handler;
+
+#[state_handler(label = "generic_state")]
+fn generic_state<T: Default>() -> u32 {
+    0
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use mfm_machine::state_handler;
+
+// the label defaults to the function name, which must follow the label rules
+#[state_handler(tags("setup"))]
+fn step2() -> u32 {
+    2
+}
+
+fn main() {}
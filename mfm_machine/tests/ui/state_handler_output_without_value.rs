@@ -0,0 +1,6 @@
+use mfm_machine::state_handler;
+
+#[state_handler(label = "nothing", output = "nothing")]
+fn nothing() {}
+
+fn main() {}
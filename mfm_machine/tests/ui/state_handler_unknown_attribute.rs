@@ -0,0 +1,6 @@
+use mfm_machine::state_handler;
+
+#[state_handler(label = "unknown", input = "config")]
+fn unknown() {}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use mfm_machine::StateMetadataReqs;
+
+#[derive(StateMetadataReqs)]
+#[state(label = "union_state")]
+union State {
+    a: u32,
+    b: f32,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use mfm_machine::StateMetadataReqs;
+
+#[derive(StateMetadataReqs)]
+#[state(label = "read_config", retries = 3)]
+struct UnknownAttribute;
+
+fn main() {}
@@ -0,0 +1,277 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+    spanned::Spanned, AttributeArgs, Error, FnArg, GenericArgument, Generics, Ident, ItemFn, Lit,
+    LitStr, Meta, NestedMeta, Pat, PathArguments, ReturnType, Type,
+};
+
+use crate::metadata::{ensure_unset, lit_str, unknown_attribute_error, StateAttr};
+
+const HANDLER_ATTR: &str = "state_handler";
+const EXTRA_KEYS: [&str; 2] = ["name", "output"];
+
+struct HandlerAttr {
+    metadata: StateAttr,
+    name: Option<Ident>,
+    output: Option<LitStr>,
+}
+
+impl HandlerAttr {
+    fn parse(args: &AttributeArgs, item: &ItemFn) -> Result<Self, Error> {
+        // the label defaults to the function name
+        let fn_ident = &item.sig.ident;
+        let default_label = LitStr::new(&fn_ident.to_string(), fn_ident.span());
+        let (metadata, unknown) = StateAttr::parse_nested(args, Some(default_label), fn_ident)?;
+
+        let mut name = None;
+        let mut output = None;
+
+        for nested in unknown {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("name") => {
+                    ensure_unset(&name, nested, "name", HANDLER_ATTR)?;
+                    let lit = lit_str(&nv.lit)?;
+                    name = Some(lit.parse::<Ident>().map_err(|_| {
+                        Error::new_spanned(
+                            &lit,
+                            "expected a valid struct name, e.g. \"ReadConfig\"",
+                        )
+                    })?);
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("output") => {
+                    ensure_unset(&output, nested, "output", HANDLER_ATTR)?;
+                    output = Some(match &nv.lit {
+                        Lit::Str(lit) if !lit.value().is_empty() => lit.clone(),
+                        lit => {
+                            return Err(Error::new_spanned(lit, "expected a non empty context key"))
+                        }
+                    });
+                }
+                _ => return Err(unknown_attribute_error(nested, &EXTRA_KEYS)),
+            }
+        }
+
+        Ok(Self {
+            metadata,
+            name,
+            output,
+        })
+    }
+}
+
+// how the function result should be handled by the generated handler
+enum Output {
+    Nothing,
+    Value,
+    Fallible { writes: bool },
+}
+
+fn output_kind(output: &ReturnType) -> Output {
+    let ty = match output {
+        ReturnType::Default => return Output::Nothing,
+        ReturnType::Type(_, ty) => ty.as_ref(),
+    };
+
+    if is_unit(ty) {
+        return Output::Nothing;
+    }
+
+    let segment = match ty {
+        Type::Path(type_path) => type_path.path.segments.last(),
+        _ => None,
+    };
+
+    match segment {
+        Some(segment) if segment.ident == "StateResult" => Output::Fallible { writes: false },
+        Some(segment) if segment.ident == "Result" => {
+            let writes = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => {
+                    matches!(args.args.first(), Some(GenericArgument::Type(ty)) if !is_unit(ty))
+                }
+                _ => false,
+            };
+            Output::Fallible { writes }
+        }
+        _ => Output::Value,
+    }
+}
+
+fn is_unit(ty: &Type) -> bool {
+    matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty())
+}
+
+// the Option<T> inputs are read as optional context values
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+fn ensure_plain_fn(item: &ItemFn) -> Result<(), Error> {
+    let sig = &item.sig;
+
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(Error::new_spanned(
+            asyncness,
+            "state handlers cannot be async",
+        ));
+    }
+
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new_spanned(
+            variadic,
+            "state handlers cannot be variadic",
+        ));
+    }
+
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "state handlers cannot be generic",
+        ));
+    }
+
+    Ok(())
+}
+
+fn to_upper_camel_case(ident: &Ident) -> Ident {
+    let camel_case: String = ident
+        .to_string()
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect();
+
+    format_ident!("{}", camel_case, span = ident.span())
+}
+
+pub(crate) fn expand_state_handler(
+    args: &AttributeArgs,
+    item: &ItemFn,
+) -> Result<TokenStream2, Error> {
+    ensure_plain_fn(item)?;
+    let attr = HandlerAttr::parse(args, item)?;
+
+    let fn_ident = &item.sig.ident;
+    let vis = &item.vis;
+    let state_ident = attr
+        .name
+        .clone()
+        .unwrap_or_else(|| to_upper_camel_case(fn_ident));
+
+    let mut inputs = Vec::new();
+    let mut args_idents = Vec::new();
+    for input in item.sig.inputs.iter() {
+        let pat_type = match input {
+            FnArg::Typed(pat_type) => pat_type,
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(
+                    receiver,
+                    "state handlers cannot take self; use a plain function",
+                ))
+            }
+        };
+
+        let ident = match pat_type.pat.as_ref() {
+            Pat::Ident(pat_ident) => &pat_ident.ident,
+            pat => return Err(Error::new_spanned(
+                pat,
+                "expected a plain identifier; it is used as the context key to read the input from",
+            )),
+        };
+
+        let ty = &pat_type.ty;
+        let key = ident.to_string();
+        let key = key.trim_start_matches('_');
+        let read = if is_option(ty) {
+            quote_spanned! {ty.span()=>
+                let #ident: #ty = ::mfm_machine::state::context::read_optional_value(&context, #key)?;
+            }
+        } else {
+            quote_spanned! {ty.span()=>
+                let #ident: #ty = ::mfm_machine::state::context::read_value(&context, #key)?;
+            }
+        };
+
+        inputs.push(read);
+        args_idents.push(ident);
+    }
+
+    let output_key = attr
+        .output
+        .clone()
+        .unwrap_or_else(|| attr.metadata.label().clone());
+
+    let call = quote! { #fn_ident(#(#args_idents),*) };
+    let write = quote! {
+        ::mfm_machine::state::context::write_value(&context, #output_key, &output)
+    };
+
+    let body = match output_kind(&item.sig.output) {
+        Output::Nothing | Output::Fallible { writes: false } if attr.output.is_some() => {
+            return Err(Error::new_spanned(
+                attr.output,
+                "the state handler function returns nothing to be written at the output key",
+            ))
+        }
+        Output::Nothing => quote! {
+            #call;
+            ::std::result::Result::Ok(())
+        },
+        Output::Fallible { writes: false } => quote! {
+            #call.map_err(::std::convert::Into::<::mfm_machine::state::StateError>::into)
+        },
+        Output::Fallible { writes: true } => quote! {
+            let output = #call
+                .map_err(::std::convert::Into::<::mfm_machine::state::StateError>::into)?;
+            #write
+        },
+        Output::Value => quote! {
+            let output = #call;
+            #write
+        },
+    };
+
+    let metadata = attr.metadata.expand_for(&state_ident, &Generics::default());
+    let doc = format!(
+        "State generated from [`{}`] by `#[state_handler]`.",
+        fn_ident
+    );
+
+    Ok(quote! {
+        #item
+
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        #vis struct #state_ident;
+
+        impl #state_ident {
+            pub fn new() -> Self {
+                Self
+            }
+        }
+
+        #metadata
+
+        impl ::mfm_machine::state::StateHandler for #state_ident {
+            fn handler(
+                &self,
+                context: ::mfm_machine::state::context::ContextWrapper,
+            ) -> ::mfm_machine::state::StateResult {
+                #(#inputs)*
+                #body
+            }
+        }
+    })
+}
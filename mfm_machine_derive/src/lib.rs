@@ -1,33 +1,59 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, AttributeArgs, DeriveInput, ItemFn};
 
-#[proc_macro_derive(StateMetadataReqs)]
+mod handler;
+mod metadata;
+
+/// Derives `StateMetadata` for a state.
+///
+/// The metadata can be declared with a `#[state(...)]` attribute:
+///
+/// ```ignore
+/// #[derive(StateMetadataReqs)]
+/// #[state(label = "read_config", tags("setup"), depends_on("config"), strategy = "latest")]
+/// pub struct ReadConfig;
+/// ```
+///
+/// Without the attribute, the struct must have the `label: Label`, `tags: Vec<Tag>`,
+/// `depends_on: Vec<Tag>` and `depends_on_strategy: DependencyStrategy` fields,
+/// which are returned as they are.
+#[proc_macro_derive(StateMetadataReqs, attributes(state))]
 pub fn state_reqs_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let ident = &input.ident;
 
-    let expanded = quote! {
-        impl StateMetadata for #ident {
-            fn label(&self) -> Label {
-                self.label
-            }
+    let expanded = metadata::expand_derive(&input);
 
-            fn tags(&self) -> Vec<Tag> {
-                self.tags.clone()
-            }
+    TokenStream::from(expanded.unwrap_or_else(|e| e.to_compile_error()))
+}
 
-            fn depends_on(&self) -> Vec<Tag> {
-                self.depends_on.clone()
-            }
+/// Turns a plain function into a state.
+///
+/// A unit struct named after the function in UpperCamelCase (or `name = "..."`) is
+/// generated, implementing `StateMetadata` and `StateHandler`. The metadata keys are the
+/// same as in `#[state(...)]`, with the label defaulting to the function name.
+///
+/// Each parameter is deserialized from the context value stored under the parameter name
+/// (without leading underscores); `Option<T>` parameters are `None` when the value can't be
+/// read. The returned value is serialized into the context under `output = "..."`, or the
+/// label when not set. Functions returning `Result<T, E>` have `E` converted into a
+/// `StateError` with `Into`.
+///
+/// ```ignore
+/// #[state_handler(label = "compute_price", tags("computation"), depends_on("setup"), output = "compute")]
+/// fn compute_price(setup: SetupCtx) -> Result<ComputePriceCtx, StateError> {
+///     // ...
+/// }
+///
+/// let states: States = Arc::new([Box::new(ComputePrice::new())]);
+/// ```
+#[proc_macro_attribute]
+pub fn state_handler(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let item = parse_macro_input!(item as ItemFn);
 
-            fn depends_on_strategy(&self) -> DependencyStrategy {
-                self.depends_on_strategy
-            }
-        }
-    };
+    let expanded = handler::expand_state_handler(&args, &item);
 
-    TokenStream::from(expanded)
+    TokenStream::from(expanded.unwrap_or_else(|e| e.to_compile_error()))
 }
@@ -0,0 +1,384 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Generics, Ident, Lit, LitStr,
+    Meta, NestedMeta, PathArguments, Type,
+};
+
+const STATE_ATTR: &str = "state";
+
+pub(crate) fn expand_derive(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    match find_state_attr(&input.attrs)? {
+        Some(attr) => StateAttr::parse(attr)?.expand(input),
+        None => expand_from_fields(input),
+    }
+}
+
+fn find_state_attr(attrs: &[Attribute]) -> Result<Option<&Attribute>, Error> {
+    let mut state_attrs = attrs.iter().filter(|attr| attr.path.is_ident(STATE_ATTR));
+    let first = state_attrs.next();
+
+    if let Some(duplicated) = state_attrs.next() {
+        return Err(Error::new_spanned(
+            duplicated,
+            "duplicated #[state(...)] attribute; declare all the metadata in a single one",
+        ));
+    }
+
+    Ok(first)
+}
+
+// (field name, expected type, generic argument of the expected type)
+const REQUIRED_FIELDS: [(&str, &str, Option<&str>); 4] = [
+    ("label", "Label", None),
+    ("tags", "Vec", Some("Tag")),
+    ("depends_on", "Vec", Some("Tag")),
+    ("depends_on_strategy", "DependencyStrategy", None),
+];
+
+fn expand_from_fields(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "StateMetadataReqs requires a struct with named fields or a #[state(...)] attribute",
+                ))
+            }
+        },
+        Data::Enum(data) => {
+            return Err(Error::new_spanned(
+                data.enum_token,
+                "StateMetadataReqs on enums requires a #[state(...)] attribute",
+            ))
+        }
+        Data::Union(data) => {
+            return Err(Error::new_spanned(
+                data.union_token,
+                "StateMetadataReqs cannot be derived for unions",
+            ))
+        }
+    };
+
+    let mut errors: Option<Error> = None;
+    let mut push_error = |e: Error| match errors.as_mut() {
+        Some(errors) => errors.combine(e),
+        None => errors = Some(e),
+    };
+
+    for (name, ty, arg) in REQUIRED_FIELDS {
+        let field = fields
+            .named
+            .iter()
+            .find(|f| f.ident.as_ref().is_some_and(|i| i == name));
+
+        match field {
+            None => push_error(Error::new_spanned(
+                &input.ident,
+                format!(
+                    "missing field `{}: {}`; add it or declare the metadata with #[state(...)]",
+                    name,
+                    expected_type_name(ty, arg)
+                ),
+            )),
+            Some(field) if !type_matches(&field.ty, ty, arg) => push_error(Error::new_spanned(
+                &field.ty,
+                format!(
+                    "mistyped field `{}`; expected `{}`",
+                    name,
+                    expected_type_name(ty, arg)
+                ),
+            )),
+            Some(_) => {}
+        }
+    }
+
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::mfm_machine::state::StateMetadata for #ident #ty_generics #where_clause {
+            fn label(&self) -> ::mfm_machine::state::Label {
+                self.label
+            }
+
+            fn tags(&self) -> ::std::vec::Vec<::mfm_machine::state::Tag> {
+                ::std::clone::Clone::clone(&self.tags)
+            }
+
+            fn depends_on(&self) -> ::std::vec::Vec<::mfm_machine::state::Tag> {
+                ::std::clone::Clone::clone(&self.depends_on)
+            }
+
+            fn depends_on_strategy(&self) -> ::mfm_machine::state::DependencyStrategy {
+                self.depends_on_strategy
+            }
+        }
+    })
+}
+
+fn expected_type_name(ty: &str, arg: Option<&str>) -> String {
+    match arg {
+        Some(arg) => format!("{}<{}>", ty, arg),
+        None => ty.to_string(),
+    }
+}
+
+// compares only the last path segment, so both `Label` and
+// `mfm_machine::state::Label` are accepted
+fn type_matches(field_ty: &Type, ty: &str, arg: Option<&str>) -> bool {
+    let segment = match field_ty {
+        Type::Path(type_path) => match type_path.path.segments.last() {
+            Some(segment) => segment,
+            None => return false,
+        },
+        _ => return false,
+    };
+
+    if segment.ident != ty {
+        return false;
+    }
+
+    match (arg, &segment.arguments) {
+        (None, PathArguments::None) => true,
+        (Some(arg), PathArguments::AngleBracketed(args)) if args.args.len() == 1 => {
+            match args.args.first() {
+                Some(GenericArgument::Type(inner)) => type_matches(inner, arg, None),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+// mirrors mfm_machine::state::ensure_nonempty_ascii_lowercase_underscore,
+// so an invalid label or tag is caught at compile time instead of panicking at runtime
+fn ensure_nonempty_ascii_lowercase_underscore(lit: &LitStr) -> Result<(), Error> {
+    let input = lit.value();
+
+    if input.is_empty() {
+        return Err(Error::new_spanned(
+            lit,
+            "empty string; this string should be non empty, lowercase and use underscore as separator",
+        ));
+    }
+
+    if !input.chars().all(|c| c.is_ascii_lowercase() || c == '_') {
+        return Err(Error::new_spanned(
+            lit,
+            format!(
+                "invalid char in '{}'; this string should be non empty, lowercase and use underscore as separator",
+                input
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+pub(crate) struct StateAttr {
+    label: LitStr,
+    tags: Vec<LitStr>,
+    depends_on: Vec<LitStr>,
+    strategy: TokenStream2,
+}
+
+impl StateAttr {
+    fn parse(attr: &Attribute) -> Result<Self, Error> {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => {
+                return Err(Error::new_spanned(
+                    meta,
+                    "expected #[state(label = \"...\", tags(...), depends_on(...), strategy = \"...\")]",
+                ))
+            }
+        };
+
+        let (state_attr, unknown) = Self::parse_nested(&list.nested, None, attr)?;
+        match unknown.first() {
+            Some(nested) => Err(unknown_attribute_error(nested, &[])),
+            None => Ok(state_attr),
+        }
+    }
+
+    /// Parses the metadata keys, returning the nested items it doesn't know about so
+    /// other macros can extend the attribute with their own keys.
+    pub(crate) fn parse_nested<'a>(
+        nested: impl IntoIterator<Item = &'a NestedMeta>,
+        default_label: Option<LitStr>,
+        missing_label_span: &dyn ToTokens,
+    ) -> Result<(Self, Vec<&'a NestedMeta>), Error> {
+        let mut unknown = Vec::new();
+        let mut label = None;
+        let mut tags = Vec::new();
+        let mut depends_on = Vec::new();
+        let mut strategy = None;
+
+        for nested in nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("label") => {
+                    ensure_unset(&label, nested, "label", STATE_ATTR)?;
+                    let lit = lit_str(&nv.lit)?;
+                    ensure_nonempty_ascii_lowercase_underscore(&lit)?;
+                    label = Some(lit);
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("strategy") => {
+                    ensure_unset(&strategy, nested, "strategy", STATE_ATTR)?;
+                    let lit = lit_str(&nv.lit)?;
+                    match lit.value().as_str() {
+                        "latest" => {
+                            strategy = Some(quote! { ::mfm_machine::state::DependencyStrategy::Latest })
+                        }
+                        _ => {
+                            return Err(Error::new_spanned(
+                                lit,
+                                "unknown dependency strategy; expected one of: \"latest\"",
+                            ))
+                        }
+                    }
+                }
+                NestedMeta::Meta(Meta::List(l)) if l.path.is_ident("tags") => {
+                    tags.extend(tag_list(&l.nested)?);
+                }
+                NestedMeta::Meta(Meta::List(l)) if l.path.is_ident("depends_on") => {
+                    depends_on.extend(tag_list(&l.nested)?);
+                }
+                NestedMeta::Meta(meta)
+                    if ["label", "strategy", "tags", "depends_on"]
+                        .iter()
+                        .any(|name| meta.path().is_ident(name)) =>
+                {
+                    return Err(Error::new_spanned(
+                        nested,
+                        "malformed state attribute; expected `label = \"...\"`, `tags(\"...\")`, `depends_on(\"...\")` or `strategy = \"...\"`",
+                    ))
+                }
+                _ => unknown.push(nested),
+            }
+        }
+
+        let label = match (label, default_label) {
+            (Some(label), _) => Some(label),
+            (None, Some(default_label)) => {
+                ensure_nonempty_ascii_lowercase_underscore(&default_label)?;
+                Some(default_label)
+            }
+            (None, None) => None,
+        };
+        let label = label.ok_or_else(|| {
+            Error::new_spanned(
+                missing_label_span,
+                "missing label; add `label = \"...\"` to #[state(...)]",
+            )
+        })?;
+
+        let state_attr = Self {
+            label,
+            tags,
+            depends_on,
+            strategy: strategy
+                .unwrap_or_else(|| quote! { ::mfm_machine::state::DependencyStrategy::Latest }),
+        };
+
+        Ok((state_attr, unknown))
+    }
+
+    pub(crate) fn label(&self) -> &LitStr {
+        &self.label
+    }
+
+    fn expand(&self, input: &DeriveInput) -> Result<TokenStream2, Error> {
+        if let Data::Union(data) = &input.data {
+            return Err(Error::new_spanned(
+                data.union_token,
+                "StateMetadataReqs cannot be derived for unions",
+            ));
+        }
+
+        Ok(self.expand_for(&input.ident, &input.generics))
+    }
+
+    pub(crate) fn expand_for(&self, ident: &Ident, generics: &Generics) -> TokenStream2 {
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        let label = &self.label;
+        let tags = &self.tags;
+        let depends_on = &self.depends_on;
+        let strategy = &self.strategy;
+
+        quote! {
+            impl #impl_generics ::mfm_machine::state::StateMetadata for #ident #ty_generics #where_clause {
+                fn label(&self) -> ::mfm_machine::state::Label {
+                    ::mfm_machine::state::Label::new(#label).expect("label validated at compile time")
+                }
+
+                fn tags(&self) -> ::std::vec::Vec<::mfm_machine::state::Tag> {
+                    ::std::vec![#(::mfm_machine::state::Tag::new(#tags).expect("tag validated at compile time")),*]
+                }
+
+                fn depends_on(&self) -> ::std::vec::Vec<::mfm_machine::state::Tag> {
+                    ::std::vec![#(::mfm_machine::state::Tag::new(#depends_on).expect("tag validated at compile time")),*]
+                }
+
+                fn depends_on_strategy(&self) -> ::mfm_machine::state::DependencyStrategy {
+                    #strategy
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn ensure_unset<T>(
+    value: &Option<T>,
+    nested: &NestedMeta,
+    name: &str,
+    attr: &str,
+) -> Result<(), Error> {
+    match value {
+        Some(_) => Err(Error::new_spanned(
+            nested,
+            format!("duplicated `{}` in #[{}(...)]", name, attr),
+        )),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn lit_str(lit: &Lit) -> Result<LitStr, Error> {
+    match lit {
+        Lit::Str(s) => Ok(s.clone()),
+        _ => Err(Error::new_spanned(lit, "expected a string literal")),
+    }
+}
+
+fn tag_list<'a>(nested: impl IntoIterator<Item = &'a NestedMeta>) -> Result<Vec<LitStr>, Error> {
+    nested
+        .into_iter()
+        .map(|n| match n {
+            NestedMeta::Lit(lit) => {
+                let lit = lit_str(lit)?;
+                ensure_nonempty_ascii_lowercase_underscore(&lit)?;
+                Ok(lit)
+            }
+            _ => Err(Error::new_spanned(n, "expected a string literal")),
+        })
+        .collect()
+}
+
+pub(crate) fn unknown_attribute_error(nested: &NestedMeta, extra_keys: &[&str]) -> Error {
+    let keys = ["label", "tags", "depends_on", "strategy"]
+        .iter()
+        .chain(extra_keys)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Error::new_spanned(
+        nested,
+        format!("unknown state attribute; expected one of: {}", keys),
+    )
+}